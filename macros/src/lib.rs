@@ -89,7 +89,8 @@ pub fn solutions(_: TokenStream) -> TokenStream {
                 quote! {
                     (#year, #day) => {
                         let result = crate::#year_module::#day_module::solve(input);
-                        (result.0.to_string(), result.1.to_string())
+                        crate::Answers::into_answers(result)
+                            .map_err(|error| error.in_day(self.year, self.day))
                     }
                 }
             })
@@ -102,7 +103,7 @@ pub fn solutions(_: TokenStream) -> TokenStream {
         ];
 
         impl Solution {
-            pub fn run(&self, input: &str) -> (String, String) {
+            pub fn run(&self, input: &str) -> Result<(String, String), ParseError> {
                 match (self.year.0, self.day.0) {
                     #(#solution_runners)*
                     _ => unreachable!(),
@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+
+/// Which slots each item could go in, where every item needs a slot of its own. There can be
+/// more slots than items
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Candidates {
+    allowed: Vec<Vec<bool>>,
+    slots: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AssignmentError {
+    NoSolution,
+    /// Two of the solutions
+    Ambiguous(Vec<usize>, Vec<usize>),
+}
+
+impl Candidates {
+    pub(crate) fn from_fn(items: usize, slots: usize, f: impl Fn(usize, usize) -> bool) -> Self {
+        let allowed = (0..items)
+            .map(|item| (0..slots).map(|slot| f(item, slot)).collect())
+            .collect();
+        Candidates { allowed, slots }
+    }
+
+    pub(crate) fn items(&self) -> usize {
+        self.allowed.len()
+    }
+
+    pub(crate) fn is_allowed(&self, item: usize, slot: usize) -> bool {
+        self.allowed[item][slot]
+    }
+
+    /// The slot for each item, when there's exactly one way to give each item a slot
+    ///
+    /// Items with one slot left and slots with one item left get fixed until nothing changes,
+    /// and only if that gets stuck does it fall back to trying each slot for an item
+    pub(crate) fn solve_unique(&self) -> Result<Vec<usize>, AssignmentError> {
+        // Saves searching when there's obviously nothing to find
+        if self.max_matching().iter().any(Option::is_none) {
+            return Err(AssignmentError::NoSolution);
+        }
+        let mut solutions = Vec::new();
+        self.search(self.allowed.clone(), &mut solutions);
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (None, _) => Err(AssignmentError::NoSolution),
+            (Some(solution), None) => Ok(solution),
+            (Some(a), Some(b)) => Err(AssignmentError::Ambiguous(a, b)),
+        }
+    }
+
+    /// Collects up to two solutions
+    fn search(&self, allowed: Vec<Vec<bool>>, solutions: &mut Vec<Vec<usize>>) {
+        let Some(allowed) = self.propagate(allowed) else {
+            return;
+        };
+        let counts = allowed
+            .iter()
+            .map(|row| row.iter().filter(|allowed| **allowed).count())
+            .collect::<Vec<_>>();
+        let branch = (0..counts.len())
+            .filter(|item| counts[*item] > 1)
+            .min_by_key(|item| counts[*item]);
+        let Some(branch) = branch else {
+            let solution = allowed
+                .iter()
+                .map(|row| row.iter().position(|allowed| *allowed).unwrap())
+                .collect();
+            solutions.push(solution);
+            return;
+        };
+
+        for slot in (0..self.slots).filter(|slot| allowed[branch][*slot]) {
+            let mut guess = allowed.clone();
+            guess[branch] = (0..self.slots).map(|other| other == slot).collect();
+            self.search(guess, solutions);
+            if solutions.len() > 1 {
+                return;
+            }
+        }
+    }
+
+    /// Fixes what's forced, or `None` if something's left with nowhere to go
+    fn propagate(&self, mut allowed: Vec<Vec<bool>>) -> Option<Vec<Vec<bool>>> {
+        let items = allowed.len();
+        loop {
+            let mut changed = false;
+            for item in 0..items {
+                let mut slots = (0..self.slots).filter(|slot| allowed[item][*slot]);
+                let slot = match (slots.next(), slots.next()) {
+                    (None, _) => return None,
+                    (Some(slot), None) => slot,
+                    _ => continue,
+                };
+                for other in (0..items).filter(|other| *other != item) {
+                    changed |= std::mem::replace(&mut allowed[other][slot], false);
+                }
+            }
+            // With as many slots as items, every slot has to be used
+            if items == self.slots {
+                for slot in 0..self.slots {
+                    let mut holders = (0..items).filter(|item| allowed[*item][slot]);
+                    let item = match (holders.next(), holders.next()) {
+                        (None, _) => return None,
+                        (Some(item), None) => item,
+                        _ => continue,
+                    };
+                    for other in (0..self.slots).filter(|other| *other != slot) {
+                        changed |= std::mem::replace(&mut allowed[item][other], false);
+                    }
+                }
+            }
+            if !changed {
+                return Some(allowed);
+            }
+        }
+    }
+
+    /// A largest set of item to slot pairs with no slot used twice, by Hopcroft-Karp
+    pub(crate) fn max_matching(&self) -> Vec<Option<usize>> {
+        let items = self.items();
+        let mut item_slot = vec![None; items];
+        let mut slot_item = vec![None; self.slots];
+        loop {
+            // Layer free items outwards along alternating paths
+            let mut layer = vec![usize::MAX; items];
+            let mut queue = VecDeque::new();
+            for item in (0..items).filter(|item| item_slot[*item].is_none()) {
+                layer[item] = 0;
+                queue.push_back(item);
+            }
+            let mut found_free_slot = false;
+            while let Some(item) = queue.pop_front() {
+                for slot in (0..self.slots).filter(|slot| self.allowed[item][*slot]) {
+                    match slot_item[slot] {
+                        None => found_free_slot = true,
+                        Some(next) if layer[next] == usize::MAX => {
+                            layer[next] = layer[item] + 1;
+                            queue.push_back(next);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if !found_free_slot {
+                return item_slot;
+            }
+
+            let free = (0..items)
+                .filter(|item| item_slot[*item].is_none())
+                .collect::<Vec<_>>();
+            for item in free {
+                self.augment(item, &mut layer, &mut item_slot, &mut slot_item);
+            }
+        }
+    }
+
+    /// Looks for a path from `item` to a free slot that only goes down the layers, flipping
+    /// the pairs along it if there is one
+    fn augment(
+        &self,
+        item: usize,
+        layer: &mut [usize],
+        item_slot: &mut [Option<usize>],
+        slot_item: &mut [Option<usize>],
+    ) -> bool {
+        for slot in 0..self.slots {
+            if !self.allowed[item][slot] {
+                continue;
+            }
+            let extends = match slot_item[slot] {
+                None => true,
+                Some(next) => {
+                    layer[next] == layer[item] + 1
+                        && self.augment(next, layer, item_slot, slot_item)
+                }
+            };
+            if extends {
+                item_slot[item] = Some(slot);
+                slot_item[slot] = Some(item);
+                return true;
+            }
+        }
+        // Dead end, so don't come back this way in this phase
+        layer[item] = usize::MAX;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let from_rows = |rows: &[&str]| {
+            Candidates::from_fn(rows.len(), rows[0].len(), |item, slot| {
+                rows[item].as_bytes()[slot] == b'#'
+            })
+        };
+        assert_eq!(
+            from_rows(&["##.", "#..", "###"]).solve_unique(),
+            Ok(vec![1, 0, 2])
+        );
+        assert_eq!(
+            from_rows(&["##.", "##.", "##."]).solve_unique(),
+            Err(AssignmentError::NoSolution)
+        );
+        assert_eq!(
+            from_rows(&["##..", "##..", "..##"]).solve_unique(),
+            Err(AssignmentError::Ambiguous(vec![0, 1, 2], vec![0, 1, 3]))
+        );
+        assert_eq!(from_rows(&["##.", "#.#"]).max_matching().len(), 2);
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut state = 99_u64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..500 {
+            let items = (random() % 5 + 1) as usize;
+            let slots = items + (random() % 3) as usize;
+            let bits = random();
+            let candidates = Candidates::from_fn(items, slots, |item, slot| {
+                bits >> (item * slots + slot) & 3 != 0
+            });
+
+            let solutions = (0..slots)
+                .permutations(items)
+                .filter(|slots| (0..items).all(|item| candidates.is_allowed(item, slots[item])))
+                .collect::<Vec<_>>();
+            let matched = candidates.max_matching().iter().flatten().count();
+            assert_eq!(matched == items, !solutions.is_empty());
+            match candidates.solve_unique() {
+                Ok(solution) => assert_eq!(solutions, [solution]),
+                Err(AssignmentError::NoSolution) => assert!(solutions.is_empty()),
+                Err(AssignmentError::Ambiguous(a, b)) => {
+                    assert!(a != b && solutions.contains(&a) && solutions.contains(&b));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+
+pub(crate) type Cell<const D: usize> = [i64; D];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Neighbourhood {
+    /// Every cell within a Chebyshev distance of 1
+    Moore,
+    /// Every cell within a Manhattan distance of 1
+    VonNeumann,
+    /// The six neighbours of a 2D axial hex coordinate
+    Hex,
+    /// The first cell of the domain seen in each Moore direction. Dense storage only
+    LineOfSight,
+}
+
+impl Neighbourhood {
+    /// Offsets of the neighbourhood, optionally including the centre
+    ///
+    /// Moore and von Neumann offsets are ordered with the last axis varying slowest, so for
+    /// `[x, y]` cells they're in reading order
+    pub(crate) fn offsets<const D: usize>(&self, include_centre: bool) -> Vec<Cell<D>> {
+        match self {
+            Neighbourhood::Moore | Neighbourhood::LineOfSight | Neighbourhood::VonNeumann => {
+                let von_neumann = *self == Neighbourhood::VonNeumann;
+                (0..3_usize.pow(D as u32))
+                    .map(|mut i| {
+                        let mut offset = [0; D];
+                        for value in offset.iter_mut() {
+                            *value = (i % 3) as i64 - 1;
+                            i /= 3;
+                        }
+                        offset
+                    })
+                    .filter(|offset| {
+                        let distance = offset.iter().map(|v| v.abs()).sum::<i64>();
+                        match distance {
+                            0 => include_centre && *self != Neighbourhood::LineOfSight,
+                            1 => true,
+                            _ => !von_neumann,
+                        }
+                    })
+                    .collect()
+            }
+            Neighbourhood::Hex => {
+                assert_eq!(D, 2, "Hex neighbourhoods are 2D only");
+                const OFFSETS: [[i64; 2]; 6] = [[1, -1], [1, 0], [0, 1], [-1, 1], [-1, 0], [0, -1]];
+                let centre = include_centre.then_some([0, 0]);
+                centre
+                    .into_iter()
+                    .chain(OFFSETS)
+                    .map(|offset| std::array::from_fn(|i| offset[i]))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Rule {
+    /// Birth/survival by live neighbour count. Bit `n` set means a count of `n` matches
+    Totalistic { birth: u128, survival: u128 },
+    /// Next state looked up by reading the neighbourhood, centre included, as a big-endian number
+    Table(Vec<bool>),
+}
+
+impl Rule {
+    pub(crate) fn life_like(birth: &[usize], survival: &[usize]) -> Rule {
+        let mask = |counts: &[usize]| counts.iter().fold(0_u128, |acc, count| acc | 1 << count);
+        Rule::Totalistic {
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+
+    /// B3/S23
+    pub(crate) fn conway() -> Rule {
+        Rule::life_like(&[3], &[2, 3])
+    }
+
+    fn includes_centre(&self) -> bool {
+        matches!(self, Rule::Table(_))
+    }
+
+    fn next_state(&self, alive: bool, neighbours: impl Iterator<Item = bool>) -> bool {
+        match self {
+            Rule::Totalistic { birth, survival } => {
+                let count = neighbours.filter(|&v| v).count();
+                let mask = if alive { survival } else { birth };
+                mask >> count & 1 == 1
+            }
+            Rule::Table(table) => {
+                let index = neighbours.fold(0, |acc, v| acc << 1 | v as usize);
+                table[index]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Storage<const D: usize> {
+    /// Cells whose state differs from the background
+    Sparse(HashSet<Cell<D>>),
+    Dense(DenseGrid<D>),
+}
+
+#[derive(Debug, Clone)]
+struct DenseGrid<const D: usize> {
+    size: [usize; D],
+    alive: Vec<bool>,
+    /// Cells outside of the domain are always dead
+    domain: Vec<bool>,
+    /// `None` for neighbours outside of the grid, which are always dead
+    neighbours: Vec<Vec<Option<usize>>>,
+}
+
+impl<const D: usize> DenseGrid<D> {
+    fn index(&self, cell: &Cell<D>) -> Option<usize> {
+        let mut index = 0;
+        let mut stride = 1;
+        for (&value, &size) in cell.iter().zip(self.size.iter()) {
+            if value < 0 || value as usize >= size {
+                return None;
+            }
+            index += value as usize * stride;
+            stride *= size;
+        }
+        Some(index)
+    }
+
+    fn cell(&self, mut index: usize) -> Cell<D> {
+        let mut cell = [0; D];
+        for (value, &size) in cell.iter_mut().zip(self.size.iter()) {
+            *value = (index % size) as i64;
+            index /= size;
+        }
+        cell
+    }
+
+    fn add(cell: &Cell<D>, offset: &Cell<D>, distance: i64) -> Cell<D> {
+        std::array::from_fn(|i| cell[i] + offset[i] * distance)
+    }
+
+    fn build_neighbours(&mut self, neighbourhood: Neighbourhood, offsets: &[Cell<D>]) {
+        self.neighbours = (0..self.alive.len())
+            .map(|index| {
+                let cell = self.cell(index);
+                offsets
+                    .iter()
+                    .map(|offset| {
+                        if neighbourhood != Neighbourhood::LineOfSight {
+                            return self.index(&Self::add(&cell, offset, 1));
+                        }
+                        (1..)
+                            .map(|distance| self.index(&Self::add(&cell, offset, distance)))
+                            .find(|index| index.is_none_or(|index| self.domain[index]))
+                            .unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+/// A cellular automaton over a `D`-dimensional lattice
+///
+/// Sparse storage is unbounded and only tracks cells that differ from the background, which can
+/// itself flip each generation. Dense storage is a fixed box with a domain mask, and everything
+/// outside of the domain is always dead
+#[derive(Debug, Clone)]
+pub(crate) struct Automaton<const D: usize> {
+    rule: Rule,
+    offsets: Vec<Cell<D>>,
+    storage: Storage<D>,
+    background: bool,
+    generation: usize,
+}
+
+impl<const D: usize> Automaton<D> {
+    pub(crate) fn sparse(
+        cells: impl IntoIterator<Item = Cell<D>>,
+        neighbourhood: Neighbourhood,
+        rule: Rule,
+    ) -> Automaton<D> {
+        assert_ne!(
+            neighbourhood,
+            Neighbourhood::LineOfSight,
+            "Line of sight needs a bounded domain"
+        );
+        Automaton {
+            offsets: neighbourhood.offsets(rule.includes_centre()),
+            rule,
+            storage: Storage::Sparse(cells.into_iter().collect()),
+            background: false,
+            generation: 0,
+        }
+    }
+
+    /// A dense box of `size` with its origin at zero. Only cells matching `domain` take part
+    pub(crate) fn dense(
+        size: [usize; D],
+        domain: impl Fn(&Cell<D>) -> bool,
+        neighbourhood: Neighbourhood,
+        rule: Rule,
+    ) -> Automaton<D> {
+        let offsets = neighbourhood.offsets(rule.includes_centre());
+        let len = size.iter().product();
+        let mut grid = DenseGrid {
+            size,
+            alive: vec![false; len],
+            domain: Vec::new(),
+            neighbours: Vec::new(),
+        };
+        grid.domain = (0..len).map(|index| domain(&grid.cell(index))).collect();
+        grid.build_neighbours(neighbourhood, &offsets);
+        Automaton {
+            rule,
+            offsets,
+            storage: Storage::Dense(grid),
+            background: false,
+            generation: 0,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn generation(&self) -> usize {
+        self.generation
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn background(&self) -> bool {
+        self.background
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_alive(&self, cell: &Cell<D>) -> bool {
+        match &self.storage {
+            Storage::Sparse(cells) => cells.contains(cell) != self.background,
+            Storage::Dense(grid) => grid.index(cell).is_some_and(|index| grid.alive[index]),
+        }
+    }
+
+    pub(crate) fn set(&mut self, cell: Cell<D>, alive: bool) {
+        match &mut self.storage {
+            Storage::Sparse(cells) => {
+                if alive != self.background {
+                    cells.insert(cell);
+                } else {
+                    cells.remove(&cell);
+                }
+            }
+            Storage::Dense(grid) => {
+                let index = grid.index(&cell).expect("Cell outside of dense grid");
+                grid.alive[index] = alive && grid.domain[index];
+            }
+        }
+    }
+
+    /// Number of live cells. Panics if the background is alive, as there are infinitely many
+    pub(crate) fn alive_count(&self) -> usize {
+        assert!(!self.background, "Infinitely many cells alive");
+        match &self.storage {
+            Storage::Sparse(cells) => cells.len(),
+            Storage::Dense(grid) => grid.alive.iter().filter(|&&v| v).count(),
+        }
+    }
+
+    /// Advances one generation, returning whether anything changed
+    pub(crate) fn step(&mut self) -> bool {
+        let background = self.background;
+        let changed = match &mut self.storage {
+            Storage::Sparse(cells) => {
+                let next_background = self
+                    .rule
+                    .next_state(background, (0..self.offsets.len()).map(|_| background));
+                let state = |cell: &Cell<D>| cells.contains(cell) != background;
+                let candidates = cells
+                    .iter()
+                    .flat_map(|cell| {
+                        std::iter::once(*cell).chain(
+                            self.offsets
+                                .iter()
+                                .map(|offset| DenseGrid::add(cell, offset, 1)),
+                        )
+                    })
+                    .collect::<HashSet<_>>();
+                let next = candidates
+                    .into_par_iter()
+                    .filter(|cell| {
+                        let neighbours = self
+                            .offsets
+                            .iter()
+                            .map(|offset| state(&DenseGrid::add(cell, offset, 1)));
+                        self.rule.next_state(state(cell), neighbours) != next_background
+                    })
+                    .collect::<HashSet<_>>();
+                let changed = next != *cells || next_background != background;
+                *cells = next;
+                self.background = next_background;
+                changed
+            }
+            Storage::Dense(grid) => {
+                let next = (0..grid.alive.len())
+                    .into_par_iter()
+                    .map(|index| {
+                        if !grid.domain[index] {
+                            return false;
+                        }
+                        let neighbours = grid.neighbours[index]
+                            .iter()
+                            .map(|neighbour| neighbour.is_some_and(|i| grid.alive[i]));
+                        self.rule.next_state(grid.alive[index], neighbours)
+                    })
+                    .collect::<Vec<_>>();
+                let changed = next != grid.alive;
+                grid.alive = next;
+                changed
+            }
+        };
+        self.generation += 1;
+        changed
+    }
+
+    pub(crate) fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Steps until a generation leaves everything unchanged, returning the generation reached
+    pub(crate) fn run_until_stable(&mut self) -> usize {
+        while self.step() {}
+        self.generation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glider() {
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        let mut automaton = Automaton::sparse(glider, Neighbourhood::Moore, Rule::conway());
+        automaton.run(4);
+        assert_eq!(automaton.alive_count(), 5);
+        assert!(glider
+            .iter()
+            .all(|&[x, y]| automaton.is_alive(&[x + 1, y + 1])));
+    }
+
+    #[test]
+    fn test_dense_matches_sparse() {
+        let cells = [[1, 1], [2, 1], [3, 1], [3, 2], [2, 4], [4, 4]];
+        let mut sparse = Automaton::sparse(cells, Neighbourhood::Moore, Rule::conway());
+        let mut dense = Automaton::dense([50, 50], |_| true, Neighbourhood::Moore, Rule::conway());
+        for [x, y] in cells {
+            dense.set([x + 20, y + 20], true);
+        }
+        for _ in 0..10 {
+            sparse.step();
+            dense.step();
+            assert_eq!(sparse.alive_count(), dense.alive_count());
+        }
+    }
+
+    #[test]
+    fn test_stable() {
+        let block = [[0, 0], [1, 0], [0, 1], [1, 1]];
+        let mut automaton = Automaton::sparse(block, Neighbourhood::Moore, Rule::conway());
+        assert_eq!(automaton.run_until_stable(), 1);
+    }
+
+    #[test]
+    fn test_flipping_background() {
+        // Every dead cell surrounded by dead cells is born, and every live one dies
+        let mut table = vec![false; 512];
+        table[0] = true;
+        let mut automaton = Automaton::sparse([[0, 0]], Neighbourhood::Moore, Rule::Table(table));
+        automaton.step();
+        assert!(automaton.background());
+        automaton.step();
+        assert!(!automaton.background());
+    }
+}
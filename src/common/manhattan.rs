@@ -0,0 +1,356 @@
+use super::{Vector2, Vector3};
+
+/// Turns a point 45°, to `u = x + y` and `v = x - y`. Manhattan distance becomes the larger
+/// of the differences in `u` and `v`, so diamonds become squares
+pub(crate) fn rotate(point: Vector2) -> Vector2 {
+    Vector2::new(point.x + point.y, point.x - point.y)
+}
+
+/// Undoes `rotate`. Only points where `u` and `v` are both even or both odd came from one
+#[allow(dead_code)]
+pub(crate) fn unrotate(point: Vector2) -> Option<Vector2> {
+    let (u, v) = (point.x, point.y);
+    let same_parity = (u - v).rem_euclid(2) == 0;
+    same_parity.then(|| Vector2::new((u + v) / 2, (u - v) / 2))
+}
+
+/// Every point within `radius` of `center`, by Manhattan distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Diamond {
+    pub(crate) center: Vector2,
+    pub(crate) radius: i64,
+}
+
+/// Inclusive on all sides, and empty if `min` is past `max` either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Rect {
+    pub(crate) min: Vector2,
+    pub(crate) max: Vector2,
+}
+
+/// A set of points in rotated coordinates, as rectangles that don't overlap
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Region {
+    rects: Vec<Rect>,
+}
+
+impl Diamond {
+    pub(crate) fn new(center: Vector2, radius: i64) -> Diamond {
+        Diamond { center, radius }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn contains(&self, point: Vector2) -> bool {
+        self.center.manhattan_distance(point) <= self.radius
+    }
+
+    /// The first and last `x` covered in row `y`
+    pub(crate) fn row(&self, y: i64) -> Option<(i64, i64)> {
+        let width = self.radius - (self.center.y - y).abs();
+        (width >= 0).then_some((self.center.x - width, self.center.x + width))
+    }
+
+    /// This diamond as a square in rotated coordinates
+    pub(crate) fn rotated(&self) -> Rect {
+        let center = rotate(self.center);
+        let offset = Vector2::new(self.radius, self.radius);
+        Rect {
+            min: center - offset,
+            max: center + offset,
+        }
+    }
+}
+
+impl Rect {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn contains(&self, point: Vector2) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    pub(crate) fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect {
+            min: Vector2::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y)),
+            max: Vector2::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y)),
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// What's left of this after taking away `other`, in up to four pieces
+    pub(crate) fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let Some(overlap) = self.intersection(other) else {
+            return vec![*self];
+        };
+        let (min, max) = (self.min, self.max);
+        [
+            // Full height strips either side of the overlap
+            Rect {
+                min,
+                max: Vector2::new(overlap.min.x - 1, max.y),
+            },
+            Rect {
+                min: Vector2::new(overlap.max.x + 1, min.y),
+                max,
+            },
+            // Then above and below it
+            Rect {
+                min: Vector2::new(overlap.min.x, min.y),
+                max: Vector2::new(overlap.max.x, overlap.min.y - 1),
+            },
+            Rect {
+                min: Vector2::new(overlap.min.x, overlap.max.y + 1),
+                max: Vector2::new(overlap.max.x, max.y),
+            },
+        ]
+        .into_iter()
+        .filter(|rect| !rect.is_empty())
+        .collect()
+    }
+
+    /// The smallest rectangle in rotated coordinates holding all of this one
+    pub(crate) fn rotated_bounds(&self) -> Rect {
+        Rect {
+            min: Vector2::new(self.min.x + self.min.y, self.min.x - self.max.y),
+            max: Vector2::new(self.max.x + self.max.y, self.max.x - self.min.y),
+        }
+    }
+
+    /// How many real points this holds, taking it to be in rotated coordinates
+    #[allow(dead_code)]
+    pub(crate) fn rotated_area(&self) -> i64 {
+        let evens = |min: i64, max: i64| max.div_euclid(2) - (min - 1).div_euclid(2);
+        let (even_u, even_v) = (evens(self.min.x, self.max.x), evens(self.min.y, self.max.y));
+        let odd_u = self.max.x - self.min.x + 1 - even_u;
+        let odd_v = self.max.y - self.min.y + 1 - even_v;
+        even_u * even_v + odd_u * odd_v
+    }
+}
+
+impl From<Rect> for Region {
+    fn from(rect: Rect) -> Self {
+        Region {
+            rects: [rect].into_iter().filter(|rect| !rect.is_empty()).collect(),
+        }
+    }
+}
+
+impl Region {
+    /// Everything covered by any of `diamonds`
+    #[allow(dead_code)]
+    pub(crate) fn from_diamonds(diamonds: &[Diamond]) -> Region {
+        diamonds.iter().fold(Region::default(), |region, diamond| {
+            region.union(&Region::from(diamond.rotated()))
+        })
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn union(&self, other: &Region) -> Region {
+        let mut rects = self.rects.clone();
+        rects.extend(other.subtract(self).rects);
+        Region { rects }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn intersection(&self, other: &Region) -> Region {
+        let rects = self
+            .rects
+            .iter()
+            .flat_map(|a| other.rects.iter().filter_map(|b| a.intersection(b)))
+            .collect();
+        Region { rects }
+    }
+
+    pub(crate) fn subtract(&self, other: &Region) -> Region {
+        let rects = other.rects.iter().fold(self.rects.clone(), |rects, taken| {
+            rects.iter().flat_map(|rect| rect.subtract(taken)).collect()
+        });
+        Region { rects }
+    }
+
+    /// How many real points are in the region
+    #[allow(dead_code)]
+    pub(crate) fn area(&self) -> i64 {
+        self.rects.iter().map(Rect::rotated_area).sum()
+    }
+
+    /// Every real point of the region inside `bounds`, which is in ordinary coordinates.
+    /// Each rectangle only visits the columns where it overlaps `bounds`, and each of those
+    /// has at least one point in it, so this takes time in proportion to what's taken from it
+    pub(crate) fn points_within(&self, bounds: &Rect) -> impl Iterator<Item = Vector2> + '_ {
+        let bounds = *bounds;
+        self.rects.iter().flat_map(move |rect| {
+            let (u0, u1, v0, v1) = (rect.min.x, rect.max.x, rect.min.y, rect.max.y);
+            let (y0, y1) = (bounds.min.y, bounds.max.y);
+            // Where the lowest `y` a column allows is still at most the highest
+            let first = [bounds.min.x, v0 + y0, u0 - y1, -(-u0 - v0).div_euclid(2)];
+            let last = [bounds.max.x, u1 - y0, v1 + y1, (u1 + v1).div_euclid(2)];
+            let (first, last) = (first.into_iter().max(), last.into_iter().min());
+            (first.unwrap()..=last.unwrap()).flat_map(move |x| {
+                let low = y0.max(u0 - x).max(x - v1);
+                let high = y1.min(u1 - x).min(x - v0);
+                (low..=high).map(move |y| Vector2::new(x, y))
+            })
+        })
+    }
+}
+
+/// Every point within `radius` of `center` in 3D, by Manhattan distance
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Octahedron {
+    pub(crate) center: Vector3,
+    pub(crate) radius: i64,
+}
+
+/// Ranges on the four axes `x + y + z`, `x + y - z`, `x - y + z` and `-x + y + z`. The
+/// Manhattan distance between two points is the largest difference along these, so an
+/// octahedron is all four ranges having the same width, and octahedra intersect to another
+/// one of these
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct OctaBox {
+    pub(crate) ranges: [(i64, i64); 4],
+}
+
+/// `point` on the axes `OctaBox` uses
+#[allow(dead_code)]
+pub(crate) fn rotate_3d(point: Vector3) -> [i64; 4] {
+    let Vector3 { x, y, z } = point;
+    [x + y + z, x + y - z, x - y + z, -x + y + z]
+}
+
+#[allow(dead_code)]
+impl Octahedron {
+    pub(crate) fn new(center: Vector3, radius: i64) -> Octahedron {
+        Octahedron { center, radius }
+    }
+
+    pub(crate) fn contains(&self, point: Vector3) -> bool {
+        let (center, radius) = (self.center, self.radius);
+        (point.x - center.x).abs() + (point.y - center.y).abs() + (point.z - center.z).abs()
+            <= radius
+    }
+
+    pub(crate) fn rotated(&self) -> OctaBox {
+        OctaBox {
+            ranges: rotate_3d(self.center).map(|c| (c - self.radius, c + self.radius)),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl OctaBox {
+    pub(crate) fn contains(&self, point: Vector3) -> bool {
+        let rotated = rotate_3d(point);
+        (0..4).all(|axis| (self.ranges[axis].0..=self.ranges[axis].1).contains(&rotated[axis]))
+    }
+
+    pub(crate) fn intersection(&self, other: &OctaBox) -> OctaBox {
+        let mut ranges = self.ranges;
+        for (range, other) in ranges.iter_mut().zip(other.ranges.iter()) {
+            *range = (range.0.max(other.0), range.1.min(other.1));
+        }
+        OctaBox { ranges }
+    }
+
+    /// Whether there's any space inside, not necessarily at whole numbers. The first axis is
+    /// the sum of the other three, so it has to meet the range they can add up to
+    pub(crate) fn is_empty(&self) -> bool {
+        let [first, rest @ ..] = self.ranges;
+        let low = rest.iter().map(|range| range.0).sum::<i64>();
+        let high = rest.iter().map(|range| range.1).sum::<i64>();
+        self.ranges.iter().any(|range| range.0 > range.1) || first.0 > high || low > first.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diamonds() {
+        let diamonds = [
+            Diamond::new(Vector2::new(0, 0), 3),
+            Diamond::new(Vector2::new(4, 1), 2),
+            Diamond::new(Vector2::new(1, -3), 0),
+        ];
+        let region = Region::from_diamonds(&diamonds);
+        let overlap =
+            Region::from(diamonds[0].rotated()).intersection(&Region::from(diamonds[1].rotated()));
+        let (mut covered, mut shared) = (0, 0);
+        for x in -6..=8 {
+            for y in -6..=6 {
+                let point = Vector2::new(x, y);
+                let inside = diamonds.iter().filter(|d| d.contains(point)).count();
+                covered += i64::from(inside > 0);
+                shared += i64::from(diamonds[0].contains(point) && diamonds[1].contains(point));
+                let found = region.rects().iter().filter(|r| r.contains(rotate(point)));
+                assert_eq!(found.count(), usize::from(inside > 0));
+                assert_eq!(unrotate(rotate(point)), Some(point));
+            }
+        }
+        assert_eq!(region.area(), covered);
+        assert_eq!(overlap.area(), shared);
+        assert_eq!(unrotate(Vector2::new(1, 0)), None);
+        assert_eq!(diamonds[0].row(2), Some((-1, 1)));
+        assert_eq!(diamonds[0].row(4), None);
+    }
+
+    #[test]
+    fn test_points_within() {
+        let bounds = Rect {
+            min: Vector2::new(0, 0),
+            max: Vector2::new(6, 5),
+        };
+        let diamonds = [
+            Diamond::new(Vector2::new(1, 1), 2),
+            Diamond::new(Vector2::new(5, 4), 3),
+            Diamond::new(Vector2::new(1, 5), 1),
+        ];
+        let uncovered = diamonds
+            .iter()
+            .fold(Region::from(bounds.rotated_bounds()), |region, diamond| {
+                region.subtract(&Region::from(diamond.rotated()))
+            });
+        let mut points = uncovered.points_within(&bounds).collect::<Vec<_>>();
+        points.sort_by_key(|p| (p.x, p.y));
+
+        let mut expected = Vec::new();
+        for x in 0..=6 {
+            for y in 0..=5 {
+                let point = Vector2::new(x, y);
+                if !diamonds.iter().any(|d| d.contains(point)) {
+                    expected.push(point);
+                }
+            }
+        }
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn test_octahedra() {
+        let a = Octahedron::new(Vector3::new(0, 0, 0), 4);
+        let b = Octahedron::new(Vector3::new(3, 2, -1), 3);
+        let far = Octahedron::new(Vector3::new(9, 0, 0), 4);
+        let both = a.rotated().intersection(&b.rotated());
+        for x in -5..=7 {
+            for y in -5..=6 {
+                for z in -5..=5 {
+                    let point = Vector3::new(x, y, z);
+                    assert_eq!(a.rotated().contains(point), a.contains(point));
+                    assert_eq!(both.contains(point), a.contains(point) && b.contains(point));
+                }
+            }
+        }
+        assert!(!both.is_empty());
+        assert!(a.rotated().intersection(&far.rotated()).is_empty());
+    }
+}
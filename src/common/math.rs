@@ -1,16 +1,146 @@
-use std::iter::Product;
-
 use num::Integer;
 
-pub(crate) fn chinese_remainder<T: Integer + Copy + Product>(a: &[T], m: &[T]) -> Option<T> {
-    let big_n = m.iter().copied().product::<T>();
-    a.iter()
-        .zip(m.iter())
-        .try_fold(T::zero(), |acc, (&a_i, &m_i)| {
-            let p = big_n / m_i;
-            Some(acc + a_i * mod_inv(p, m_i)? * p)
-        })
-        .map(|r| (r % big_n + big_n) % big_n)
+/// The numbers `x` with `x ≡ residue (mod modulus)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Congruence {
+    residue: i128,
+    modulus: i128,
+}
+
+/// Times something happens in a sequence that eventually repeats: the hits before `cycle_start`,
+/// then the hits of one cycle, which recur every `period`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EventuallyPeriodic {
+    prefix_hits: Vec<i128>,
+    cycle_start: i128,
+    period: i128,
+    cycle_hits: Vec<i128>,
+}
+
+impl Congruence {
+    pub(crate) fn new(residue: i128, modulus: i128) -> Congruence {
+        assert!(modulus > 0, "Moduli have to be positive");
+        Congruence {
+            residue: residue.rem_euclid(modulus),
+            modulus,
+        }
+    }
+
+    /// The smallest non-negative solution
+    pub(crate) fn residue(&self) -> i128 {
+        self.residue
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn modulus(&self) -> i128 {
+        self.modulus
+    }
+
+    /// The numbers satisfying both, which needn't have coprime moduli. `None` if there aren't
+    /// any, or if working them out overflows an i128
+    pub(crate) fn merge(self, other: Congruence) -> Option<Congruence> {
+        let g = self.modulus.gcd(&other.modulus);
+        let difference = other.residue - self.residue;
+        if difference % g != 0 {
+            return None;
+        }
+        // self.residue + self.modulus * k, for the k that also satisfies other
+        let (m1, m2) = (self.modulus / g, other.modulus / g);
+        let k = (difference / g)
+            .rem_euclid(m2)
+            .checked_mul(mod_inv(m1.rem_euclid(m2), m2)?)?
+            .rem_euclid(m2);
+        let modulus = self.modulus.checked_mul(m2)?;
+        let residue = self.modulus.checked_mul(k)?.checked_add(self.residue)?;
+        Some(Congruence::new(residue, modulus))
+    }
+
+    /// The smallest solution that's at least `min`
+    pub(crate) fn first_from(&self, min: i128) -> i128 {
+        min + (self.residue - min).rem_euclid(self.modulus)
+    }
+}
+
+/// The numbers satisfying every congruence, if there are any
+pub(crate) fn solve_congruences(
+    congruences: impl IntoIterator<Item = Congruence>,
+) -> Option<Congruence> {
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(0, 1), Congruence::merge)
+}
+
+impl EventuallyPeriodic {
+    /// `cycle_hits` have to fall in the first cycle, from `cycle_start` up to a period later
+    pub(crate) fn new(
+        prefix_hits: Vec<i128>,
+        cycle_start: i128,
+        period: i128,
+        cycle_hits: Vec<i128>,
+    ) -> EventuallyPeriodic {
+        assert!(prefix_hits.iter().all(|hit| *hit < cycle_start));
+        assert!(cycle_hits
+            .iter()
+            .all(|hit| (cycle_start..cycle_start + period).contains(hit)));
+        EventuallyPeriodic {
+            prefix_hits,
+            cycle_start,
+            period,
+            cycle_hits,
+        }
+    }
+
+    pub(crate) fn hits_at(&self, time: i128) -> bool {
+        if time < self.cycle_start {
+            return self.prefix_hits.contains(&time);
+        }
+        let time = self.cycle_start + (time - self.cycle_start) % self.period;
+        self.cycle_hits.contains(&time)
+    }
+
+    /// The first time every sequence hits at once
+    ///
+    /// Before every sequence is cycling, the times are checked directly. After that, each
+    /// choice of one cycle hit per sequence is a set of congruences
+    pub(crate) fn first_common_hit(sequences: &[EventuallyPeriodic]) -> Option<i128> {
+        let (first, rest) = sequences.split_first()?;
+        let settled = sequences.iter().map(|s| s.cycle_start).max().unwrap();
+
+        let early = first.prefix_hits.iter().copied().chain(
+            (0..)
+                .map(|cycle| first.cycle_start + cycle * first.period)
+                .take_while(|start| *start < settled)
+                .flat_map(|start| {
+                    first
+                        .cycle_hits
+                        .iter()
+                        .map(move |hit| hit - first.cycle_start + start)
+                }),
+        );
+        if let Some(hit) = early
+            .filter(|time| *time < settled && rest.iter().all(|s| s.hits_at(*time)))
+            .min()
+        {
+            return Some(hit);
+        }
+
+        let mut combinations = vec![Congruence::new(0, 1)];
+        for sequence in sequences {
+            combinations = combinations
+                .iter()
+                .flat_map(|combined| {
+                    sequence
+                        .cycle_hits
+                        .iter()
+                        .filter_map(|hit| combined.merge(Congruence::new(*hit, sequence.period)))
+                })
+                .collect();
+        }
+        combinations
+            .iter()
+            .map(|combined| combined.first_from(settled))
+            .min()
+    }
 }
 
 #[allow(clippy::many_single_char_names)]
@@ -37,7 +167,46 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_chinese() {
-        assert_eq!(chinese_remainder(&[2, 3, 2], &[3, 5, 7]), Some(23))
+    fn test_congruences() {
+        let solve = |pairs: &[(i128, i128)]| {
+            solve_congruences(pairs.iter().map(|(r, m)| Congruence::new(*r, *m)))
+                .map(|c| (c.residue(), c.modulus()))
+        };
+        assert_eq!(solve(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // Moduli sharing factors
+        assert_eq!(solve(&[(3, 4), (5, 6)]), Some((11, 12)));
+        assert_eq!(solve(&[(3, 4), (4, 6)]), None);
+        // Big enough that the product of the moduli overflows a u64
+        let primes = [1_000_000_007, 998_244_353, 1_000_000_009];
+        let result = solve(&primes.map(|p| (p - 1, p))).unwrap();
+        assert_eq!(result.0, result.1 - 1);
+        // Coprime, but with a combined modulus too big for an i128
+        assert_eq!(
+            solve(&[(1, 1 << 61), (1, 3_i128.pow(38)), (1, 5_i128.pow(26))]),
+            None
+        );
+        assert_eq!(Congruence::new(-1, 5).first_from(12), 14);
+    }
+
+    #[test]
+    fn test_eventually_periodic() {
+        // Hits at 1, then 4 and 6 every 5 from 3
+        let a = EventuallyPeriodic::new(vec![1], 3, 5, vec![4, 6]);
+        assert!([1, 4, 6, 9, 11].iter().all(|time| a.hits_at(*time)));
+        assert!(![0, 2, 3, 5, 7].iter().any(|time| a.hits_at(*time)));
+
+        let b = EventuallyPeriodic::new(vec![1], 2, 3, vec![2]);
+        assert_eq!(
+            EventuallyPeriodic::first_common_hit(&[a.clone(), b]),
+            Some(1)
+        );
+        let c = EventuallyPeriodic::new(vec![], 0, 4, vec![3]);
+        // 11, 19, ... meets 4, 6, 9, 11
+        assert_eq!(
+            EventuallyPeriodic::first_common_hit(&[a.clone(), c]),
+            Some(11)
+        );
+        let d = EventuallyPeriodic::new(vec![], 0, 10, vec![0, 5]);
+        assert_eq!(EventuallyPeriodic::first_common_hit(&[a, d]), None);
     }
 }
@@ -1,11 +1,29 @@
+mod assignment;
+mod automaton;
+mod manhattan;
 mod math;
 mod md5;
+mod nested;
+mod parse_error;
+mod permutation;
+mod ring;
+mod symmetry;
+mod tsp;
 mod vector2;
 mod vector3;
 mod winnow;
 
+pub(crate) use assignment::*;
+pub(crate) use automaton::*;
+pub(crate) use manhattan::*;
 pub(crate) use math::*;
 pub(crate) use md5::*;
+pub(crate) use nested::*;
+pub use parse_error::ParseError;
+pub(crate) use permutation::*;
+pub(crate) use ring::*;
+pub(crate) use symmetry::*;
+pub(crate) use tsp::*;
 pub(crate) use vector2::*;
 pub(crate) use vector3::*;
 pub(crate) use winnow::*;
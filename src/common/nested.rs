@@ -0,0 +1,324 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{Display, Write},
+};
+
+use winnow::{
+    ascii::{dec_int, multispace0},
+    combinator::{alt, delimited, separated, separated_pair},
+    prelude::*,
+    token::take_till,
+};
+
+/// A JSON-like value that borrows its strings from the input where it can
+///
+/// `Ord` is structural, so it's a total order that agrees with `Eq`. The distress signal
+/// ordering of 2022 day 13 is `signal_cmp`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Nested<'a> {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(Cow<'a, str>),
+    List(Vec<Nested<'a>>),
+    Object(Vec<(Cow<'a, str>, Nested<'a>)>),
+}
+
+impl<'a> Nested<'a> {
+    pub(crate) fn parse(input: &'a str) -> Result<Nested<'a>, ()> {
+        delimited(multispace0, parse_nested, multispace0)
+            .parse(input)
+            .map_err(|_| ())
+    }
+
+    /// Sum of every number, skipping anything `prune` returns true for
+    pub(crate) fn sum_numbers(&self, prune: &impl Fn(&Nested) -> bool) -> i64 {
+        if prune(self) {
+            return 0;
+        }
+        match self {
+            Nested::Number(value) => *value,
+            Nested::List(values) => values.iter().map(|v| v.sum_numbers(prune)).sum(),
+            Nested::Object(entries) => entries.iter().map(|(_, v)| v.sum_numbers(prune)).sum(),
+            Nested::Null | Nested::Bool(_) | Nested::String(_) => 0,
+        }
+    }
+
+    /// Whether this is an object with `value` as one of its values. Keys aren't checked
+    pub(crate) fn object_contains(&self, value: &Nested) -> bool {
+        match self {
+            Nested::Object(entries) => entries.iter().any(|(_, v)| v == value),
+            _ => false,
+        }
+    }
+
+    /// 1-based positions the `dividers` would end up at if added to `packets` and sorted by
+    /// `signal_cmp`
+    pub(crate) fn divider_positions(packets: &[Nested], dividers: &[Nested]) -> Vec<usize> {
+        let all = packets.iter().chain(dividers).collect::<Vec<_>>();
+        let mut order = (0..all.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| all[*a].signal_cmp(all[*b]));
+        // Found by index, as a packet can equal a divider
+        let mut positions = vec![0; all.len()];
+        for (position, index) in order.into_iter().enumerate() {
+            positions[index] = position + 1;
+        }
+        positions.split_off(packets.len())
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Nested::Null => 0,
+            Nested::Bool(_) => 1,
+            Nested::Number(_) => 2,
+            Nested::String(_) => 3,
+            Nested::List(_) => 4,
+            Nested::Object(_) => 5,
+        }
+    }
+
+    /// Like `rank`, but numbers and lists are one kind, as the signal ordering compares them
+    fn signal_rank(&self) -> u8 {
+        match self {
+            Nested::Number(_) => Nested::List(Vec::new()).rank(),
+            _ => self.rank(),
+        }
+    }
+
+    /// The distress signal ordering, where a number compared with a list acts like a single
+    /// item list. It's a total preorder, with values like `1` and `[1]` equal. Anything that
+    /// isn't a number or list goes by kind, then structurally
+    pub(crate) fn signal_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Nested::Number(a), Nested::Number(b)) => a.cmp(b),
+            (Nested::List(a), Nested::List(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.signal_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Nested::Number(_), Nested::List(b)) => match b.first() {
+                Some(first) => self.signal_cmp(first).then(1.cmp(&b.len())),
+                None => Ordering::Greater,
+            },
+            (Nested::List(_), Nested::Number(_)) => other.signal_cmp(self).reverse(),
+            _ => self
+                .signal_rank()
+                .cmp(&other.signal_rank())
+                .then_with(|| self.structural_cmp(other)),
+        }
+    }
+
+    /// By kind, then by value, with lists and objects going item by item
+    fn structural_cmp(&self, other: &Self) -> Ordering {
+        let by_items = |a: &[Nested], b: &[Nested]| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.structural_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        };
+        match (self, other) {
+            (Nested::Bool(a), Nested::Bool(b)) => a.cmp(b),
+            (Nested::Number(a), Nested::Number(b)) => a.cmp(b),
+            (Nested::String(a), Nested::String(b)) => a.cmp(b),
+            (Nested::List(a), Nested::List(b)) => by_items(a, b),
+            (Nested::Object(a), Nested::Object(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|((a_key, a), (b_key, b))| a_key.cmp(b_key).then_with(|| a.structural_cmp(b)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Ord for Nested<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.structural_cmp(other)
+    }
+}
+
+impl PartialOrd for Nested<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Fails on numbers that aren't integers fitting in an `i64`
+impl TryFrom<&serde_json::Value> for Nested<'static> {
+    type Error = ();
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            serde_json::Value::Null => Nested::Null,
+            serde_json::Value::Bool(value) => Nested::Bool(*value),
+            serde_json::Value::Number(value) => Nested::Number(value.as_i64().ok_or(())?),
+            serde_json::Value::String(value) => Nested::String(Cow::Owned(value.clone())),
+            serde_json::Value::Array(values) => Nested::List(
+                values
+                    .iter()
+                    .map(Nested::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            serde_json::Value::Object(entries) => Nested::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok((Cow::Owned(key.clone()), Nested::try_from(value)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+impl Display for Nested<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Nested::Null => f.write_str("null"),
+            Nested::Bool(value) => write!(f, "{value}"),
+            Nested::Number(value) => write!(f, "{value}"),
+            Nested::String(value) => write!(f, "\"{value}\""),
+            Nested::List(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Nested::Object(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "\"{key}\":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Strings don't support escapes, so they can be borrowed as-is
+fn parse_string<'a>(input: &mut &'a str) -> winnow::Result<&'a str> {
+    delimited('"', take_till(0.., '"'), '"').parse_next(input)
+}
+
+fn parse_nested<'a>(input: &mut &'a str) -> winnow::Result<Nested<'a>> {
+    let token = |c| delimited(multispace0, c, multispace0);
+    alt((
+        "null".value(Nested::Null),
+        "true".value(Nested::Bool(true)),
+        "false".value(Nested::Bool(false)),
+        dec_int.map(Nested::Number),
+        parse_string.map(|s| Nested::String(Cow::Borrowed(s))),
+        delimited(
+            token('['),
+            separated(0.., parse_nested, token(',')),
+            token(']'),
+        )
+        .map(Nested::List),
+        delimited(
+            token('{'),
+            separated(
+                0..,
+                separated_pair(parse_string.map(Cow::Borrowed), token(':'), parse_nested),
+                token(','),
+            ),
+            token('}'),
+        )
+        .map(Nested::Object),
+    ))
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for s in [
+            "[1,[2,[3,[4,[5,6,7]]]],8,9]",
+            "[[]]",
+            r#"{"a":[1,"red",null],"b":{}}"#,
+        ] {
+            assert_eq!(Nested::parse(s).unwrap().to_string(), s);
+            let json = serde_json::from_str::<serde_json::Value>(s).unwrap();
+            assert_eq!(Nested::try_from(&json), Ok(Nested::parse(s).unwrap()));
+        }
+        assert!(Nested::parse("[1,2").is_err());
+
+        for s in ["[1.5]", "18446744073709551615", r#"{"a":-0.0}"#] {
+            let json = serde_json::from_str::<serde_json::Value>(s).unwrap();
+            assert!(Nested::try_from(&json).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let parse = |s| Nested::parse(s).unwrap();
+        let signal_cmp = |a, b| parse(a).signal_cmp(&parse(b));
+        assert!(signal_cmp("[[1],[2,3,4]]", "[[1],4]").is_lt());
+        assert!(signal_cmp("[9]", "[[8,7,6]]").is_gt());
+        assert!(signal_cmp("[[[]]]", "[[]]").is_gt());
+        assert!(signal_cmp("[1]", "[[1]]").is_eq());
+        // `Ord` still tells those apart
+        assert!(parse("[1]") < parse("[[1]]"));
+        assert_eq!(parse("[3]").cmp(&parse("[3]")), Ordering::Equal);
+
+        // A packet equal to a divider sorts before it
+        let packets = [parse("[[2]]"), parse("[1]")];
+        let dividers = [parse("[[2]]"), parse("[[6]]")];
+        assert_eq!(Nested::divider_positions(&packets, &dividers), [3, 4]);
+    }
+
+    /// Small values that are mostly numbers and lists, with the odd other kind mixed in
+    fn generate(seed: &mut u64, depth: usize) -> Nested<'static> {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        let choice = *seed % 10;
+        match choice {
+            0 => Nested::Null,
+            1 => Nested::String(Cow::Borrowed("a")),
+            2..=5 => Nested::Number((*seed >> 8) as i64 % 3),
+            _ if depth == 0 => Nested::Number((*seed >> 8) as i64 % 3),
+            _ => {
+                let len = (*seed >> 8) as usize % 3;
+                Nested::List((0..len).map(|_| generate(seed, depth - 1)).collect())
+            }
+        }
+    }
+
+    #[test]
+    fn test_transitive() {
+        let mut seed = 0x2545_f491_4f6c_dd1d;
+        let values = (0..60).map(|_| generate(&mut seed, 3)).collect::<Vec<_>>();
+        for compare in [Nested::cmp, Nested::signal_cmp] {
+            for a in values.iter() {
+                for b in values.iter() {
+                    assert_eq!(compare(a, b), compare(b, a).reverse(), "{a} against {b}");
+                    for c in values.iter() {
+                        if compare(a, b).is_le() && compare(b, c).is_le() {
+                            assert!(compare(a, c).is_le(), "{a} <= {b} <= {c}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sum_numbers() {
+        let red = Nested::String(Cow::Borrowed("red"));
+        let value = Nested::parse(r#"[1,{"c":"red","b":2},3]"#).unwrap();
+        assert_eq!(value.sum_numbers(&|_| false), 6);
+        assert_eq!(value.sum_numbers(&|v| v.object_contains(&red)), 4);
+    }
+}
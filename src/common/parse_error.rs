@@ -0,0 +1,102 @@
+use std::fmt::Display;
+
+use winnow::error::ContextError;
+
+use crate::{Day, Year};
+
+/// Where and why an input failed to parse, with the offending line for context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    day: Option<(Year, Day)>,
+    /// 1-based
+    line: usize,
+    /// 1-based, in characters
+    column: usize,
+    snippet: String,
+    message: String,
+}
+
+impl ParseError {
+    /// An error at byte `offset` of `input`
+    pub(crate) fn new(input: &str, offset: usize, message: impl Into<String>) -> ParseError {
+        let offset = offset.min(input.len());
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+        ParseError {
+            day: None,
+            line: input[..offset].matches('\n').count() + 1,
+            column: input[line_start..offset].chars().count() + 1,
+            snippet: input[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn from_winnow(
+        input: &str,
+        error: winnow::error::ParseError<&str, ContextError>,
+    ) -> ParseError {
+        let message = match error.inner().to_string() {
+            message if message.is_empty() => "unexpected input".to_string(),
+            message => message,
+        };
+        ParseError::new(input, error.offset(), message)
+    }
+
+    pub(crate) fn in_day(self, year: Year, day: Day) -> ParseError {
+        ParseError {
+            day: Some((year, day)),
+            ..self
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((year, day)) = self.day {
+            write!(f, "{year} day {day}: ")?;
+        }
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )?;
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{gutter} | {}^", " ".repeat(self.column - 1))
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let error = ParseError::new("abc\r\nde?f\n", 7, "expected letter");
+        assert_eq!((error.line(), error.column()), (2, 3));
+        assert_eq!(
+            error.in_day(Year::new(2023), Day::new(7)).to_string(),
+            "2023 day 7: expected letter at line 2, column 3
+  |
+2 | de?f
+  |   ^"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+/// A rearrangement of `0..len`: applying it puts whatever was at `sources[i]` at position `i`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Permutation {
+    sources: Vec<usize>,
+}
+
+impl Permutation {
+    pub(crate) fn identity(len: usize) -> Permutation {
+        Permutation {
+            sources: (0..len).collect(),
+        }
+    }
+
+    /// Fails unless `sources` holds each of `0..len` exactly once
+    #[allow(dead_code)]
+    pub(crate) fn from_sources(sources: Vec<usize>) -> Option<Permutation> {
+        let mut seen = vec![false; sources.len()];
+        for source in sources.iter() {
+            if std::mem::replace(seen.get_mut(*source)?, true) {
+                return None;
+            }
+        }
+        Some(Permutation { sources })
+    }
+
+    /// Swaps what's at `a` and `b`
+    pub(crate) fn transposition(len: usize, a: usize, b: usize) -> Permutation {
+        let mut permutation = Permutation::identity(len);
+        permutation.sources.swap(a, b);
+        permutation
+    }
+
+    /// Moves everything `amount` places right, wrapping round to the start
+    pub(crate) fn rotation_right(len: usize, amount: usize) -> Permutation {
+        // Nothing to turn when there are no items
+        let amount = amount.checked_rem(len).unwrap_or(0);
+        Permutation {
+            sources: (0..len).map(|i| (i + len - amount) % len).collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub(crate) fn apply<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        assert_eq!(items.len(), self.len(), "Permutation is the wrong length");
+        self.sources.iter().map(|i| items[*i].clone()).collect()
+    }
+
+    /// This permutation followed by `other`
+    pub(crate) fn then(&self, other: &Permutation) -> Permutation {
+        Permutation {
+            sources: other.sources.iter().map(|i| self.sources[*i]).collect(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn inverse(&self) -> Permutation {
+        let mut sources = vec![0; self.len()];
+        for (position, source) in self.sources.iter().enumerate() {
+            sources[*source] = position;
+        }
+        Permutation { sources }
+    }
+
+    /// Applied `exponent` times in a row, by repeated squaring
+    pub(crate) fn pow(&self, mut exponent: u64) -> Permutation {
+        let mut result = Permutation::identity(self.len());
+        let mut square = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// Each cycle of positions, starting from its smallest, with fixed points included
+    #[allow(dead_code)]
+    pub(crate) fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.len()];
+        let mut cycles = Vec::new();
+        for start in 0..self.len() {
+            let mut cycle = Vec::new();
+            let mut position = start;
+            while !seen[position] {
+                seen[position] = true;
+                cycle.push(position);
+                position = self.sources[position];
+            }
+            if !cycle.is_empty() {
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algebra() {
+        let items = ['a', 'b', 'c', 'd', 'e'];
+        let rotate = Permutation::rotation_right(5, 2);
+        assert_eq!(rotate.apply(&items), ['d', 'e', 'a', 'b', 'c']);
+        assert_eq!(Permutation::rotation_right(0, 3).len(), 0);
+        let swap = Permutation::transposition(5, 0, 4);
+        let both = rotate.then(&swap);
+        assert_eq!(both.apply(&items), swap.apply(&rotate.apply(&items)));
+        assert_eq!(both.then(&both.inverse()), Permutation::identity(5));
+        assert_eq!(both.inverse().then(&both), Permutation::identity(5));
+
+        let mut repeated = items.to_vec();
+        for exponent in 0..20 {
+            assert_eq!(both.pow(exponent).apply(&items), repeated);
+            repeated = both.apply(&repeated);
+        }
+        assert_eq!(rotate.pow(1_000_000_005), Permutation::identity(5));
+
+        assert_eq!(Permutation::from_sources(vec![1, 1, 0]), None);
+        assert_eq!(Permutation::from_sources(vec![1, 3, 0]), None);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let permutation = Permutation::from_sources(vec![2, 0, 1, 3, 5, 4]).unwrap();
+        assert_eq!(permutation.cycles(), [vec![0, 2, 1], vec![3], vec![4, 5]]);
+        assert_eq!(permutation.pow(6), Permutation::identity(6));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+/// A circle of the labels `0..len`, stored as each label's successor so runs of labels can be
+/// cut out and put back anywhere in constant time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Ring {
+    next: Vec<usize>,
+    head: usize,
+}
+
+/// Labels cut out of a ring, still linked to each other from `first` to `last`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Run {
+    first: usize,
+    last: usize,
+    len: usize,
+}
+
+impl Ring {
+    /// `order` has to hold each of `0..len` exactly once. The first label becomes the head
+    pub(crate) fn from_order(order: impl IntoIterator<Item = usize>) -> Option<Ring> {
+        let order = order.into_iter().collect::<Vec<_>>();
+        let mut next = vec![usize::MAX; order.len()];
+        for (i, label) in order.iter().enumerate() {
+            let slot = next.get_mut(*label)?;
+            if *slot != usize::MAX {
+                return None;
+            }
+            *slot = order[(i + 1) % order.len()];
+        }
+        Some(Ring {
+            next,
+            head: *order.first()?,
+        })
+    }
+
+    /// How many labels the ring was made with, counting any cut out
+    pub(crate) fn len(&self) -> usize {
+        self.next.len()
+    }
+
+    pub(crate) fn head(&self) -> usize {
+        self.head
+    }
+
+    pub(crate) fn next(&self, label: usize) -> usize {
+        self.next[label]
+    }
+
+    /// Moves the head `steps` labels along
+    pub(crate) fn rotate(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.head = self.next[self.head];
+        }
+    }
+
+    /// Cuts out the `len` labels following `label`, which must leave at least `label` behind
+    pub(crate) fn remove_after(&mut self, label: usize, len: usize) -> Run {
+        assert!(len > 0, "Runs can't be empty");
+        let first = self.next[label];
+        let mut last = first;
+        for _ in 1..len {
+            last = self.next[last];
+        }
+        assert_ne!(last, label, "Can't remove the whole ring");
+        self.next[label] = self.next[last];
+        if self
+            .run_labels(&Run { first, last, len })
+            .any(|l| l == self.head)
+        {
+            self.head = label;
+        }
+        Run { first, last, len }
+    }
+
+    /// Puts a run back in, straight after `label`
+    pub(crate) fn insert_after(&mut self, label: usize, run: Run) {
+        self.next[run.last] = self.next[label];
+        self.next[label] = run.first;
+    }
+
+    /// The labels of a run in order, which only makes sense while it's cut out
+    pub(crate) fn run_labels(&self, run: &Run) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(run.first), |label| Some(self.next[*label])).take(run.len)
+    }
+
+    /// Each label once, going round from `start`, which has to still be in the ring
+    pub(crate) fn iter_from(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(start), move |label| {
+            Some(self.next[*label]).filter(|next| *next != start)
+        })
+    }
+}
+
+/// A circular buffer with a cursor, where everything happens at the cursor and the cursor
+/// moves by rotating the buffer under it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CursorRing<T> {
+    /// The front is the cursor
+    items: VecDeque<T>,
+}
+
+impl<T> CursorRing<T> {
+    #[allow(dead_code)]
+    pub(crate) fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn current(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    /// The item `offset` along from the cursor, going round as many times as needed
+    pub(crate) fn get(&self, offset: isize) -> Option<&T> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let index = offset.rem_euclid(self.items.len() as isize) as usize;
+        self.items.get(index)
+    }
+
+    /// Moves the cursor forwards `steps`, or backwards if negative
+    pub(crate) fn rotate(&mut self, steps: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let steps = steps.rem_euclid(self.items.len() as isize) as usize;
+        self.items.rotate_left(steps);
+    }
+
+    /// Puts `item` in at the cursor, so it's current and the old current follows it
+    pub(crate) fn insert(&mut self, item: T) {
+        self.items.push_front(item);
+    }
+
+    /// Takes out the current item, leaving the cursor on the one that followed it
+    #[allow(dead_code)]
+    pub(crate) fn remove(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    /// From the cursor round to the item before it
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+}
+
+impl<T> FromIterator<T> for CursorRing<T> {
+    /// The first item is current
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        CursorRing {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        assert_eq!(Ring::from_order([0, 2, 2]), None);
+        assert_eq!(Ring::from_order([0, 3, 1]), None);
+
+        let mut ring = Ring::from_order([2, 0, 4, 1, 3]).unwrap();
+        assert_eq!(ring.iter_from(4).collect::<Vec<_>>(), [4, 1, 3, 2, 0]);
+
+        let run = ring.remove_after(4, 3);
+        assert_eq!(ring.run_labels(&run).collect::<Vec<_>>(), [1, 3, 2]);
+        assert_eq!(ring.iter_from(0).collect::<Vec<_>>(), [0, 4]);
+        // The head was cut out, so it falls back to where the cut was
+        assert_eq!(ring.head(), 4);
+
+        ring.insert_after(0, run);
+        assert_eq!(ring.iter_from(0).collect::<Vec<_>>(), [0, 1, 3, 2, 4]);
+        ring.rotate(7);
+        assert_eq!(ring.head(), 1);
+        assert_eq!(ring.next(1), 3);
+    }
+
+    #[test]
+    fn test_cursor_ring() {
+        let mut ring = (0..5).collect::<CursorRing<_>>();
+        ring.rotate(-2);
+        assert_eq!(ring.current(), Some(&3));
+        assert_eq!(ring.get(7), Some(&0));
+        ring.insert(9);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [9, 3, 4, 0, 1, 2]);
+        assert_eq!(ring.remove(), Some(9));
+        assert_eq!(ring.remove(), Some(3));
+        assert_eq!(ring.current(), Some(&4));
+        assert_eq!(ring.len(), 4);
+
+        let mut empty = CursorRing::<u8>::from_iter([]);
+        empty.rotate(3);
+        assert_eq!(empty.get(1), None);
+    }
+}
@@ -0,0 +1,255 @@
+use std::{fmt::Display, str::FromStr};
+
+/// One of the 8 symmetries of a square (the dihedral group D4): a flip left to right if
+/// `flipped`, followed by `quarter_turns` clockwise rotations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Symmetry {
+    quarter_turns: u8,
+    flipped: bool,
+}
+
+impl Symmetry {
+    #[allow(dead_code)]
+    pub(crate) const IDENTITY: Symmetry = Symmetry {
+        quarter_turns: 0,
+        flipped: false,
+    };
+
+    pub(crate) fn rotation(quarter_turns: u8) -> Symmetry {
+        Symmetry {
+            quarter_turns: quarter_turns % 4,
+            flipped: false,
+        }
+    }
+
+    pub(crate) fn flip() -> Symmetry {
+        Symmetry {
+            quarter_turns: 0,
+            flipped: true,
+        }
+    }
+
+    /// All 8, starting with the identity
+    pub(crate) fn all() -> impl Iterator<Item = Symmetry> {
+        [false, true].into_iter().flat_map(|flipped| {
+            (0..4).map(move |quarter_turns| Symmetry {
+                quarter_turns,
+                flipped,
+            })
+        })
+    }
+
+    /// This symmetry followed by `other`
+    pub(crate) fn then(self, other: Symmetry) -> Symmetry {
+        // A flip turns later rotations into rotations the other way
+        let quarter_turns = match other.flipped {
+            true => other.quarter_turns + 4 - self.quarter_turns,
+            false => other.quarter_turns + self.quarter_turns,
+        };
+        Symmetry {
+            quarter_turns: quarter_turns % 4,
+            flipped: self.flipped != other.flipped,
+        }
+    }
+
+    pub(crate) fn inverse(self) -> Symmetry {
+        let quarter_turns = match self.flipped {
+            true => self.quarter_turns,
+            false => (4 - self.quarter_turns) % 4,
+        };
+        Symmetry {
+            quarter_turns,
+            flipped: self.flipped,
+        }
+    }
+
+    /// Applies the symmetry to a whole grid, which doesn't have to be square
+    pub(crate) fn apply<T: Clone>(self, grid: &[Vec<T>]) -> Vec<Vec<T>> {
+        let mut grid = match self.flipped {
+            true => grid
+                .iter()
+                .map(|row| row.iter().rev().cloned().collect())
+                .collect(),
+            false => grid.to_vec(),
+        };
+        for _ in 0..self.quarter_turns {
+            let height = grid.len();
+            let width = grid.first().map_or(0, Vec::len);
+            grid = (0..width)
+                .map(|y| {
+                    (0..height)
+                        .map(|x| grid[height - 1 - x][y].clone())
+                        .collect()
+                })
+                .collect();
+        }
+        grid
+    }
+
+    /// Where `(x, y)` moves to within a square of side `size`
+    pub(crate) fn map(self, x: usize, y: usize, size: usize) -> (usize, usize) {
+        let (mut x, mut y) = match self.flipped {
+            true => (size - 1 - x, y),
+            false => (x, y),
+        };
+        for _ in 0..self.quarter_turns {
+            (x, y) = (size - 1 - y, x);
+        }
+        (x, y)
+    }
+}
+
+/// A square pattern of up to 4x4 bits, packed in reading order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct BitPattern {
+    size: u8,
+    bits: u16,
+}
+
+impl BitPattern {
+    pub(crate) fn from_fn(size: usize, f: impl Fn(usize, usize) -> bool) -> BitPattern {
+        assert!(size <= 4, "Bit patterns are at most 4x4");
+        let mut bits = 0;
+        for y in 0..size {
+            for x in 0..size {
+                if f(x, y) {
+                    bits |= 1 << (y * size + x);
+                }
+            }
+        }
+        BitPattern {
+            size: size as u8,
+            bits,
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    pub(crate) fn get(&self, x: usize, y: usize) -> bool {
+        self.bits & (1 << (y * self.size() + x)) != 0
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn count_ones(&self) -> u32 {
+        self.bits.count_ones()
+    }
+
+    pub(crate) fn transformed(&self, symmetry: Symmetry) -> BitPattern {
+        let size = self.size();
+        let inverse = symmetry.inverse();
+        BitPattern::from_fn(size, |x, y| {
+            let (x, y) = inverse.map(x, y, size);
+            self.get(x, y)
+        })
+    }
+
+    /// The smallest of the pattern's 8 orientations, so patterns that are symmetries of each
+    /// other share one key, along with the symmetry that takes this pattern there
+    pub(crate) fn canonical(&self) -> (BitPattern, Symmetry) {
+        Symmetry::all()
+            .map(|symmetry| (self.transformed(symmetry), symmetry))
+            .min_by_key(|(pattern, _)| *pattern)
+            .unwrap()
+    }
+}
+
+/// Rows of `#` and `.` separated by `/`, like `.#./..#/###`
+impl FromStr for BitPattern {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .split('/')
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if rows.len() > 4 || rows.iter().any(|row| row.len() != rows.len()) {
+            return Err(());
+        }
+        Ok(BitPattern::from_fn(rows.len(), |x, y| rows[y][x]))
+    }
+}
+
+impl Display for BitPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.size() {
+            if y != 0 {
+                write!(f, "/")?;
+            }
+            for x in 0..self.size() {
+                write!(f, "{}", if self.get(x, y) { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group() {
+        let all = Symmetry::all().collect::<Vec<_>>();
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], Symmetry::IDENTITY);
+        let pattern = "#.#./.#../..../...#".parse::<BitPattern>().unwrap();
+        for a in all.iter() {
+            assert_eq!(a.then(a.inverse()), Symmetry::IDENTITY);
+            for b in all.iter() {
+                assert_eq!(
+                    pattern.transformed(*a).transformed(*b),
+                    pattern.transformed(a.then(*b))
+                );
+            }
+        }
+        // Every orientation of this pattern is different
+        let mut orientations = all
+            .iter()
+            .map(|s| pattern.transformed(*s))
+            .collect::<Vec<_>>();
+        orientations.sort();
+        orientations.dedup();
+        assert_eq!(orientations.len(), 8);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let pattern = "#.#/.#./...".parse::<BitPattern>().unwrap();
+        let rotated = pattern.transformed(Symmetry::rotation(1));
+        assert_eq!(rotated.to_string(), "..#/.#./..#");
+        let flipped = "#.#/##./...".parse::<BitPattern>().unwrap();
+        assert_eq!(
+            flipped.transformed(Symmetry::flip()).to_string(),
+            "#.#/.##/..."
+        );
+
+        let grid = (0..3)
+            .map(|y| (0..3).map(|x| pattern.get(x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for symmetry in Symmetry::all() {
+            let transformed = pattern.transformed(symmetry);
+            let expected = (0..3)
+                .map(|y| (0..3).map(|x| transformed.get(x, y)).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            assert_eq!(symmetry.apply(&grid), expected);
+        }
+        assert_eq!(
+            Symmetry::rotation(1).apply(&[vec![1, 2, 3], vec![4, 5, 6]]),
+            [vec![4, 1], vec![5, 2], vec![6, 3]]
+        );
+
+        let (canonical, symmetry) = rotated.canonical();
+        assert_eq!(canonical, pattern.canonical().0);
+        assert_eq!(rotated.transformed(symmetry), canonical);
+    }
+}
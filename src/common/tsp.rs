@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+
+/// Weights between nodes, where `weights[from][to]` needn't match `weights[to][from]` and
+/// `None` means there's no way between them
+pub(crate) type Weights = Vec<Vec<Option<i64>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Objective {
+    Shortest,
+    Longest,
+}
+
+/// A travelling salesman problem, solved with Held-Karp dynamic programming over subsets of
+/// nodes. That takes `2^n * n^2` time rather than the `n!` of trying every order
+#[derive(Debug, Clone)]
+pub(crate) struct Tsp {
+    weights: Weights,
+    objective: Objective,
+    /// Whether the tour returns to where it started
+    closed: bool,
+    start: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tour {
+    pub(crate) total: i64,
+    /// Each node once. A closed tour goes back from the last to the first
+    pub(crate) order: Vec<usize>,
+}
+
+impl Tsp {
+    /// The shortest open path, starting anywhere
+    pub(crate) fn new(weights: Weights) -> Tsp {
+        assert!(weights.len() < 32, "Too many nodes to solve");
+        Tsp {
+            weights,
+            objective: Objective::Shortest,
+            closed: false,
+            start: None,
+        }
+    }
+
+    pub(crate) fn objective(self, objective: Objective) -> Tsp {
+        Tsp { objective, ..self }
+    }
+
+    /// Go back to the start at the end
+    pub(crate) fn closed(self) -> Tsp {
+        Tsp {
+            closed: true,
+            ..self
+        }
+    }
+
+    pub(crate) fn starting_at(self, start: usize) -> Tsp {
+        Tsp {
+            start: Some(start),
+            ..self
+        }
+    }
+
+    /// The best tour, or `None` if missing edges mean there isn't one
+    pub(crate) fn solve(&self) -> Option<Tour> {
+        let n = self.weights.len();
+        if n == 0 {
+            return None;
+        }
+        let better = |a: i64, b: i64| match self.objective {
+            Objective::Shortest => a < b,
+            Objective::Longest => a > b,
+        };
+        // Closed tours can start anywhere, so might as well start at 0
+        let start = self.start.or(self.closed.then_some(0));
+
+        // best[visited][last], with the node before `last` kept to rebuild the order
+        let full = (1 << n) - 1;
+        let mut best = vec![vec![None::<(i64, usize)>; n]; 1 << n];
+        for node in (0..n).filter(|node| start.is_none_or(|start| start == *node)) {
+            best[1 << node][node] = Some((0, node));
+        }
+        for visited in 1..=full {
+            for last in 0..n {
+                let Some((total, _)) = best[visited][last] else {
+                    continue;
+                };
+                for next in (0..n).filter(|next| visited & (1 << next) == 0) {
+                    let Some(weight) = self.weights[last][next] else {
+                        continue;
+                    };
+                    let entry = &mut best[visited | (1 << next)][next];
+                    if entry.is_none_or(|(other, _)| better(total + weight, other)) {
+                        *entry = Some((total + weight, last));
+                    }
+                }
+            }
+        }
+
+        let (total, last) = (0..n)
+            .filter_map(|last| {
+                let (total, _) = best[full][last]?;
+                match start.filter(|_| self.closed) {
+                    Some(start) => Some((total + self.weights[last][start]?, last)),
+                    None => Some((total, last)),
+                }
+            })
+            .reduce(|a, b| if better(b.0, a.0) { b } else { a })?;
+
+        let mut order = vec![last];
+        let mut visited = full;
+        let mut node = last;
+        while visited.count_ones() > 1 {
+            let (_, previous) = best[visited][node].unwrap();
+            visited &= !(1 << node);
+            node = previous;
+            order.push(node);
+        }
+        order.reverse();
+        Some(Tour { total, order })
+    }
+}
+
+/// Steps between each pair of `points` on a grid, moving up, down, left and right through
+/// `open` cells
+pub(crate) fn grid_distances(open: &[Vec<bool>], points: &[(usize, usize)]) -> Weights {
+    points
+        .iter()
+        .map(|&(x, y)| {
+            let mut steps = vec![vec![None; open[0].len()]; open.len()];
+            steps[y][x] = Some(0);
+            let mut queue = VecDeque::from([(x, y)]);
+            while let Some((x, y)) = queue.pop_front() {
+                let current = steps[y][x].unwrap();
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    let is_open = open.get(ny).and_then(|row| row.get(nx)) == Some(&true);
+                    if is_open && steps[ny][nx].is_none() {
+                        steps[ny][nx] = Some(current + 1);
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            points.iter().map(|&(x, y)| steps[y][x]).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    /// Every order, to check against
+    fn brute_force(tsp: &Tsp) -> Option<i64> {
+        let n = tsp.weights.len();
+        (0..n)
+            .permutations(n)
+            .filter(|order| tsp.start.is_none_or(|start| order[0] == start))
+            .filter_map(|order| {
+                let mut total = 0;
+                for pair in order.windows(2) {
+                    total += tsp.weights[pair[0]][pair[1]]?;
+                }
+                if tsp.closed {
+                    total += tsp.weights[*order.last().unwrap()][order[0]]?;
+                }
+                Some(total)
+            })
+            .reduce(|a, b| match tsp.objective {
+                Objective::Shortest => a.min(b),
+                Objective::Longest => a.max(b),
+            })
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut state = 12345_u64;
+        let mut random = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as i64
+        };
+        for n in 1..=6 {
+            // Asymmetric, negative and missing weights
+            let weights = (0..n)
+                .map(|_| {
+                    (0..n)
+                        .map(|_| (random() % 5 != 0).then(|| random() % 40 - 10))
+                        .collect()
+                })
+                .collect::<Weights>();
+            for objective in [Objective::Shortest, Objective::Longest] {
+                for closed in [false, true] {
+                    for start in [None, Some(n - 1)] {
+                        let mut tsp = Tsp::new(weights.clone()).objective(objective);
+                        if closed {
+                            tsp = tsp.closed();
+                        }
+                        if let Some(start) = start {
+                            tsp = tsp.starting_at(start);
+                        }
+                        let tour = tsp.solve();
+                        assert_eq!(tour.as_ref().map(|t| t.total), brute_force(&tsp));
+                        if let Some(tour) = tour {
+                            assert_eq!(tour.order.len(), n);
+                            assert!(start.is_none_or(|start| tour.order[0] == start));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_distances() {
+        let open = ["..#.", "..#.", "...."]
+            .iter()
+            .map(|row| row.chars().map(|c| c == '.').collect())
+            .collect::<Vec<_>>();
+        let distances = grid_distances(&open, &[(0, 0), (3, 0), (1, 1)]);
+        assert_eq!(distances[0], [Some(0), Some(7), Some(2)]);
+        assert_eq!(distances[1][2], Some(5));
+        let walled = grid_distances(&open, &[(0, 0), (2, 0)]);
+        assert_eq!(walled[0][1], None);
+    }
+}
@@ -1,7 +1,125 @@
-use winnow::{ascii::digit1, Parser};
+use std::str::FromStr;
+
+use winnow::{
+    ascii::{dec_int, dec_uint, digit1, line_ending, multispace0, space0},
+    combinator::{alt, eof, peek, repeat, separated, terminated},
+    error::{ContextError, StrContext, StrContextValue},
+    stream::AsChar,
+    token::one_of,
+    Parser,
+};
+
+use super::ParseError;
+
+/// Runs `parser` over the whole input, allowing trailing whitespace
+pub(crate) fn parse_all<'a, O>(
+    input: &'a str,
+    parser: impl Parser<&'a str, O, ContextError>,
+) -> Result<O, ParseError> {
+    terminated(parser, (multispace0, eof.context(expected("end of input"))))
+        .parse(input)
+        .map_err(|error| ParseError::from_winnow(input, error))
+}
+
+pub(crate) fn expected(description: &'static str) -> StrContext {
+    StrContext::Expected(StrContextValue::Description(description))
+}
 
 pub fn parse_u32(input: &mut &str) -> winnow::Result<u32> {
     digit1
-        .map(|digits: &str| digits.parse::<u32>().unwrap())
+        .try_map(str::parse::<u32>)
+        .context(expected("number"))
         .parse_next(input)
 }
+
+pub(crate) fn parse_u64(input: &mut &str) -> winnow::Result<u64> {
+    dec_uint.context(expected("number")).parse_next(input)
+}
+
+pub(crate) fn parse_i64(input: &mut &str) -> winnow::Result<i64> {
+    dec_int.context(expected("number")).parse_next(input)
+}
+
+/// A single digit as its value
+pub(crate) fn parse_digit(input: &mut &str) -> winnow::Result<u8> {
+    one_of(AsChar::is_dec_digit)
+        .map(|c: char| c.to_digit(10).unwrap() as u8)
+        .context(expected("digit"))
+        .parse_next(input)
+}
+
+/// Newline separated items, accepting CRLF
+pub(crate) fn lines<'a, O>(
+    item: impl Parser<&'a str, O, ContextError>,
+) -> impl Parser<&'a str, Vec<O>, ContextError> {
+    separated(1.., item, line_ending)
+}
+
+/// Blank line separated blocks
+pub(crate) fn blocks<'a, O>(
+    block: impl Parser<&'a str, O, ContextError>,
+) -> impl Parser<&'a str, Vec<O>, ContextError> {
+    separated(1.., block, (line_ending, line_ending))
+}
+
+/// Rows of cells, all the same width
+pub(crate) fn grid<'a, O>(
+    cell: impl Parser<&'a str, O, ContextError>,
+) -> impl Parser<&'a str, Vec<Vec<O>>, ContextError> {
+    let row = terminated(
+        repeat(1.., cell),
+        peek(alt((line_ending, eof))).context(expected("end of row")),
+    );
+    lines(row)
+        .verify(|rows: &Vec<Vec<O>>| rows.iter().all(|row| row.len() == rows[0].len()))
+        .context(expected("rows of equal width"))
+}
+
+/// `key: value` lines
+pub(crate) fn key_values<'a, K, V>(
+    key: impl Parser<&'a str, K, ContextError>,
+    value: impl Parser<&'a str, V, ContextError>,
+) -> impl Parser<&'a str, Vec<(K, V)>, ContextError> {
+    lines((terminated(key, (':', space0)), value))
+}
+
+/// Anything implementing `FromStr` from a run of characters matching `predicate`
+pub(crate) fn parse_from_str<'a, T: FromStr>(
+    predicate: impl Fn(char) -> bool,
+) -> impl Parser<&'a str, T, ContextError> {
+    winnow::token::take_while(1.., predicate).verify_map(|s: &str| s.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use winnow::ascii::alpha1;
+
+    use super::*;
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(
+            parse_all("1 -2\r\n3 4\n\n", lines(separated(1.., parse_i64, ' '))),
+            Ok(vec![vec![1, -2], vec![3, 4]])
+        );
+        assert_eq!(
+            parse_all("12\n34\n\n56", blocks(grid(parse_digit))),
+            Ok(vec![vec![vec![1, 2], vec![3, 4]], vec![vec![5, 6]]])
+        );
+        assert_eq!(
+            parse_all("a: 1\nbc:2", key_values(alpha1, parse_u64)),
+            Ok(vec![("a", 1), ("bc", 2)])
+        );
+        assert_eq!(parse_all("4294967295", parse_u32), Ok(u32::MAX));
+        assert!(parse_all("4294967296", parse_u32).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse_all("12\n3x\n", grid(parse_digit)).unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 1));
+
+        let error = parse_all("12\n3\n", grid(parse_digit)).unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 1));
+    }
+}
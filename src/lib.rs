@@ -9,6 +9,8 @@ macros::mod_years!();
 
 macros::solutions!();
 
+pub use common::ParseError;
+
 pub struct Solution {
     pub year: Year,
     pub day: Day,
@@ -20,12 +22,29 @@ impl Solution {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Year(i64);
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Day(u8);
 
+/// What a `solve` function can return. Either the two answers, or the answers behind a parse error
+pub trait Answers {
+    fn into_answers(self) -> Result<(String, String), ParseError>;
+}
+
+impl<A: ToString, B: ToString> Answers for (A, B) {
+    fn into_answers(self) -> Result<(String, String), ParseError> {
+        Ok((self.0.to_string(), self.1.to_string()))
+    }
+}
+
+impl<A: ToString, B: ToString> Answers for Result<(A, B), ParseError> {
+    fn into_answers(self) -> Result<(String, String), ParseError> {
+        self.and_then(Answers::into_answers)
+    }
+}
+
 pub fn get_solution(year: Year, day: Day) -> Option<&'static Solution> {
     SOLUTIONS
         .iter()
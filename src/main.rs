@@ -101,6 +101,7 @@ fn run_solution(solution: &Solution, session_key: &str) -> Result<()> {
     let before = Instant::now();
     let result = solution.run(&input);
     let time_diff = before.elapsed();
+    let result = result?;
 
     println!("Part 1:  {}", result.0);
     println!("Part 2:  {}", result.1);
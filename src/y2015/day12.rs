@@ -1,39 +1,14 @@
-use serde_json::Value;
+use std::borrow::Cow;
+
+use crate::common::Nested;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let root: Value = serde_json::from_str(input).unwrap();
+    let root = Nested::parse(input).unwrap();
 
-    let mut total = 0;
-    let mut q = vec![&root];
-    while let Some(node) = q.pop() {
-        match node {
-            Value::Null => {}
-            Value::Bool(_) => {}
-            Value::Number(value) => total += value.as_i64().unwrap(),
-            Value::String(_) => {}
-            Value::Array(array) => q.extend(array),
-            Value::Object(object) => q.extend(object.values()),
-        }
-    }
-    let part1 = total;
+    let part1 = root.sum_numbers(&|_| false);
 
-    let mut total = 0;
-    let mut q = vec![&root];
-    while let Some(node) = q.pop() {
-        match node {
-            Value::Null => {}
-            Value::Bool(_) => {}
-            Value::Number(value) => total += value.as_i64().unwrap(),
-            Value::String(_) => {}
-            Value::Array(array) => q.extend(array),
-            Value::Object(object) => {
-                if !object.values().any(|a| a == "red") {
-                    q.extend(object.values());
-                }
-            }
-        }
-    }
-    let part2 = total;
+    let red = Nested::String(Cow::Borrowed("red"));
+    let part2 = root.sum_numbers(&|node| node.object_contains(&red));
 
     (part1, part2)
 }
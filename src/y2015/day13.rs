@@ -1,83 +1,67 @@
 use std::collections::HashMap;
 
-use itertools::Itertools;
+use crate::common::{Objective, Tsp, Weights};
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut rels = HashMap::new();
+    let mut people = HashMap::new();
+    let mut rels = Vec::new();
     for line in input.lines() {
         let parts = line.split(' ').collect::<Vec<_>>();
-        let person_a = parts[0];
+        let mut index = |name| {
+            let next = people.len();
+            *people.entry(name).or_insert(next)
+        };
+        let person_a = index(parts[0]);
         let is_neg = parts[2] == "lose";
-        let value = parts[3].parse::<i32>().unwrap();
-        let person_b = parts[10].trim_end_matches('.');
+        let value = parts[3].parse::<i64>().unwrap();
+        let person_b = index(parts[10].trim_end_matches('.'));
         let value = if is_neg { -value } else { value };
-        rels.entry(person_a)
-            .or_insert_with(HashMap::new)
-            .insert(person_b, value);
+        rels.push((person_a, person_b, value));
     }
 
-    let mut best = i32::MIN;
-    let names = rels.keys().collect::<Vec<_>>();
-    let names_len = names.len();
-    for comb in names.into_iter().permutations(names_len) {
-        let mut last_person = comb[0];
-        let end_person = comb[comb.len() - 1];
-        //println!("{} {}", end_person, last_person);
-        let mut happiness = rels[end_person][last_person] + rels[last_person][end_person];
-        for &person in &comb[1..] {
-            //println!("{} {}", person, last_person);
-            happiness += rels[person][last_person] + rels[last_person][person];
-            last_person = person;
-        }
-        if happiness > best {
-            best = happiness;
-        }
-    }
-    let part1 = best;
-
-    let mut rels = HashMap::new();
-    for line in input.lines() {
-        let parts = line.split(' ').collect::<Vec<_>>();
-        let person_a = parts[0];
-        let is_neg = parts[2] == "lose";
-        let value = parts[3].parse::<i32>().unwrap();
-        let person_b = parts[10].trim_end_matches('.');
-        let value = if is_neg { -value } else { value };
-        rels.entry(person_a)
-            .or_insert_with(HashMap::new)
-            .insert(person_b, value);
-    }
+    let part1 = best_seating(people.len(), &rels);
+    // Sitting next to me changes nobody's happiness
+    let part2 = best_seating(people.len() + 1, &rels);
 
-    // Add self
-    rels.insert("me", HashMap::new());
-    let others = rels
-        .keys()
-        .filter(|&name| name != &"me")
-        .cloned()
-        .collect::<Vec<_>>();
-    for name in others {
-        rels.get_mut("me").unwrap().insert(name, 0);
-        rels.get_mut(name).unwrap().insert("me", 0);
-    }
+    (part1, part2)
+}
 
-    let mut best = i32::MIN;
-    let names = rels.keys().collect::<Vec<_>>();
-    let names_len = names.len();
-    for comb in names.into_iter().permutations(names_len) {
-        let mut last_person = comb[0];
-        let end_person = comb[comb.len() - 1];
-        //println!("{} {}", end_person, last_person);
-        let mut happiness = rels[end_person][last_person] + rels[last_person][end_person];
-        for &person in &comb[1..] {
-            //println!("{} {}", person, last_person);
-            happiness += rels[person][last_person] + rels[last_person][person];
-            last_person = person;
-        }
-        if happiness > best {
-            best = happiness;
+/// Happiness of the best arrangement around a round table. Both people sitting next to each
+/// other count, so each pair's weight is the sum of how they feel about each other
+fn best_seating(people: usize, rels: &[(usize, usize, i64)]) -> i64 {
+    let mut weights: Weights = vec![vec![Some(0); people]; people];
+    for (a, b, value) in rels {
+        for (from, to) in [(a, b), (b, a)] {
+            weights[*from][*to] = weights[*from][*to].map(|total| total + value);
         }
     }
-    let part2 = best;
+    Tsp::new(weights)
+        .objective(Objective::Longest)
+        .closed()
+        .solve()
+        .unwrap()
+        .total
+}
 
-    (part1, part2)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = "Alice would gain 54 happiness units by sitting next to Bob.
+Alice would lose 79 happiness units by sitting next to Carol.
+Alice would lose 2 happiness units by sitting next to David.
+Bob would gain 83 happiness units by sitting next to Alice.
+Bob would lose 7 happiness units by sitting next to Carol.
+Bob would lose 63 happiness units by sitting next to David.
+Carol would lose 62 happiness units by sitting next to Alice.
+Carol would gain 60 happiness units by sitting next to Bob.
+Carol would gain 55 happiness units by sitting next to David.
+David would gain 46 happiness units by sitting next to Alice.
+David would lose 7 happiness units by sitting next to Bob.
+David would gain 41 happiness units by sitting next to Carol.";
+        let (part1, _) = solve(input);
+        assert_eq!(part1.to_string(), "330");
+    }
 }
@@ -1,132 +1,54 @@
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut lines = input.lines();
-    let hitpoints = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Hit Points: ")
-        .parse::<i32>()
-        .unwrap();
-    let damage = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Damage: ")
-        .parse::<i32>()
+use crate::common::ParseError;
+
+use super::rpg::Fighter;
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let boss = Fighter::parse(input)?;
+    let loadouts = loadouts();
+
+    let part1 = loadouts
+        .iter()
+        .filter(|(_, player)| player.beats(&boss))
+        .map(|(cost, _)| *cost)
+        .min()
         .unwrap();
-    let armor = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Armor: ")
-        .parse::<i32>()
+    let part2 = loadouts
+        .iter()
+        .filter(|(_, player)| !player.beats(&boss))
+        .map(|(cost, _)| *cost)
+        .max()
         .unwrap();
-    let boss = Character {
-        hitpoints,
-        damage,
-        armor,
-    };
 
-    let mut armors = ARMORS.iter().map(Some).collect::<Vec<_>>();
-    armors.push(None);
-    let mut ring_combos = Vec::new();
-    let mut rings = RINGS.iter().map(Some).collect::<Vec<_>>();
-    rings.push(None);
-    for (i, ring_a) in rings.iter().enumerate() {
-        for (j, ring_b) in rings.iter().enumerate() {
-            if i == j {
-                continue;
-            }
-            ring_combos.push((ring_a, ring_b));
-        }
-    }
-
-    let mut best_win = i32::MAX;
-    for weapon in WEAPONS.iter() {
-        for armor in armors.iter() {
-            for (ring_a, ring_b) in ring_combos.iter() {
-                let mut items = vec![weapon];
-                if let Some(ring_a) = ring_a {
-                    items.push(ring_a);
-                }
-                if let Some(ring_b) = ring_b {
-                    items.push(ring_b);
-                }
-                if let Some(armor) = armor {
-                    items.push(armor);
-                }
-                let player = Character::from_health_and_items(100, &items);
-                if player.fight(&boss) {
-                    let total_cost = items.iter().map(|item| item.cost).sum();
-                    if total_cost < best_win {
-                        best_win = total_cost;
-                    }
-                }
-            }
-        }
-    }
-    let part1 = best_win;
+    Ok((part1, part2))
+}
 
-    let mut worst_loss = i32::MIN;
+/// The cost of each allowed set of items and the player they make: one weapon, up to one
+/// armor and up to two different rings
+fn loadouts() -> Vec<(i32, Fighter)> {
+    let armors = ARMORS.iter().map(Some).chain([None]).collect::<Vec<_>>();
+    let rings = RINGS.iter().map(Some).chain([None]).collect::<Vec<_>>();
+    let mut loadouts = Vec::new();
     for weapon in WEAPONS.iter() {
         for armor in armors.iter() {
-            for (ring_a, ring_b) in ring_combos.iter() {
-                let mut items = vec![weapon];
-                if let Some(ring_a) = ring_a {
-                    items.push(ring_a);
+            for (i, ring_a) in rings.iter().enumerate() {
+                // Two empty ring slots are fine, two of the same ring aren't
+                for ring_b in rings[i + 1..]
+                    .iter()
+                    .chain(ring_a.is_none().then_some(&None))
+                {
+                    let items = [Some(weapon), *armor, *ring_a, *ring_b];
+                    let items = items.iter().flatten();
+                    let player = Fighter {
+                        hitpoints: 100,
+                        damage: items.clone().map(|item| item.damage).sum(),
+                        armor: items.clone().map(|item| item.armor).sum(),
+                    };
+                    loadouts.push((items.map(|item| item.cost).sum(), player));
                 }
-                if let Some(ring_b) = ring_b {
-                    items.push(ring_b);
-                }
-                if let Some(armor) = armor {
-                    items.push(armor);
-                }
-                let player = Character::from_health_and_items(100, &items);
-                if !player.fight(&boss) {
-                    let total_cost = items.iter().map(|item| item.cost).sum();
-                    if total_cost > worst_loss {
-                        worst_loss = total_cost;
-                    }
-                }
-            }
-        }
-    }
-    let part2 = worst_loss;
-    (part1, part2)
-}
-
-#[derive(Debug)]
-struct Character {
-    hitpoints: i32,
-    damage: i32,
-    armor: i32,
-}
-
-impl Character {
-    pub fn from_health_and_items(hitpoints: i32, items: &[&Item]) -> Self {
-        let (damage, armor) = items.iter().fold((0, 0), |acc, item| {
-            (acc.0 + item.damage, acc.1 + item.armor)
-        });
-        Self {
-            hitpoints,
-            damage,
-            armor,
-        }
-    }
-
-    pub fn fight(&self, other: &Character) -> bool {
-        let mut own_health = self.hitpoints;
-        let mut other_health = other.hitpoints;
-        loop {
-            let damage_done = (self.damage - other.armor).max(1);
-            other_health -= damage_done;
-            if other_health <= 0 {
-                return true;
-            }
-            let damage_taken = (other.damage - self.armor).max(1);
-            own_health -= damage_taken;
-            if own_health <= 0 {
-                return false;
             }
         }
     }
+    loadouts
 }
 
 #[derive(Debug)]
@@ -225,20 +147,35 @@ const RINGS: [Item; 6] = [
 
 #[cfg(test)]
 mod tests {
+    use super::super::rpg::{Action, Fight, Rules};
     use super::*;
 
     #[test]
     fn test_fight() {
-        let player = Character {
+        let player = Fighter {
             hitpoints: 8,
             damage: 5,
             armor: 5,
         };
-        let boss = Character {
+        let boss = Fighter {
             hitpoints: 12,
             damage: 7,
             armor: 2,
         };
-        assert!(player.fight(&boss));
+        assert!(player.beats(&boss));
+        assert_eq!(player.hits_to_kill(&boss), 4);
+
+        let rules = Rules::weapons();
+        let fight = Fight::new(&rules, player, 0, boss);
+        assert_eq!(
+            fight.narrate(&rules, &[Action::Attack; 4]).unwrap(),
+            "The player deals 5-2 = 3 damage; the boss goes down to 9 hit points.
+The boss deals 7-5 = 2 damage; the player goes down to 6 hit points.
+The player deals 5-2 = 3 damage; the boss goes down to 6 hit points.
+The boss deals 7-5 = 2 damage; the player goes down to 4 hit points.
+The player deals 5-2 = 3 damage; the boss goes down to 3 hit points.
+The boss deals 7-5 = 2 damage; the player goes down to 2 hit points.
+The player deals 5-2 = 3 damage; the boss goes down to 0 hit points."
+        );
     }
 }
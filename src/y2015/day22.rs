@@ -1,263 +1,153 @@
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut lines = input.lines();
-    let boss_hitpoints = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Hit Points: ")
-        .parse::<i32>()
-        .unwrap();
-    let boss_damage = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Damage: ")
-        .parse::<u32>()
-        .unwrap();
+use crate::common::ParseError;
 
-    let initial_state = FightState {
-        turn: Turn::Player,
+use super::rpg::{Fight, Fighter, Rules};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let boss = Fighter::parse(input)?;
+    let player = Fighter {
         hitpoints: 50,
-        mana: 500,
-        mana_spent: 0,
-        shield_timer: 0,
-        poison_timer: 0,
-        recharge_timer: 0,
-        boss_hitpoints,
+        damage: 0,
+        armor: 0,
+    };
+    let least_mana = |rules: Rules| {
+        let fight = Fight::new(&rules, player, 500, boss);
+        fight.cheapest_win(&rules).unwrap().mana
     };
-    let mut states = vec![initial_state];
-    let mut lowest_mana_win = u32::MAX;
-    while let Some(mut state) = states.pop() {
-        if state.mana_spent > lowest_mana_win {
-            continue;
-        }
-        if state.boss_hitpoints <= 0 {
-            if state.mana_spent < lowest_mana_win {
-                lowest_mana_win = state.mana_spent;
-            }
-            continue;
-        }
-
-        if state.shield_timer > 0 {
-            state.shield_timer -= 1;
-        }
-        if state.poison_timer > 0 {
-            state.poison_timer -= 1;
-            state.boss_hitpoints -= 3;
-        }
-        if state.recharge_timer > 0 {
-            state.recharge_timer -= 1;
-            state.mana += 101;
-        }
 
-        if state.boss_hitpoints <= 0 {
-            if state.mana_spent < lowest_mana_win {
-                lowest_mana_win = state.mana_spent;
-            }
-            continue;
-        }
+    let part1 = least_mana(Rules::wizard());
+    let part2 = least_mana(Rules::wizard().drain(1));
 
-        match state.turn {
-            Turn::Player => {
-                state.turn = Turn::Boss;
-                if state.mana < 53 {
-                    continue;
-                }
-                if state.mana >= 53 {
-                    let mut new_state = state.clone();
-                    new_state.boss_hitpoints -= 4;
-                    new_state.mana -= 53;
-                    new_state.mana_spent += 53;
-                    states.push(new_state);
-                }
-                if state.mana >= 73 {
-                    let mut new_state = state.clone();
-                    new_state.boss_hitpoints -= 2;
-                    new_state.hitpoints += 2;
-                    new_state.mana -= 73;
-                    new_state.mana_spent += 73;
-                    states.push(new_state);
-                }
-                if state.mana >= 113 && state.shield_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.shield_timer = 6;
-                    new_state.mana -= 113;
-                    new_state.mana_spent += 113;
-                    states.push(new_state);
-                }
-                if state.mana >= 173 && state.poison_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.poison_timer = 6;
-                    new_state.mana -= 173;
-                    new_state.mana_spent += 173;
-                    states.push(new_state);
-                }
-                if state.mana >= 229 && state.recharge_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.recharge_timer = 5;
-                    new_state.mana -= 229;
-                    new_state.mana_spent += 229;
-                    states.push(new_state);
-                }
-            }
-            Turn::Boss => {
-                let damage = if state.shield_timer > 0 {
-                    (boss_damage - 7).max(1)
-                } else {
-                    boss_damage
-                };
-                state.hitpoints -= damage as i32;
-                if state.hitpoints <= 0 {
-                    continue;
-                }
-                state.turn = Turn::Player;
-                states.push(state);
-            }
-        }
-    }
-    let part1 = lowest_mana_win;
+    Ok((part1, part2))
+}
 
-    let mut lines = input.lines();
-    let boss_hitpoints = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Hit Points: ")
-        .parse::<i32>()
-        .unwrap();
-    let boss_damage = lines
-        .next()
-        .unwrap()
-        .trim_start_matches("Damage: ")
-        .parse::<u32>()
-        .unwrap();
+#[cfg(test)]
+mod tests {
+    use super::super::rpg::Action;
+    use super::*;
 
-    let initial_state = FightState {
-        turn: Turn::Player,
-        hitpoints: 50,
-        mana: 500,
-        mana_spent: 0,
-        shield_timer: 0,
-        poison_timer: 0,
-        recharge_timer: 0,
-        boss_hitpoints,
+    const PLAYER: Fighter = Fighter {
+        hitpoints: 10,
+        damage: 0,
+        armor: 0,
     };
-    let mut states = vec![initial_state];
-    let mut lowest_mana_win = u32::MAX;
-    while let Some(mut state) = states.pop() {
-        if state.mana_spent >= lowest_mana_win {
-            continue;
-        }
-        if state.boss_hitpoints <= 0 {
-            if state.mana_spent < lowest_mana_win {
-                lowest_mana_win = state.mana_spent;
-            }
-            continue;
-        }
-        if state.turn == Turn::Player {
-            state.hitpoints -= 1;
-            if state.hitpoints <= 0 {
-                continue;
-            }
-        }
 
-        if state.shield_timer > 0 {
-            state.shield_timer -= 1;
-        }
-        if state.poison_timer > 0 {
-            state.poison_timer -= 1;
-            state.boss_hitpoints -= 3;
-        }
-        if state.recharge_timer > 0 {
-            state.recharge_timer -= 1;
-            state.mana += 101;
-        }
+    #[test]
+    fn test_first_example() {
+        let boss = Fighter {
+            hitpoints: 13,
+            damage: 8,
+            armor: 0,
+        };
+        let rules = Rules::wizard();
+        let fight = Fight::new(&rules, PLAYER, 250, boss);
+        let win = fight.cheapest_win(&rules).unwrap();
+        assert_eq!(win.mana, 226);
+        assert_eq!(win.actions, [Action::Cast(3), Action::Cast(0)]);
+        assert_eq!(
+            fight.narrate(&rules, &win.actions).unwrap(),
+            "-- Player turn --
+- Player has 10 hit points, 0 armor, 250 mana
+- Boss has 13 hit points
+Player casts Poison.
 
-        if state.boss_hitpoints <= 0 {
-            if state.mana_spent < lowest_mana_win {
-                lowest_mana_win = state.mana_spent;
-            }
-            continue;
-        }
+-- Boss turn --
+- Player has 10 hit points, 0 armor, 77 mana
+- Boss has 13 hit points
+Poison deals 3 damage; its timer is now 5.
+Boss attacks for 8 damage.
 
-        match state.turn {
-            Turn::Player => {
-                state.turn = Turn::Boss;
-                if state.mana < 53 {
-                    continue;
-                }
-                if state.mana >= 53 {
-                    let mut new_state = state.clone();
-                    new_state.boss_hitpoints -= 4;
-                    new_state.mana -= 53;
-                    new_state.mana_spent += 53;
-                    states.push(new_state);
-                }
-                if state.mana >= 73 {
-                    let mut new_state = state.clone();
-                    new_state.boss_hitpoints -= 2;
-                    new_state.hitpoints += 2;
-                    new_state.mana -= 73;
-                    new_state.mana_spent += 73;
-                    states.push(new_state);
-                }
-                if state.mana >= 113 && state.shield_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.shield_timer = 6;
-                    new_state.mana -= 113;
-                    new_state.mana_spent += 113;
-                    states.push(new_state);
-                }
-                if state.mana >= 173 && state.poison_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.poison_timer = 6;
-                    new_state.mana -= 173;
-                    new_state.mana_spent += 173;
-                    states.push(new_state);
-                }
-                if state.mana >= 229 && state.recharge_timer == 0 {
-                    let mut new_state = state.clone();
-                    new_state.recharge_timer = 5;
-                    new_state.mana -= 229;
-                    new_state.mana_spent += 229;
-                    states.push(new_state);
-                }
-            }
-            Turn::Boss => {
-                let damage = if state.shield_timer > 0 {
-                    (boss_damage - 7).max(1)
-                } else {
-                    boss_damage
-                };
-                state.hitpoints -= damage as i32;
-                if state.hitpoints <= 0 {
-                    continue;
-                }
-                state.turn = Turn::Player;
-                states.push(state);
-            }
-        }
+-- Player turn --
+- Player has 2 hit points, 0 armor, 77 mana
+- Boss has 10 hit points
+Poison deals 3 damage; its timer is now 4.
+Player casts Magic Missile, dealing 4 damage.
+
+-- Boss turn --
+- Player has 2 hit points, 0 armor, 24 mana
+- Boss has 3 hit points
+Poison deals 3 damage. This kills the boss, and the player wins."
+        );
     }
-    let part2 = lowest_mana_win;
 
-    (part1, part2)
-}
+    #[test]
+    fn test_second_example() {
+        let boss = Fighter {
+            hitpoints: 14,
+            damage: 8,
+            armor: 0,
+        };
+        let rules = Rules::wizard();
+        let fight = Fight::new(&rules, PLAYER, 250, boss);
+        let actions = [4, 2, 1, 3, 0].map(Action::Cast);
+        assert_eq!(
+            fight.narrate(&rules, &actions).unwrap(),
+            "-- Player turn --
+- Player has 10 hit points, 0 armor, 250 mana
+- Boss has 14 hit points
+Player casts Recharge.
 
-#[derive(Debug, Clone)]
-struct FightState {
-    turn: Turn,
+-- Boss turn --
+- Player has 10 hit points, 0 armor, 21 mana
+- Boss has 14 hit points
+Recharge provides 101 mana; its timer is now 4.
+Boss attacks for 8 damage.
 
-    // Player
-    hitpoints: i32,
-    mana: u32,
-    mana_spent: u32,
-    shield_timer: u32,
-    poison_timer: u32,
-    recharge_timer: u32,
+-- Player turn --
+- Player has 2 hit points, 0 armor, 122 mana
+- Boss has 14 hit points
+Recharge provides 101 mana; its timer is now 3.
+Player casts Shield, increasing armor by 7.
 
-    // Enemy
-    boss_hitpoints: i32,
-}
+-- Boss turn --
+- Player has 2 hit points, 7 armor, 110 mana
+- Boss has 14 hit points
+Shield's timer is now 5.
+Recharge provides 101 mana; its timer is now 2.
+Boss attacks for 8 - 7 = 1 damage!
+
+-- Player turn --
+- Player has 1 hit point, 7 armor, 211 mana
+- Boss has 14 hit points
+Shield's timer is now 4.
+Recharge provides 101 mana; its timer is now 1.
+Player casts Drain, dealing 2 damage, and healing 2 hit points.
+
+-- Boss turn --
+- Player has 3 hit points, 7 armor, 239 mana
+- Boss has 12 hit points
+Shield's timer is now 3.
+Recharge provides 101 mana; its timer is now 0.
+Recharge wears off.
+Boss attacks for 8 - 7 = 1 damage!
+
+-- Player turn --
+- Player has 2 hit points, 7 armor, 340 mana
+- Boss has 12 hit points
+Shield's timer is now 2.
+Player casts Poison.
 
-#[derive(Debug, Clone, PartialEq)]
-enum Turn {
-    Player,
-    Boss,
+-- Boss turn --
+- Player has 2 hit points, 7 armor, 167 mana
+- Boss has 12 hit points
+Shield's timer is now 1.
+Poison deals 3 damage; its timer is now 5.
+Boss attacks for 8 - 7 = 1 damage!
+
+-- Player turn --
+- Player has 1 hit point, 7 armor, 167 mana
+- Boss has 9 hit points
+Shield's timer is now 0.
+Shield wears off, decreasing armor by 7.
+Poison deals 3 damage; its timer is now 4.
+Player casts Magic Missile, dealing 4 damage.
+
+-- Boss turn --
+- Player has 1 hit point, 0 armor, 114 mana
+- Boss has 2 hit points
+Poison deals 3 damage. This kills the boss, and the player wins."
+        );
+        // Casting Poison while it's still going isn't allowed
+        let recast = [3, 3].map(Action::Cast);
+        assert_eq!(fight.narrate(&rules, &recast), None);
+    }
 }
@@ -1,54 +1,19 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Write},
+    str::FromStr,
+};
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let circuit = input.parse::<Circuit>().unwrap();
-    let mut dest_map = HashMap::new();
-    for instruction in circuit.instructions.iter() {
-        let dest = match instruction {
-            Instruction::Set { dest, .. }
-            | Instruction::Not { dest, .. }
-            | Instruction::And { dest, .. }
-            | Instruction::Or { dest, .. }
-            | Instruction::LShift { dest, .. }
-            | Instruction::RShift { dest, .. } => dest,
-        };
-        dest_map.insert(dest, instruction);
-    }
-
-    let mut calculated_values = HashMap::<String, u16>::new();
-    let part1 = Circuit::calc_source(
-        &Source::Wire("a".to_string()),
-        &dest_map,
-        &mut calculated_values,
-    );
-
-    let mut dest_map = HashMap::new();
-    for instruction in circuit.instructions.iter() {
-        let dest = match instruction {
-            Instruction::Set { dest, .. }
-            | Instruction::Not { dest, .. }
-            | Instruction::And { dest, .. }
-            | Instruction::Or { dest, .. }
-            | Instruction::LShift { dest, .. }
-            | Instruction::RShift { dest, .. } => dest,
-        };
-        dest_map.insert(dest, instruction);
-    }
+    let mut circuit = input
+        .parse::<Circuit>()
+        .unwrap()
+        .compile()
+        .unwrap_or_else(|error| panic!("{error}"));
 
-    let mut calculated_values = HashMap::<String, u16>::new();
-    let a_value = Circuit::calc_source(
-        &Source::Wire("a".to_string()),
-        &dest_map,
-        &mut calculated_values,
-    );
-
-    let mut calculated_values = HashMap::<String, u16>::new();
-    calculated_values.insert("b".to_string(), a_value);
-    let part2 = Circuit::calc_source(
-        &Source::Wire("a".to_string()),
-        &dest_map,
-        &mut calculated_values,
-    );
+    let part1 = circuit.value("a").unwrap();
+    circuit.set_override("b", part1).unwrap();
+    let part2 = circuit.value("a").unwrap();
 
     (part1, part2)
 }
@@ -57,50 +22,339 @@ struct Circuit {
     instructions: Vec<Instruction>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WireIndex(usize);
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Value(u16),
+    Wire(WireIndex),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum GateKind {
+    Set,
+    Not,
+    And,
+    Or,
+    LShift(u16),
+    RShift(u16),
+}
+
+#[derive(Debug)]
+struct Gate {
+    kind: GateKind,
+    inputs: Vec<Operand>,
+    output: WireIndex,
+}
+
+/// A circuit with its gates in topological order and every wire's value worked out
+#[derive(Debug)]
+struct CompiledCircuit {
+    wire_indexes: HashMap<String, WireIndex>,
+    gates: Vec<Gate>,
+    /// For each wire, the gates that read it
+    consumers: Vec<Vec<usize>>,
+    overrides: Vec<Option<u16>>,
+    values: Vec<u16>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CircuitError {
+    UnknownWire(String),
+    /// A wire that's read but nothing drives
+    Undriven(String),
+    DrivenTwice(String),
+    /// The wires around a loop, starting and ending on the same one
+    Cycle(Vec<String>),
+}
+
 impl Circuit {
-    fn calc_source(
-        source: &Source,
-        dest_map: &HashMap<&String, &Instruction>,
-        calculated_values: &mut HashMap<String, u16>,
-    ) -> u16 {
-        match source {
-            Source::Value(value) => *value,
-            Source::Wire(wire) => {
-                if calculated_values.contains_key(wire) {
-                    calculated_values[wire]
-                } else {
-                    let instr = dest_map[wire];
-                    let value = match instr {
-                        Instruction::Set { source, .. } => {
-                            Self::calc_source(source, dest_map, calculated_values)
-                        }
-                        Instruction::Not { source, .. } => {
-                            !Self::calc_source(source, dest_map, calculated_values)
-                        }
-                        Instruction::And {
-                            source_a, source_b, ..
-                        } => {
-                            Self::calc_source(source_a, dest_map, calculated_values)
-                                & Self::calc_source(source_b, dest_map, calculated_values)
-                        }
-                        Instruction::Or {
-                            source_a, source_b, ..
-                        } => {
-                            Self::calc_source(source_a, dest_map, calculated_values)
-                                | Self::calc_source(source_b, dest_map, calculated_values)
-                        }
-                        Instruction::LShift { source, shift, .. } => {
-                            Self::calc_source(source, dest_map, calculated_values) << shift
-                        }
-                        Instruction::RShift { source, shift, .. } => {
-                            Self::calc_source(source, dest_map, calculated_values) >> shift
-                        }
-                    };
-                    calculated_values.insert(wire.to_string(), value);
-                    value
+    fn compile(&self) -> Result<CompiledCircuit, CircuitError> {
+        let mut wire_names = Vec::new();
+        let mut wire_indexes = HashMap::new();
+        let mut wire = |name: &str| {
+            *wire_indexes.entry(name.to_string()).or_insert_with(|| {
+                wire_names.push(name.to_string());
+                WireIndex(wire_names.len() - 1)
+            })
+        };
+        let mut operand = |source: &Source| match source {
+            Source::Value(value) => Operand::Value(*value),
+            Source::Wire(name) => Operand::Wire(wire(name)),
+        };
+
+        let mut unsorted = Vec::new();
+        for instruction in self.instructions.iter() {
+            let (kind, inputs, dest) = match instruction {
+                Instruction::Set { source, dest } => (GateKind::Set, vec![operand(source)], dest),
+                Instruction::Not { source, dest } => (GateKind::Not, vec![operand(source)], dest),
+                Instruction::And {
+                    source_a,
+                    source_b,
+                    dest,
+                } => (
+                    GateKind::And,
+                    vec![operand(source_a), operand(source_b)],
+                    dest,
+                ),
+                Instruction::Or {
+                    source_a,
+                    source_b,
+                    dest,
+                } => (
+                    GateKind::Or,
+                    vec![operand(source_a), operand(source_b)],
+                    dest,
+                ),
+                Instruction::LShift {
+                    source,
+                    shift,
+                    dest,
+                } => (GateKind::LShift(*shift), vec![operand(source)], dest),
+                Instruction::RShift {
+                    source,
+                    shift,
+                    dest,
+                } => (GateKind::RShift(*shift), vec![operand(source)], dest),
+            };
+            let output = match operand(&Source::Wire(dest.clone())) {
+                Operand::Wire(output) => output,
+                Operand::Value(_) => unreachable!(),
+            };
+            unsorted.push(Gate {
+                kind,
+                inputs,
+                output,
+            });
+        }
+
+        let mut drivers = vec![None; wire_names.len()];
+        for (i, gate) in unsorted.iter().enumerate() {
+            if drivers[gate.output.0].replace(i).is_some() {
+                return Err(CircuitError::DrivenTwice(wire_names[gate.output.0].clone()));
+            }
+        }
+        let drivers = drivers
+            .into_iter()
+            .enumerate()
+            .map(|(wire, driver)| {
+                driver.ok_or_else(|| CircuitError::Undriven(wire_names[wire].clone()))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+
+        // Kahn's algorithm over gates, a gate being ready once all its input wires are driven
+        let mut waiting_on = unsorted
+            .iter()
+            .map(|gate| gate.input_wires().count())
+            .collect::<Vec<_>>();
+        let mut readers = vec![Vec::new(); wire_names.len()];
+        for (i, gate) in unsorted.iter().enumerate() {
+            for input in gate.input_wires() {
+                readers[input.0].push(i);
+            }
+        }
+        let mut ready = (0..unsorted.len())
+            .filter(|i| waiting_on[*i] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::new();
+        while let Some(i) = ready.pop_front() {
+            order.push(i);
+            for reader in readers[unsorted[i].output.0].iter() {
+                waiting_on[*reader] -= 1;
+                if waiting_on[*reader] == 0 {
+                    ready.push_back(*reader);
+                }
+            }
+        }
+        if order.len() < unsorted.len() {
+            // Everything left is on or behind a cycle, so walking back through any unsorted
+            // gate's inputs has to come round to a wire seen before
+            let mut path = Vec::<WireIndex>::new();
+            let mut gate = (0..unsorted.len()).find(|i| waiting_on[*i] != 0).unwrap();
+            loop {
+                let output = unsorted[gate].output;
+                if let Some(start) = path.iter().position(|wire| *wire == output) {
+                    let mut cycle = path[start..]
+                        .iter()
+                        .rev()
+                        .map(|wire| wire_names[wire.0].clone())
+                        .collect::<Vec<_>>();
+                    cycle.push(cycle[0].clone());
+                    return Err(CircuitError::Cycle(cycle));
+                }
+                path.push(output);
+                let input = unsorted[gate]
+                    .input_wires()
+                    .find(|input| waiting_on[drivers[input.0]] != 0)
+                    .unwrap();
+                gate = drivers[input.0];
+            }
+        }
+
+        let mut gates = unsorted.into_iter().map(Some).collect::<Vec<_>>();
+        let gates = order
+            .into_iter()
+            .map(|i| gates[i].take().unwrap())
+            .collect::<Vec<_>>();
+        let mut consumers = vec![Vec::new(); wire_names.len()];
+        for (i, gate) in gates.iter().enumerate() {
+            for input in gate.input_wires() {
+                consumers[input.0].push(i);
+            }
+        }
+
+        let mut circuit = CompiledCircuit {
+            overrides: vec![None; wire_names.len()],
+            values: vec![0; wire_names.len()],
+            wire_indexes,
+            gates,
+            consumers,
+        };
+        for i in 0..circuit.gates.len() {
+            circuit.evaluate_gate(i);
+        }
+        Ok(circuit)
+    }
+}
+
+impl Gate {
+    fn input_wires(&self) -> impl Iterator<Item = WireIndex> + '_ {
+        self.inputs.iter().filter_map(|input| match input {
+            Operand::Wire(wire) => Some(*wire),
+            Operand::Value(_) => None,
+        })
+    }
+
+    fn apply(&self, values: &[u16]) -> u16 {
+        let input = |i: usize| match self.inputs[i] {
+            Operand::Value(value) => value,
+            Operand::Wire(wire) => values[wire.0],
+        };
+        match self.kind {
+            GateKind::Set => input(0),
+            GateKind::Not => !input(0),
+            GateKind::And => input(0) & input(1),
+            GateKind::Or => input(0) | input(1),
+            GateKind::LShift(shift) => input(0) << shift,
+            GateKind::RShift(shift) => input(0) >> shift,
+        }
+    }
+}
+
+impl CompiledCircuit {
+    fn wire(&self, name: &str) -> Result<WireIndex, CircuitError> {
+        self.wire_indexes
+            .get(name)
+            .copied()
+            .ok_or_else(|| CircuitError::UnknownWire(name.to_string()))
+    }
+
+    fn value(&self, name: &str) -> Result<u16, CircuitError> {
+        Ok(self.values[self.wire(name)?.0])
+    }
+
+    /// Updates the gate's output, returning whether it changed
+    fn evaluate_gate(&mut self, i: usize) -> bool {
+        let gate = &self.gates[i];
+        let value = self.overrides[gate.output.0].unwrap_or_else(|| gate.apply(&self.values));
+        let changed = self.values[gate.output.0] != value;
+        self.values[gate.output.0] = value;
+        changed
+    }
+
+    /// Forces `name` to `value` regardless of what drives it, returning how many gates were
+    /// re-evaluated
+    fn set_override(&mut self, name: &str, value: u16) -> Result<usize, CircuitError> {
+        let wire = self.wire(name)?;
+        self.overrides[wire.0] = Some(value);
+        Ok(self.propagate(wire))
+    }
+
+    #[allow(dead_code)]
+    fn clear_override(&mut self, name: &str) -> Result<usize, CircuitError> {
+        let wire = self.wire(name)?;
+        self.overrides[wire.0] = None;
+        Ok(self.propagate(wire))
+    }
+
+    /// Re-evaluates `wire`'s gate and then only the gates downstream of a change. Gates are in
+    /// topological order so one pass in that order is enough
+    fn propagate(&mut self, wire: WireIndex) -> usize {
+        let driver = self
+            .gates
+            .iter()
+            .position(|gate| gate.output == wire)
+            .unwrap();
+        let mut dirty = vec![false; self.gates.len()];
+        dirty[driver] = true;
+        let mut evaluated = 0;
+        for i in driver..self.gates.len() {
+            if !dirty[i] {
+                continue;
+            }
+            evaluated += 1;
+            if self.evaluate_gate(i) {
+                for consumer in self.consumers[self.gates[i].output.0].iter() {
+                    dirty[*consumer] = true;
                 }
             }
         }
+        evaluated
+    }
+
+    /// The gate graph in Graphviz's DOT language, with wires as ellipses and gates as boxes
+    #[allow(dead_code)]
+    fn to_dot(&self) -> String {
+        let mut wire_names = vec![""; self.values.len()];
+        for (name, wire) in self.wire_indexes.iter() {
+            wire_names[wire.0] = name;
+        }
+        let mut dot = String::from("digraph circuit {\n");
+        for name in wire_names.iter() {
+            writeln!(dot, "    \"{name}\";").unwrap();
+        }
+        for (i, gate) in self.gates.iter().enumerate() {
+            let label = match gate.kind {
+                GateKind::Set => String::new(),
+                GateKind::Not => "NOT".to_string(),
+                GateKind::And => "AND".to_string(),
+                GateKind::Or => "OR".to_string(),
+                GateKind::LShift(shift) => format!("LSHIFT {shift}"),
+                GateKind::RShift(shift) => format!("RSHIFT {shift}"),
+            };
+            let constants = gate.inputs.iter().filter_map(|input| match input {
+                Operand::Value(value) => Some(value.to_string()),
+                Operand::Wire(_) => None,
+            });
+            let label = [label]
+                .into_iter()
+                .chain(constants)
+                .filter(|s| !s.is_empty());
+            writeln!(
+                dot,
+                "    gate{i} [shape=box, label=\"{}\"];",
+                label.collect::<Vec<_>>().join(" ")
+            )
+            .unwrap();
+            for input in gate.input_wires() {
+                writeln!(dot, "    \"{}\" -> gate{i};", wire_names[input.0]).unwrap();
+            }
+            writeln!(dot, "    gate{i} -> \"{}\";", wire_names[gate.output.0]).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Display for CircuitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitError::UnknownWire(name) => write!(f, "no wire named {name}"),
+            CircuitError::Undriven(name) => write!(f, "nothing drives wire {name}"),
+            CircuitError::DrivenTwice(name) => write!(f, "wire {name} is driven more than once"),
+            CircuitError::Cycle(wires) => write!(f, "wires form a cycle: {}", wires.join(" -> ")),
+        }
     }
 }
 
@@ -213,3 +467,93 @@ impl FromStr for Source {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "123 -> x
+456 -> y
+x AND y -> d
+x OR y -> e
+x LSHIFT 2 -> f
+y RSHIFT 2 -> g
+NOT x -> h
+NOT y -> i";
+
+    #[test]
+    fn test_example() {
+        let mut circuit = EXAMPLE.parse::<Circuit>().unwrap().compile().unwrap();
+        let expected = [
+            ("d", 72),
+            ("e", 507),
+            ("f", 492),
+            ("g", 114),
+            ("h", 65412),
+            ("i", 65079),
+            ("x", 123),
+            ("y", 456),
+        ];
+        for (wire, value) in expected {
+            assert_eq!(circuit.value(wire), Ok(value));
+        }
+        assert_eq!(
+            circuit.value("z"),
+            Err(CircuitError::UnknownWire("z".to_string()))
+        );
+
+        // Only the override and the gates reading y are recomputed
+        assert_eq!(circuit.set_override("y", 0), Ok(5));
+        assert_eq!(circuit.value("d"), Ok(0));
+        assert_eq!(circuit.value("i"), Ok(65535));
+        assert_eq!(circuit.value("h"), Ok(65412));
+        assert_eq!(circuit.clear_override("y"), Ok(5));
+        assert_eq!(circuit.value("d"), Ok(72));
+
+        // Overriding to the same value doesn't go any further
+        assert_eq!(circuit.set_override("x", 123), Ok(1));
+    }
+
+    #[test]
+    fn test_errors() {
+        let compile = |input: &str| input.parse::<Circuit>().unwrap().compile().map(|_| ());
+        assert_eq!(
+            compile("1 -> a\na AND c -> b\nb -> c\nNOT c -> d"),
+            Err(CircuitError::Cycle(vec![
+                "c".to_string(),
+                "b".to_string(),
+                "c".to_string()
+            ]))
+        );
+        assert_eq!(
+            compile("x -> a"),
+            Err(CircuitError::Undriven("x".to_string()))
+        );
+        assert_eq!(
+            compile("1 -> a\n2 -> a"),
+            Err(CircuitError::DrivenTwice("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let circuit = "1 -> x\nx LSHIFT 2 -> y"
+            .parse::<Circuit>()
+            .unwrap()
+            .compile()
+            .unwrap();
+        assert_eq!(
+            circuit.to_dot(),
+            "digraph circuit {
+    \"x\";
+    \"y\";
+    gate0 [shape=box, label=\"1\"];
+    gate0 -> \"x\";
+    gate1 [shape=box, label=\"LSHIFT 2\"];
+    \"x\" -> gate1;
+    gate1 -> \"y\";
+}
+"
+        );
+    }
+}
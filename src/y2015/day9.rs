@@ -1,116 +1,48 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
-use rayon::prelude::*;
+use crate::common::{Objective, Tsp, Weights};
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     // Parse
-    let mut locations = HashSet::new();
-    let mut distances: HashMap<String, HashMap<String, u32>> = HashMap::new();
+    let mut locations = HashMap::new();
+    let mut distances = Vec::new();
     for line in input.lines() {
         let parts = line.split(' ').collect::<Vec<_>>();
-        let from = parts[0];
-        let to = parts[2];
-        let dist = parts[4].parse::<u32>().unwrap();
-
-        locations.insert(from.to_string());
-        locations.insert(to.to_string());
-
-        distances
-            .entry(from.to_string())
-            .or_default()
-            .insert(to.to_string(), dist);
-        distances
-            .entry(to.to_string())
-            .or_default()
-            .insert(from.to_string(), dist);
+        let mut index = |name| {
+            let next = locations.len();
+            *locations.entry(name).or_insert(next)
+        };
+        let from = index(parts[0]);
+        let to = index(parts[2]);
+        let dist = parts[4].parse::<i64>().unwrap();
+        distances.push((from, to, dist));
     }
 
-    // Brute force
-    let part1 = locations
-        .par_iter()
-        .map(|start| {
-            let initial_state = SearchState {
-                visited: vec![start],
-                total: 0,
-            };
-            let mut states = vec![initial_state];
-            let mut best = u32::MAX;
-            while let Some(current_state) = states.pop() {
-                if current_state.visited.len() == locations.len() {
-                    // Check result
-                    if current_state.total < best {
-                        best = current_state.total;
-                    }
-                    continue;
-                }
-
-                let can_visit = distances[current_state.last_location()]
-                    .iter()
-                    .filter(|(other, _)| !current_state.visited.contains(&other.as_str()))
-                    .collect::<Vec<_>>();
-                for (other, dist) in can_visit {
-                    let mut visited = current_state.visited.clone();
-                    visited.push(other);
-                    let new_state = SearchState {
-                        visited,
-                        total: current_state.total + dist,
-                    };
-                    states.push(new_state);
-                }
-            }
-            best
-        })
-        .min()
-        .unwrap();
-
-    // Brute force
-    let part2 = locations
-        .par_iter()
-        .map(|start| {
-            let initial_state = SearchState {
-                visited: vec![start],
-                total: 0,
-            };
-            let mut states = vec![initial_state];
-            let mut best = u32::MIN;
-            while let Some(current_state) = states.pop() {
-                if current_state.visited.len() == locations.len() {
-                    // Check result
-                    if current_state.total > best {
-                        best = current_state.total;
-                    }
-                    continue;
-                }
+    let mut weights: Weights = vec![vec![None; locations.len()]; locations.len()];
+    for (from, to, dist) in distances {
+        weights[from][to] = Some(dist);
+        weights[to][from] = Some(dist);
+    }
 
-                let can_visit = distances[current_state.last_location()]
-                    .iter()
-                    .filter(|(other, _)| !current_state.visited.contains(&other.as_str()))
-                    .collect::<Vec<_>>();
-                for (other, dist) in can_visit {
-                    let mut visited = current_state.visited.clone();
-                    visited.push(other);
-                    let new_state = SearchState {
-                        visited,
-                        total: current_state.total + dist,
-                    };
-                    states.push(new_state);
-                }
-            }
-            best
-        })
-        .max()
-        .unwrap();
+    // Santa can start and finish anywhere
+    let tsp = Tsp::new(weights);
+    let part1 = tsp.clone().solve().unwrap().total;
+    let part2 = tsp.objective(Objective::Longest).solve().unwrap().total;
 
     (part1, part2)
 }
 
-struct SearchState<'a> {
-    visited: Vec<&'a str>,
-    total: u32,
-}
-
-impl<'a> SearchState<'a> {
-    fn last_location(&self) -> &'a str {
-        self.visited.last().unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = "London to Dublin = 464
+London to Belfast = 518
+Dublin to Belfast = 141";
+        let (part1, part2) = solve(input);
+        assert_eq!(part1.to_string(), "605");
+        assert_eq!(part2.to_string(), "982");
     }
 }
@@ -1 +1,2 @@
 macros::mod_days!(2015);
+mod rpg;
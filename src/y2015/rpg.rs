@@ -0,0 +1,534 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+};
+
+use winnow::{combinator::alt, stream::AsChar, Parser};
+
+use crate::common::{expected, key_values, parse_all, parse_from_str, ParseError};
+
+/// Either side of a fight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Fighter {
+    pub(crate) hitpoints: i32,
+    pub(crate) damage: i32,
+    pub(crate) armor: i32,
+}
+
+/// Something that acts at the start of each turn, both the player's and the boss's, until its
+/// timer runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Effect {
+    pub(crate) turns: u32,
+    pub(crate) armor: i32,
+    pub(crate) damage: i32,
+    pub(crate) mana: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Spell {
+    pub(crate) name: &'static str,
+    pub(crate) cost: i32,
+    pub(crate) damage: i32,
+    pub(crate) heal: i32,
+    pub(crate) effect: Option<Effect>,
+}
+
+pub(crate) const SPELLS: [Spell; 5] = [
+    Spell {
+        name: "Magic Missile",
+        cost: 53,
+        damage: 4,
+        heal: 0,
+        effect: None,
+    },
+    Spell {
+        name: "Drain",
+        cost: 73,
+        damage: 2,
+        heal: 2,
+        effect: None,
+    },
+    Spell {
+        name: "Shield",
+        cost: 113,
+        damage: 0,
+        heal: 0,
+        effect: Some(Effect {
+            turns: 6,
+            armor: 7,
+            damage: 0,
+            mana: 0,
+        }),
+    },
+    Spell {
+        name: "Poison",
+        cost: 173,
+        damage: 0,
+        heal: 0,
+        effect: Some(Effect {
+            turns: 6,
+            armor: 0,
+            damage: 3,
+            mana: 0,
+        }),
+    },
+    Spell {
+        name: "Recharge",
+        cost: 229,
+        damage: 0,
+        heal: 0,
+        effect: Some(Effect {
+            turns: 5,
+            armor: 0,
+            damage: 0,
+            mana: 101,
+        }),
+    },
+];
+
+/// What the player does on their turn. The boss always attacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Action {
+    Attack,
+    /// Index into the rules' spells
+    Cast(usize),
+}
+
+/// What the player can do and what happens to them each turn
+#[derive(Debug, Clone)]
+pub(crate) struct Rules {
+    /// With no spells the player fights with weapons instead
+    spells: Vec<Spell>,
+    /// Hit points the player loses at the start of each of their turns
+    drain: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Ongoing,
+    Won,
+    Lost,
+}
+
+/// Where a fight stands at the start of a player turn
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Fight {
+    player: Fighter,
+    mana: i32,
+    boss: Fighter,
+    /// Turns left on each spell's effect
+    timers: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Win {
+    pub(crate) mana: i32,
+    pub(crate) actions: Vec<Action>,
+}
+
+/// Somewhere to narrate a fight to. `()` ignores it all, so searching doesn't pay for it
+trait Log {
+    fn say(&mut self, line: impl FnOnce() -> String);
+}
+
+impl Log for () {
+    fn say(&mut self, _: impl FnOnce() -> String) {}
+}
+
+impl Log for Vec<String> {
+    fn say(&mut self, line: impl FnOnce() -> String) {
+        self.push(line());
+    }
+}
+
+impl Fighter {
+    /// Reads `Hit Points: 104` style lines, with anything missing left at 0
+    pub(crate) fn parse(input: &str) -> Result<Fighter, ParseError> {
+        let stat = alt(("Hit Points", "Damage", "Armor")).context(expected("stat"));
+        let value = parse_from_str(AsChar::is_dec_digit).context(expected("number"));
+        let mut fighter = Fighter {
+            hitpoints: 0,
+            damage: 0,
+            armor: 0,
+        };
+        for (stat, value) in parse_all(input, key_values(stat, value))? {
+            match stat {
+                "Hit Points" => fighter.hitpoints = value,
+                "Damage" => fighter.damage = value,
+                _ => fighter.armor = value,
+            }
+        }
+        Ok(fighter)
+    }
+
+    /// Hits it takes `self` to bring `other` down with weapons
+    pub(crate) fn hits_to_kill(&self, other: &Fighter) -> i32 {
+        let per_hit = (self.damage - other.armor).max(1);
+        (other.hitpoints - 1) / per_hit + 1
+    }
+
+    /// Whether `self` wins a weapon fight where it hits first, without playing it out
+    pub(crate) fn beats(&self, other: &Fighter) -> bool {
+        self.hits_to_kill(other) <= other.hits_to_kill(self)
+    }
+}
+
+impl Rules {
+    /// Both sides just hit each other
+    #[allow(dead_code)]
+    pub(crate) fn weapons() -> Rules {
+        Rules {
+            spells: Vec::new(),
+            drain: 0,
+        }
+    }
+
+    pub(crate) fn wizard() -> Rules {
+        Rules {
+            spells: SPELLS.to_vec(),
+            drain: 0,
+        }
+    }
+
+    pub(crate) fn drain(self, drain: i32) -> Rules {
+        Rules { drain, ..self }
+    }
+
+    fn is_duel(&self) -> bool {
+        self.spells.is_empty()
+    }
+
+    fn actions(&self) -> Vec<Action> {
+        match self.is_duel() {
+            true => vec![Action::Attack],
+            false => (0..self.spells.len()).map(Action::Cast).collect(),
+        }
+    }
+
+    fn cost(&self, action: Action) -> i32 {
+        match action {
+            Action::Attack => 0,
+            Action::Cast(spell) => self.spells[spell].cost,
+        }
+    }
+}
+
+impl Fight {
+    pub(crate) fn new(rules: &Rules, player: Fighter, mana: i32, boss: Fighter) -> Fight {
+        Fight {
+            player,
+            mana,
+            boss,
+            timers: vec![0; rules.spells.len()],
+        }
+    }
+
+    /// The win spending the least mana, trying cheaper fights first
+    pub(crate) fn cheapest_win(&self, rules: &Rules) -> Option<Win> {
+        // Each fight along with where it came from, to get the actions back at the end
+        let mut fights = vec![(self.clone(), None::<(usize, Action)>)];
+        let mut queue = BinaryHeap::from([Reverse((0, false, 0))]);
+        let mut seen = HashSet::new();
+        while let Some(Reverse((mana, won, index))) = queue.pop() {
+            if won {
+                let mut actions = Vec::new();
+                let mut index = index;
+                while let Some((previous, action)) = fights[index].1 {
+                    actions.push(action);
+                    index = previous;
+                }
+                actions.reverse();
+                return Some(Win { mana, actions });
+            }
+            if !seen.insert(fights[index].0.clone()) {
+                continue;
+            }
+            for action in rules.actions() {
+                let Some((next, outcome)) = fights[index].0.round(rules, action, &mut ()) else {
+                    continue;
+                };
+                if outcome != Outcome::Lost {
+                    fights.push((next, Some((index, action))));
+                    let won = outcome == Outcome::Won;
+                    queue.push(Reverse((mana + rules.cost(action), won, fights.len() - 1)));
+                }
+            }
+        }
+        None
+    }
+
+    /// The play by play of the fight going the way `actions` say, worded like the puzzle's
+    /// examples, or `None` if one of them isn't allowed
+    #[allow(dead_code)]
+    pub(crate) fn narrate(&self, rules: &Rules, actions: &[Action]) -> Option<String> {
+        let mut log = Vec::new();
+        let mut fight = self.clone();
+        for action in actions.iter() {
+            let (next, outcome) = fight.round(rules, *action, &mut log)?;
+            if outcome != Outcome::Ongoing {
+                break;
+            }
+            fight = next;
+        }
+        Some(log.join("\n").trim_end().to_string())
+    }
+
+    /// The player's turn doing `action` followed by the boss's turn, or `None` if `action`
+    /// isn't allowed
+    fn round(&self, rules: &Rules, action: Action, log: &mut impl Log) -> Option<(Fight, Outcome)> {
+        let mut fight = self.clone();
+        let outcome = fight.play_round(rules, action, log)?;
+        Some((fight, outcome))
+    }
+
+    fn play_round(&mut self, rules: &Rules, action: Action, log: &mut impl Log) -> Option<Outcome> {
+        self.header(rules, "Player", log);
+        if rules.drain > 0 {
+            self.player.hitpoints -= rules.drain;
+            log.say(|| format!("Player loses {}.{}", hit_points(rules.drain), self.ending()));
+            if self.outcome() != Outcome::Ongoing {
+                return Some(self.outcome());
+            }
+        }
+        self.apply_effects(rules, log);
+        if self.outcome() != Outcome::Ongoing {
+            return Some(self.outcome());
+        }
+        self.act(rules, action, log)?;
+        if self.outcome() != Outcome::Ongoing {
+            return Some(self.outcome());
+        }
+        if !rules.is_duel() {
+            log.say(String::new);
+        }
+
+        self.header(rules, "Boss", log);
+        self.apply_effects(rules, log);
+        if self.outcome() != Outcome::Ongoing {
+            return Some(self.outcome());
+        }
+        let armor = self.armor(rules);
+        let hit = (self.boss.damage - armor).max(1);
+        self.player.hitpoints -= hit;
+        log.say(|| self.attack_line(rules, ("boss", "player"), self.boss.damage, armor));
+        if !rules.is_duel() && self.outcome() == Outcome::Ongoing {
+            log.say(String::new);
+        }
+        Some(self.outcome())
+    }
+
+    fn act(&mut self, rules: &Rules, action: Action, log: &mut impl Log) -> Option<()> {
+        match action {
+            Action::Attack => {
+                if !rules.is_duel() {
+                    return None;
+                }
+                let armor = self.boss.armor;
+                self.boss.hitpoints -= (self.player.damage - armor).max(1);
+                log.say(|| self.attack_line(rules, ("player", "boss"), self.player.damage, armor));
+            }
+            Action::Cast(index) => {
+                let spell = rules.spells.get(index)?;
+                if spell.cost > self.mana || self.timers[index] > 0 {
+                    return None;
+                }
+                self.mana -= spell.cost;
+                self.boss.hitpoints -= spell.damage;
+                self.player.hitpoints += spell.heal;
+                if let Some(effect) = spell.effect {
+                    self.timers[index] = effect.turns;
+                }
+                log.say(|| {
+                    let mut line = format!("Player casts {}", spell.name);
+                    if spell.damage > 0 {
+                        line += &format!(", dealing {} damage", spell.damage);
+                    }
+                    if spell.heal > 0 {
+                        line += &format!(", and healing {}", hit_points(spell.heal));
+                    }
+                    if let Some(effect) = spell.effect.filter(|effect| effect.armor > 0) {
+                        line += &format!(", increasing armor by {}", effect.armor);
+                    }
+                    format!("{line}.{}", self.ending())
+                });
+            }
+        }
+        Some(())
+    }
+
+    /// Counts down each active effect, stopping early if that kills the boss
+    fn apply_effects(&mut self, rules: &Rules, log: &mut impl Log) {
+        for (index, spell) in rules.spells.iter().enumerate() {
+            let Some(effect) = spell.effect.filter(|_| self.timers[index] > 0) else {
+                continue;
+            };
+            self.timers[index] -= 1;
+            self.boss.hitpoints -= effect.damage;
+            self.mana += effect.mana;
+            let timer = self.timers[index];
+            let name = spell.name;
+            if self.outcome() != Outcome::Ongoing {
+                log.say(|| format!("{name} deals {} damage.{}", effect.damage, self.ending()));
+                return;
+            }
+            log.say(|| match (effect.damage, effect.mana) {
+                (0, 0) => format!("{name}'s timer is now {timer}."),
+                (0, mana) => format!("{name} provides {mana} mana; its timer is now {timer}."),
+                (damage, _) => format!("{name} deals {damage} damage; its timer is now {timer}."),
+            });
+            if timer == 0 {
+                log.say(|| match effect.armor {
+                    0 => format!("{name} wears off."),
+                    armor => format!("{name} wears off, decreasing armor by {armor}."),
+                });
+            }
+        }
+    }
+
+    /// The player's armor with any effects
+    fn armor(&self, rules: &Rules) -> i32 {
+        let effects = rules
+            .spells
+            .iter()
+            .zip(self.timers.iter())
+            .filter(|(_, timer)| **timer > 0)
+            .filter_map(|(spell, _)| spell.effect)
+            .map(|effect| effect.armor)
+            .sum::<i32>();
+        self.player.armor + effects
+    }
+
+    fn outcome(&self) -> Outcome {
+        if self.boss.hitpoints <= 0 {
+            Outcome::Won
+        } else if self.player.hitpoints <= 0 {
+            Outcome::Lost
+        } else {
+            Outcome::Ongoing
+        }
+    }
+
+    fn ending(&self) -> &'static str {
+        match self.outcome() {
+            Outcome::Ongoing => "",
+            Outcome::Won => " This kills the boss, and the player wins.",
+            Outcome::Lost => " This kills the player, and the boss wins.",
+        }
+    }
+
+    fn header(&self, rules: &Rules, side: &str, log: &mut impl Log) {
+        if rules.is_duel() {
+            return;
+        }
+        log.say(|| format!("-- {side} turn --"));
+        log.say(|| {
+            let (hitpoints, armor) = (self.player.hitpoints, self.armor(rules));
+            format!(
+                "- Player has {}, {armor} armor, {} mana",
+                hit_points(hitpoints),
+                self.mana
+            )
+        });
+        log.say(|| format!("- Boss has {}", hit_points(self.boss.hitpoints)));
+    }
+
+    /// An attack that's already happened, worded like the item shop fight for weapon fights
+    /// and like the wizard fights otherwise
+    fn attack_line(&self, rules: &Rules, sides: (&str, &str), damage: i32, armor: i32) -> String {
+        let (attacker, defender) = sides;
+        let hit = (damage - armor).max(1);
+        if rules.is_duel() {
+            let left = match defender {
+                "boss" => self.boss.hitpoints,
+                _ => self.player.hitpoints,
+            };
+            return format!(
+                "The {attacker} deals {damage}-{armor} = {hit} damage; \
+                 the {defender} goes down to {} hit points.",
+                left.max(0)
+            );
+        }
+        let attacker = match attacker {
+            "boss" => "Boss",
+            _ => "Player",
+        };
+        match armor {
+            0 => format!("{attacker} attacks for {damage} damage.{}", self.ending()),
+            _ => format!(
+                "{attacker} attacks for {damage} - {armor} = {hit} damage!{}",
+                self.ending()
+            ),
+        }
+    }
+}
+
+fn hit_points(amount: i32) -> String {
+    match amount {
+        1 => "1 hit point".to_string(),
+        _ => format!("{amount} hit points"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Fighter::parse("Hit Points: 104\nDamage: 8\n"),
+            Ok(Fighter {
+                hitpoints: 104,
+                damage: 8,
+                armor: 0,
+            })
+        );
+        assert!(Fighter::parse("Hit Points: 104\nMana: 8").is_err());
+        assert!(Fighter::parse("Damage: -8").is_err());
+    }
+
+    #[test]
+    fn test_closed_form() {
+        let rules = Rules::weapons();
+        for (hitpoints, damage, armor) in [(8, 5, 5), (8, 3, 1), (20, 9, 0), (1, 1, 9)] {
+            let player = Fighter {
+                hitpoints,
+                damage,
+                armor,
+            };
+            for boss_hitpoints in 1..20 {
+                for boss_damage in 0..10 {
+                    let boss = Fighter {
+                        hitpoints: boss_hitpoints,
+                        damage: boss_damage,
+                        armor: 2,
+                    };
+                    let win = Fight::new(&rules, player, 0, boss).cheapest_win(&rules);
+                    assert_eq!(player.beats(&boss), win.is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_drain() {
+        let rules = Rules::weapons().drain(3);
+        let player = Fighter {
+            hitpoints: 3,
+            damage: 10,
+            armor: 0,
+        };
+        let boss = Fighter {
+            hitpoints: 5,
+            damage: 1,
+            armor: 0,
+        };
+        let fight = Fight::new(&rules, player, 0, boss);
+        assert_eq!(fight.cheapest_win(&rules), None);
+        assert_eq!(
+            fight.narrate(&rules, &[Action::Attack]).unwrap(),
+            "Player loses 3 hit points. This kills the player, and the boss wins."
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::{num::ParseIntError, str::FromStr};
 
-use crate::common::chinese_remainder;
+use crate::common::{solve_congruences, Congruence};
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let mut disks = input
@@ -19,13 +19,11 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
 }
 
 fn work(disks: &[Disk]) -> i64 {
-    let offsets = disks
-        .iter()
-        .enumerate()
-        .map(|(i, disk)| (-disk.offset - (i as i64 + 1)).rem_euclid(disk.size))
-        .collect::<Vec<_>>();
-    let m = disks.iter().map(|d| d.size).collect::<Vec<_>>();
-    chinese_remainder(&offsets, &m).unwrap()
+    // Disk i is reached i + 1 seconds after pressing, and has to be at 0 then
+    let congruences = disks.iter().enumerate().map(|(i, disk)| {
+        Congruence::new(-disk.offset as i128 - (i as i128 + 1), disk.size as i128)
+    });
+    solve_congruences(congruences).unwrap().residue() as i64
 }
 
 #[derive(Debug)]
@@ -1,15 +1,15 @@
-use std::collections::VecDeque;
+use crate::common::Ring;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let elf_count = input.trim().parse::<usize>().unwrap();
 
-    let mut elves = (1..=elf_count).collect::<VecDeque<_>>();
-    for _ in 0..(elves.len() - 1) {
-        let front = elves.pop_front().unwrap();
-        elves.push_back(front);
-        elves.pop_front();
+    // The head is the elf whose turn it is
+    let mut elves = Ring::from_order(0..elf_count).unwrap();
+    for _ in 0..(elf_count - 1) {
+        elves.remove_after(elves.head(), 1);
+        elves.rotate(1);
     }
-    let part1 = elves[0];
+    let part1 = elves.head() + 1;
 
     let part2 = solve_across(elf_count);
 
@@ -37,18 +37,24 @@ fn solve_across(elf_count: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
+    use crate::common::CursorRing;
+
     use super::*;
 
     #[test]
     fn test_across() {
         for test in 1..5_000 {
-            let mut elves = (1..=test).collect::<VecDeque<_>>();
-            for _ in 0..(elves.len() - 1) {
-                elves.remove(elves.len() / 2);
-                let front = elves.pop_front().unwrap();
-                elves.push_back(front);
+            // The cursor follows the elf across from whoever's turn it is
+            let mut elves = (1..=test).collect::<CursorRing<_>>();
+            elves.rotate((test / 2) as isize);
+            while elves.len() > 1 {
+                let odd = elves.len() % 2 == 1;
+                elves.remove();
+                if odd {
+                    elves.rotate(1);
+                }
             }
-            assert_eq!(elves[0], solve_across(test));
+            assert_eq!(*elves.current().unwrap(), solve_across(test));
         }
     }
 }
@@ -1,148 +1,348 @@
-use std::{num::ParseIntError, str::FromStr};
-
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let instructions = input
-        .lines()
-        .map(|l| l.parse::<Instruction>().unwrap())
-        .collect::<Vec<_>>();
-
-    // Part 1: Scramble
-    let mut data = "abcdefgh".chars().collect::<Vec<_>>();
-    let data_len = data.len();
-    for instr in instructions.iter() {
-        match instr {
+use std::{collections::HashSet, fmt::Display};
+
+use winnow::{
+    combinator::{alt, opt},
+    prelude::*,
+    token::one_of,
+};
+
+use crate::common::{expected, lines, parse_all, parse_u64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let program = Program::parse(input)?;
+
+    let part1 = program.scramble("abcdefgh").unwrap();
+    let part2 = program.inverse(8).unwrap().scramble("fbgdceah").unwrap();
+
+    Ok((part1, part2))
+}
+
+#[derive(Debug)]
+struct Program {
+    instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    SwapPos {
+        x: usize,
+        y: usize,
+    },
+    SwapChar {
+        x: char,
+        y: char,
+    },
+    RotateLeft {
+        amount: usize,
+    },
+    RotateRight {
+        amount: usize,
+    },
+    RotateOnPosition {
+        x: char,
+    },
+    /// Undoes `RotateOnPosition` on passwords of length `len`, which is only possible for some
+    /// lengths
+    UnrotateOnPosition {
+        x: char,
+        len: usize,
+    },
+    Reverse {
+        x: usize,
+        y: usize,
+    },
+    Move {
+        x: usize,
+        y: usize,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ScrambleError {
+    PositionOutOfRange(usize),
+    MissingLetter(char),
+    RepeatedLetter(char),
+    /// Different starting positions rotate the letter to the same place, so there's no way back
+    AmbiguousRotation {
+        len: usize,
+    },
+    /// An unscrambling program used on a password of a different length than it was made for
+    WrongLength {
+        expected: usize,
+        len: usize,
+    },
+}
+
+impl Program {
+    fn parse(input: &str) -> Result<Program, ParseError> {
+        let instructions = parse_all(input, lines(parse_instruction))?;
+        Ok(Program { instructions })
+    }
+
+    fn scramble(&self, password: &str) -> Result<String, ScrambleError> {
+        let mut data = password.chars().collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        if let Some(c) = data.iter().find(|c| !seen.insert(**c)) {
+            return Err(ScrambleError::RepeatedLetter(*c));
+        }
+        for instruction in self.instructions.iter() {
+            instruction.apply(&mut data)?;
+        }
+        Ok(data.into_iter().collect())
+    }
+
+    /// The program that unscrambles passwords of length `len`
+    fn inverse(&self, len: usize) -> Result<Program, ScrambleError> {
+        let instructions = self
+            .instructions
+            .iter()
+            .rev()
+            .map(|instruction| instruction.inverse(len))
+            .collect::<Result<_, _>>()?;
+        Ok(Program { instructions })
+    }
+}
+
+impl Instruction {
+    fn apply(&self, data: &mut Vec<char>) -> Result<(), ScrambleError> {
+        let len = data.len();
+        let check = |position: usize| match position < len {
+            true => Ok(position),
+            false => Err(ScrambleError::PositionOutOfRange(position)),
+        };
+        let find = |data: &[char], c: char| {
+            data.iter()
+                .position(|other| *other == c)
+                .ok_or(ScrambleError::MissingLetter(c))
+        };
+        match *self {
             Instruction::SwapPos { x, y } => {
-                data.swap(*x, *y);
+                data.swap(check(x)?, check(y)?);
             }
             Instruction::SwapChar { x, y } => {
-                let x_pos = data.iter().enumerate().find(|(_, c)| *c == x).unwrap().0;
-                let y_pos = data.iter().enumerate().find(|(_, c)| *c == y).unwrap().0;
+                let x_pos = find(data, x)?;
+                let y_pos = find(data, y)?;
                 data.swap(x_pos, y_pos);
             }
             Instruction::RotateLeft { amount } => {
-                data.rotate_left(*amount);
+                data.rotate_left(amount % len.max(1));
             }
             Instruction::RotateRight { amount } => {
-                data.rotate_right(*amount);
+                data.rotate_right(amount % len.max(1));
             }
             Instruction::RotateOnPosition { x } => {
-                let x_pos = data.iter().enumerate().find(|(_, c)| *c == x).unwrap().0;
-                let rot_amount = 1 + x_pos + usize::from(x_pos >= 4);
-                data.rotate_right(rot_amount % data_len);
+                let x_pos = find(data, x)?;
+                data.rotate_right(rotation_for(x_pos) % len);
+            }
+            Instruction::UnrotateOnPosition { x, len: expected } => {
+                if len != expected {
+                    return Err(ScrambleError::WrongLength { expected, len });
+                }
+                let x_pos = find(data, x)?;
+                let original = (0..len)
+                    .find(|original| (original + rotation_for(*original)) % len == x_pos)
+                    .ok_or(ScrambleError::AmbiguousRotation { len })?;
+                data.rotate_left(rotation_for(original) % len);
             }
             Instruction::Reverse { x, y } => {
-                data[*x..(*y + 1)].reverse();
+                data[check(x.min(y))?..=check(x.max(y))?].reverse();
             }
             Instruction::Move { x, y } => {
-                let c = data.remove(*x);
-                data.insert(*y, c);
+                let c = data.remove(check(x)?);
+                data.insert(check(y)?, c);
             }
         }
+        Ok(())
     }
-    let part1 = data.into_iter().collect::<String>();
-
-    // Part 2: Unsramble
-    let mut data = "fbgdceah".chars().collect::<Vec<_>>();
-    let data_len = data.len();
-    assert_eq!(data_len, 8);
-    for instr in instructions.iter().rev() {
-        match instr {
-            Instruction::SwapPos { x, y } => {
-                data.swap(*x, *y);
-            }
-            Instruction::SwapChar { x, y } => {
-                let x_pos = data.iter().enumerate().find(|(_, c)| *c == x).unwrap().0;
-                let y_pos = data.iter().enumerate().find(|(_, c)| *c == y).unwrap().0;
-                data.swap(x_pos, y_pos);
-            }
-            Instruction::RotateLeft { amount } => {
-                data.rotate_right(*amount);
-            }
-            Instruction::RotateRight { amount } => {
-                data.rotate_left(*amount);
-            }
+
+    /// The instruction undoing this one on passwords of length `len`
+    fn inverse(&self, len: usize) -> Result<Instruction, ScrambleError> {
+        Ok(match *self {
+            Instruction::SwapPos { .. }
+            | Instruction::SwapChar { .. }
+            | Instruction::Reverse { .. } => *self,
+            Instruction::RotateLeft { amount } => Instruction::RotateRight { amount },
+            Instruction::RotateRight { amount } => Instruction::RotateLeft { amount },
             Instruction::RotateOnPosition { x } => {
-                // original 0 1 2 3 4 5 6 7
-                // new      1 3 5 7 2 4 6 0
-                // rotation 1 2 3 4 6 7 0 1
-                let x_pos = data.iter().enumerate().find(|(_, c)| *c == x).unwrap().0;
-                let rot_amount = match x_pos {
-                    1 => 1,
-                    3 => 2,
-                    5 => 3,
-                    7 => 4,
-                    2 => 6,
-                    4 => 7,
-                    6 => 0,
-                    0 => 1,
-                    _ => unreachable!(),
-                };
-                data.rotate_left(rot_amount);
+                if !rotation_invertible(len) {
+                    return Err(ScrambleError::AmbiguousRotation { len });
+                }
+                Instruction::UnrotateOnPosition { x, len }
             }
-            Instruction::Reverse { x, y } => {
-                data[*x..(*y + 1)].reverse();
-            }
-            Instruction::Move { x, y } => {
-                let c = data.remove(*y);
-                data.insert(*x, c);
-            }
-        }
+            Instruction::UnrotateOnPosition { x, .. } => Instruction::RotateOnPosition { x },
+            Instruction::Move { x, y } => Instruction::Move { x: y, y: x },
+        })
     }
-    let part2 = data.into_iter().collect::<String>();
+}
 
-    (part1, part2)
+/// How far right "rotate based on position of letter" turns, given the letter's position
+fn rotation_for(position: usize) -> usize {
+    1 + position + usize::from(position >= 4)
 }
 
-#[derive(Debug)]
-enum Instruction {
-    SwapPos { x: usize, y: usize },
-    SwapChar { x: char, y: char },
-    RotateLeft { amount: usize },
-    RotateRight { amount: usize },
-    RotateOnPosition { x: char },
-    Reverse { x: usize, y: usize },
-    Move { x: usize, y: usize },
+/// Whether every starting position rotates the letter somewhere different for this length
+fn rotation_invertible(len: usize) -> bool {
+    let mut seen = HashSet::new();
+    (0..len).all(|position| seen.insert((position + rotation_for(position)) % len))
 }
 
-impl FromStr for Instruction {
-    type Err = ParseIntError;
+fn parse_instruction(input: &mut &str) -> winnow::Result<Instruction> {
+    let position = || parse_u64.map(|position| position as usize);
+    let letter = || one_of(|c: char| !c.is_whitespace()).context(expected("letter"));
+    alt((
+        ("swap position ", position(), " with position ", position())
+            .map(|(_, x, _, y)| Instruction::SwapPos { x, y }),
+        ("swap letter ", letter(), " with letter ", letter())
+            .map(|(_, x, _, y)| Instruction::SwapChar { x, y }),
+        ("rotate left ", position(), " step", opt('s'))
+            .map(|(_, amount, _, _)| Instruction::RotateLeft { amount }),
+        ("rotate right ", position(), " step", opt('s'))
+            .map(|(_, amount, _, _)| Instruction::RotateRight { amount }),
+        ("rotate based on position of letter ", letter())
+            .map(|(_, x)| Instruction::RotateOnPosition { x }),
+        ("reverse positions ", position(), " through ", position())
+            .map(|(_, x, _, y)| Instruction::Reverse { x, y }),
+        ("move position ", position(), " to position ", position())
+            .map(|(_, x, _, y)| Instruction::Move { x, y }),
+    ))
+    .context(expected("instruction"))
+    .parse_next(input)
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(' ').collect::<Vec<_>>();
-        Ok(match (parts[0], parts[1]) {
-            ("swap", "position") => {
-                let x = parts[2].parse()?;
-                let y = parts[5].parse()?;
-                Instruction::SwapPos { x, y }
-            }
-            ("swap", "letter") => {
-                let x = parts[2].chars().next().unwrap();
-                let y = parts[5].chars().next().unwrap();
-                Instruction::SwapChar { x, y }
-            }
-            ("rotate", "left") => {
-                let amount = parts[2].parse()?;
-                Instruction::RotateLeft { amount }
-            }
-            ("rotate", "right") => {
-                let amount = parts[2].parse()?;
-                Instruction::RotateRight { amount }
+impl Display for ScrambleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrambleError::PositionOutOfRange(position) => {
+                write!(f, "position {position} is past the end of the password")
             }
-            ("rotate", "based") => {
-                let x = parts[6].chars().next().unwrap();
-                Instruction::RotateOnPosition { x }
+            ScrambleError::MissingLetter(c) => write!(f, "password has no letter {c}"),
+            ScrambleError::RepeatedLetter(c) => write!(f, "password has {c} more than once"),
+            ScrambleError::AmbiguousRotation { len } => {
+                write!(
+                    f,
+                    "rotating on letter position can't be undone for length {len}"
+                )
             }
-            ("reverse", _) => {
-                let x = parts[2].parse()?;
-                let y = parts[4].parse()?;
-                Instruction::Reverse { x, y }
+            ScrambleError::WrongLength { expected, len } => {
+                write!(f, "unscrambling for length {expected} used on length {len}")
             }
-            ("move", _) => {
-                let x = parts[2].parse()?;
-                let y = parts[5].parse()?;
-                Instruction::Move { x, y }
-            }
-            _ => unreachable!(),
-        })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Permutation;
+
+    const EXAMPLE: &str = "swap position 4 with position 0
+swap letter d with letter b
+reverse positions 0 through 4
+rotate left 1 step
+move position 1 to position 4
+move position 3 to position 0
+rotate based on position of letter b
+rotate based on position of letter d";
+
+    #[test]
+    fn test_example() {
+        let program = Program::parse(EXAMPLE).unwrap();
+        assert_eq!(program.scramble("abcde"), Ok("decab".to_string()));
+        assert_eq!(
+            program.inverse(5).unwrap_err(),
+            ScrambleError::AmbiguousRotation { len: 5 }
+        );
+        assert_eq!(
+            program.scramble("abcdd"),
+            Err(ScrambleError::RepeatedLetter('d'))
+        );
+    }
+
+    #[test]
+    fn test_rotation_inverse() {
+        // Every position of the letter lands somewhere different for length 8, so the inverse
+        // is unique
+        assert!(rotation_invertible(8));
+        let rotate = Instruction::RotateOnPosition { x: 'x' };
+        let unrotate = rotate.inverse(8).unwrap();
+        for position in 0..8 {
+            let mut data = "abcdefg".chars().collect::<Vec<_>>();
+            data.insert(position, 'x');
+            let original = data.clone();
+            rotate.apply(&mut data).unwrap();
+            unrotate.apply(&mut data).unwrap();
+            assert_eq!(data, original);
+        }
+
+        let ambiguous = (1..=10)
+            .filter(|len| !rotation_invertible(*len))
+            .collect::<Vec<_>>();
+        assert_eq!(ambiguous, [2, 4, 5, 6, 7, 9, 10]);
+        assert_eq!(
+            rotate.inverse(5),
+            Err(ScrambleError::AmbiguousRotation { len: 5 })
+        );
+    }
+
+    /// The scramble of `password` as a rearrangement of its positions. Letter based
+    /// instructions make this depend on the password, not just its length
+    fn permutation(program: &Program, password: &str) -> Permutation {
+        let scrambled = program.scramble(password).unwrap();
+        let sources = scrambled
+            .chars()
+            .map(|c| password.chars().position(|other| other == c).unwrap())
+            .collect();
+        Permutation::from_sources(sources).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Any characters work as letters, not just a to h
+        let program = Program::parse(
+            "swap position 4 with position 0
+swap letter # with letter 7
+reverse positions 0 through 4
+rotate left 3 steps
+move position 1 to position 6
+move position 7 to position 0
+rotate based on position of letter 7
+rotate right 10 steps
+rotate based on position of letter #",
+        )
+        .unwrap();
+        let password = "#1234567";
+        let scrambled = program.scramble(password).unwrap();
+        assert_ne!(scrambled, password);
+        assert_eq!(
+            program.inverse(8).unwrap().scramble(&scrambled),
+            Ok(password.to_string())
+        );
+
+        let permutation = permutation(&program, password);
+        let password = password.chars().collect::<Vec<_>>();
+        let scrambled = scrambled.chars().collect::<Vec<_>>();
+        assert_eq!(permutation.apply(&password), scrambled);
+        assert_eq!(permutation.inverse().apply(&scrambled), password);
+    }
+
+    #[test]
+    fn test_errors() {
+        let program = Program::parse("reverse positions 4 through 1").unwrap();
+        assert_eq!(program.scramble("abcde"), Ok("aedcb".to_string()));
+
+        let unscramble = Program::parse("rotate based on position of letter a")
+            .unwrap()
+            .inverse(8)
+            .unwrap();
+        assert_eq!(
+            unscramble.scramble("abc"),
+            Err(ScrambleError::WrongLength {
+                expected: 8,
+                len: 3
+            })
+        );
+
+        let error = Program::parse("swap position 1 with position 2\nspin 3").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 1));
     }
 }
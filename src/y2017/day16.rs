@@ -1,4 +1,6 @@
-use std::collections::{HashSet, VecDeque};
+use crate::common::Permutation;
+
+const PROGRAMS: usize = 16;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let actions = input
@@ -6,64 +8,69 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         .split(',')
         .map(Action::from_str)
         .collect::<Vec<_>>();
-    let mut programs = ('a'..='p').collect::<VecDeque<_>>();
-    for action in actions {
-        match action {
-            Action::Spin { amount } => programs.rotate_right(amount),
-            Action::Swap { a, b } => programs.swap(a, b),
-            Action::Partner { a, b } => {
-                let a_pos = programs.iter().position(|&e| e == a).unwrap();
-                let b_pos = programs.iter().position(|&e| e == b).unwrap();
-                programs.swap(a_pos, b_pos);
-            }
-        }
-    }
-    let part1 = programs.iter().collect::<String>();
-    let actions = input
-        .trim()
-        .split(',')
-        .map(Action::from_str)
-        .collect::<Vec<_>>();
-    let mut programs = ('a'..='p').collect::<VecDeque<_>>();
-    let mut seen = HashSet::new();
-    seen.insert(programs.clone());
-    for step in 1.. {
-        for action in actions.iter() {
-            match action {
-                Action::Spin { amount } => programs.rotate_right(*amount),
-                Action::Swap { a, b } => programs.swap(*a, *b),
+    let dance = Dance::new(&actions, PROGRAMS);
+
+    let part1 = dance.repeat(1).line_up();
+    let part2 = dance.repeat(1_000_000_000).line_up();
+
+    (part1, part2)
+}
+
+/// A dance split into moves by position and renames by label. Renaming doesn't care where
+/// programs stand, so the two parts can be repeated separately
+#[derive(Debug, Clone)]
+struct Dance {
+    positions: Permutation,
+    /// Source `i` is what program `i` has been renamed to
+    labels: Permutation,
+}
+
+enum Action {
+    Spin { amount: usize },
+    Swap { a: usize, b: usize },
+    Partner { a: char, b: char },
+}
+
+impl Dance {
+    fn new(actions: &[Action], programs: usize) -> Self {
+        let mut positions = Permutation::identity(programs);
+        let mut labels = Permutation::identity(programs);
+        for action in actions {
+            match *action {
+                Action::Spin { amount } => {
+                    positions = positions.then(&Permutation::rotation_right(programs, amount));
+                }
+                Action::Swap { a, b } => {
+                    positions = positions.then(&Permutation::transposition(programs, a, b));
+                }
                 Action::Partner { a, b } => {
-                    let a_pos = programs.iter().position(|e| e == a).unwrap();
-                    let b_pos = programs.iter().position(|e| e == b).unwrap();
-                    programs.swap(a_pos, b_pos);
+                    let (a, b) = (label_index(a), label_index(b));
+                    labels = Permutation::transposition(programs, a, b).then(&labels);
                 }
             }
         }
-        if !seen.insert(programs.clone()) {
-            let remaining_steps = 1_000_000_000 % step;
-            for _ in 0..remaining_steps {
-                for action in actions.iter() {
-                    match action {
-                        Action::Spin { amount } => programs.rotate_right(*amount),
-                        Action::Swap { a, b } => programs.swap(*a, *b),
-                        Action::Partner { a, b } => {
-                            let a_pos = programs.iter().position(|e| e == a).unwrap();
-                            let b_pos = programs.iter().position(|e| e == b).unwrap();
-                            programs.swap(a_pos, b_pos);
-                        }
-                    }
-                }
-            }
-            return (part1, programs.iter().collect::<String>());
+        Dance { positions, labels }
+    }
+
+    fn repeat(&self, times: u64) -> Dance {
+        Dance {
+            positions: self.positions.pow(times),
+            labels: self.labels.pow(times),
         }
     }
-    unreachable!()
+
+    /// The programs' names in order after dancing from `a` onwards
+    fn line_up(&self) -> String {
+        let names = (0..self.positions.len())
+            .map(|label| (b'a' + label as u8) as char)
+            .collect::<Vec<_>>();
+        let renamed = self.labels.apply(&names);
+        self.positions.apply(&renamed).into_iter().collect()
+    }
 }
 
-enum Action {
-    Spin { amount: usize },
-    Swap { a: usize, b: usize },
-    Partner { a: char, b: char },
+fn label_index(label: char) -> usize {
+    (label as u8 - b'a') as usize
 }
 
 impl Action {
@@ -92,3 +99,19 @@ impl Action {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let actions = "s1,x3/4,pe/b"
+            .split(',')
+            .map(Action::from_str)
+            .collect::<Vec<_>>();
+        let dance = Dance::new(&actions, 5);
+        assert_eq!(dance.line_up(), "baedc");
+        assert_eq!(dance.repeat(2).line_up(), "ceadb");
+    }
+}
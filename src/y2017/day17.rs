@@ -1,26 +1,23 @@
-use std::collections::VecDeque;
+use crate::common::CursorRing;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let step_count = input.trim().parse::<usize>().unwrap();
 
-    let mut spinlock = VecDeque::new();
-    spinlock.push_front(0);
-    for i in 1..=2017 {
-        let shift = (step_count + 1) % spinlock.len();
-        spinlock.rotate_left(shift);
-        spinlock.push_front(i);
-    }
-    let part1 = spinlock[1];
+    let part1 = *spin(step_count, 2017).get(1).unwrap();
 
-    let mut spinlock = VecDeque::new();
-    spinlock.push_front(0);
-    for i in 1..=50_000_000 {
-        let shift = (step_count + 1) % spinlock.len();
-        spinlock.rotate_left(shift);
-        spinlock.push_front(i);
-    }
-    let pos = spinlock.iter().position(|&ele| ele == 0).unwrap();
-    let part2 = spinlock[pos + 1];
+    let spinlock = spin(step_count, 50_000_000);
+    let zero = spinlock.iter().position(|&value| value == 0).unwrap();
+    let part2 = *spinlock.get(zero as isize + 1).unwrap();
 
     (part1, part2)
 }
+
+/// The buffer after inserting `1..=insertions`, with the cursor on the last one inserted
+fn spin(step_count: usize, insertions: usize) -> CursorRing<usize> {
+    let mut spinlock = CursorRing::from_iter([0]);
+    for i in 1..=insertions {
+        spinlock.rotate((step_count + 1) as isize);
+        spinlock.insert(i);
+    }
+    spinlock
+}
@@ -1,184 +1,103 @@
-use std::{collections::HashMap, iter, str::FromStr};
+use std::collections::HashMap;
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut transforms = HashMap::new();
-    for line in input.lines() {
-        let mut patterns = line.split(" => ").map(|x| x.parse::<Pattern>().unwrap());
-        let from = patterns.next().unwrap();
-        let to = patterns.next().unwrap();
-        for pattern in iter::once(from.flipped()).chain(iter::once(from)) {
-            let rotated_90 = pattern.rotated_clockwise();
-            let rotated_180 = rotated_90.rotated_clockwise();
-            let rotated_270 = rotated_180.rotated_clockwise();
-            transforms.insert(pattern, to.clone());
-            transforms.insert(rotated_90, to.clone());
-            transforms.insert(rotated_180, to.clone());
-            transforms.insert(rotated_270, to.clone());
-        }
-    }
-
-    let mut grid = vec![
-        vec![false, true, false],
-        vec![false, false, true],
-        vec![true, true, true],
-    ];
-    for _ in 0..5 {
-        grid = step(grid, &transforms);
-    }
+use crate::common::BitPattern;
 
-    let part1 = grid
-        .iter()
-        .map(|row| {
-            row.iter()
-                .fold(0, |acc, &curr| if curr { acc + 1 } else { acc })
-        })
-        .sum::<u32>();
+const START: &str = ".#./..#/###";
 
-    let mut grid = vec![
-        vec![false, true, false],
-        vec![false, false, true],
-        vec![true, true, true],
-    ];
-    for _ in 0..18 {
-        grid = step(grid, &transforms);
-    }
+pub fn solve(input: &str) -> (impl ToString, impl ToString) {
+    let mut enhancer = Enhancer::new(input);
+    let start = START.parse().unwrap();
 
-    let part2 = grid
-        .iter()
-        .map(|row| {
-            row.iter()
-                .fold(0, |acc, &curr| if curr { acc + 1 } else { acc })
-        })
-        .sum::<u32>();
+    let part1 = enhancer.count_after(start, 5);
+    let part2 = enhancer.count_after(start, 18);
 
     (part1, part2)
 }
 
-fn step(grid: Vec<Vec<bool>>, transforms: &HashMap<Pattern, Pattern>) -> Vec<Vec<bool>> {
-    if grid.len().is_multiple_of(2) {
-        let blocks = (0..grid.len())
-            .step_by(2)
-            .map(|y| {
-                (0..grid.len())
-                    .step_by(2)
-                    .map(|x| {
-                        let block = vec![
-                            vec![grid[y][x], grid[y][x + 1]],
-                            vec![grid[y + 1][x], grid[y + 1][x + 1]],
-                        ];
-                        transforms.get(&Pattern { data: block }).unwrap()
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<Vec<_>>>();
-        let mut new_grid = Vec::new();
-        for row in blocks {
-            let inner_row_0 = row
-                .iter()
-                .flat_map(|r| r.data[0].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_0);
-            let inner_row_1 = row
-                .iter()
-                .flat_map(|r| r.data[1].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_1);
-            let inner_row_2 = row
-                .iter()
-                .flat_map(|r| r.data[2].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_2);
-        }
-        new_grid
-    } else {
-        let blocks = (0..grid.len())
-            .step_by(3)
-            .map(|y| {
-                (0..grid.len())
-                    .step_by(3)
-                    .map(|x| {
-                        let block = vec![
-                            vec![grid[y][x], grid[y][x + 1], grid[y][x + 2]],
-                            vec![grid[y + 1][x], grid[y + 1][x + 1], grid[y + 1][x + 2]],
-                            vec![grid[y + 2][x], grid[y + 2][x + 1], grid[y + 2][x + 2]],
-                        ];
-                        transforms.get(&Pattern { data: block }).unwrap()
-                    })
-                    .collect::<Vec<_>>()
+/// The enhancement rules, stored once each under the canonical orientation of their input
+struct Enhancer {
+    rules: HashMap<BitPattern, BitPattern>,
+    /// Pixels on after some iterations starting from a canonical 3x3 block
+    counts: HashMap<(BitPattern, usize), u64>,
+}
+
+type Grid = Vec<Vec<bool>>;
+
+impl Enhancer {
+    fn new(input: &str) -> Self {
+        let rules = input
+            .lines()
+            .map(|line| {
+                let (from, to) = line.split_once(" => ").unwrap();
+                let from = from.parse::<BitPattern>().unwrap();
+                (from.canonical().0, to.parse().unwrap())
             })
-            .collect::<Vec<Vec<_>>>();
-        let mut new_grid = Vec::new();
-        for row in blocks {
-            let inner_row_0 = row
-                .iter()
-                .flat_map(|r| r.data[0].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_0);
-            let inner_row_1 = row
-                .iter()
-                .flat_map(|r| r.data[1].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_1);
-            let inner_row_2 = row
-                .iter()
-                .flat_map(|r| r.data[2].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_2);
-            let inner_row_3 = row
-                .iter()
-                .flat_map(|r| r.data[3].clone())
-                .collect::<Vec<_>>();
-            new_grid.push(inner_row_3);
+            .collect();
+        Self {
+            rules,
+            counts: HashMap::new(),
         }
-        new_grid
     }
-}
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
-struct Pattern {
-    data: Vec<Vec<bool>>,
-}
+    fn enhance(&self, pattern: BitPattern) -> BitPattern {
+        self.rules[&pattern.canonical().0]
+    }
 
-impl Pattern {
-    fn flipped(&self) -> Pattern {
-        let mut data = self.data.clone();
-        for row in data.iter_mut() {
-            row.reverse();
+    /// Splits the grid into 2x2 or 3x3 blocks and enhances each of them
+    fn step(&self, grid: &Grid) -> Grid {
+        let size = grid.len();
+        let block_size = if size.is_multiple_of(2) { 2 } else { 3 };
+        let blocks = size / block_size;
+        let mut new_grid = vec![vec![false; blocks * (block_size + 1)]; blocks * (block_size + 1)];
+        for block_y in 0..blocks {
+            for block_x in 0..blocks {
+                let (x0, y0) = (block_x * block_size, block_y * block_size);
+                let block = BitPattern::from_fn(block_size, |x, y| grid[y0 + y][x0 + x]);
+                let enhanced = self.enhance(block);
+                let (x0, y0) = (block_x * enhanced.size(), block_y * enhanced.size());
+                for y in 0..enhanced.size() {
+                    for x in 0..enhanced.size() {
+                        new_grid[y0 + y][x0 + x] = enhanced.get(x, y);
+                    }
+                }
+            }
         }
-        Pattern { data }
+        new_grid
     }
 
-    fn rotated_clockwise(&self) -> Pattern {
-        let data = (0..self.data.len())
-            .map(|y| {
-                (0..self.data.len())
-                    .map(|x| self.data[self.data.len() - x - 1][y])
-                    .collect()
-            })
-            .collect();
-        Pattern { data }
-    }
-}
+    /// Pixels on after `iterations`, starting from a 3x3 `block`
+    ///
+    /// Three iterations take a 3x3 block to 9x9, which splits into 3x3 blocks that never
+    /// interact again, so only those blocks need tracking rather than the whole grid
+    fn count_after(&mut self, block: BitPattern, iterations: usize) -> u64 {
+        // Orientation doesn't change the count, so blocks can share their canonical entry
+        let key = (block.canonical().0, iterations);
+        if let Some(count) = self.counts.get(&key) {
+            return *count;
+        }
 
-impl FromStr for Pattern {
-    type Err = ();
+        let mut grid = (0..3)
+            .map(|y| (0..3).map(|x| block.get(x, y)).collect())
+            .collect::<Grid>();
+        for _ in 0..iterations.min(3) {
+            grid = self.step(&grid);
+        }
+        let count = if iterations <= 3 {
+            grid.iter().flatten().filter(|on| **on).count() as u64
+        } else {
+            let mut count = 0;
+            for block_y in 0..3 {
+                for block_x in 0..3 {
+                    let block =
+                        BitPattern::from_fn(3, |x, y| grid[block_y * 3 + y][block_x * 3 + x]);
+                    count += self.count_after(block, iterations - 3);
+                }
+            }
+            count
+        };
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Pattern {
-            data: s
-                .split('/')
-                .map(|section| {
-                    section
-                        .chars()
-                        .map(|c| match c {
-                            '#' => true,
-                            '.' => false,
-                            _ => unreachable!(),
-                        })
-                        .collect()
-                })
-                .collect(),
-        })
+        self.counts.insert(key, count);
+        count
     }
 }
 
@@ -186,74 +105,48 @@ impl FromStr for Pattern {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_step() {
-        let input = "../.# => ##./#../...
+    const EXAMPLE: &str = "../.# => ##./#../...
 .#./..#/### => #..#/..../..../#..#";
-        let mut transforms = HashMap::new();
-        for line in input.lines() {
-            let mut patterns = line.split(" => ").map(|x| x.parse::<Pattern>().unwrap());
-            let from = patterns.next().unwrap();
-            let to = patterns.next().unwrap();
-            for pattern in iter::once(from.flipped()).chain(iter::once(from)) {
-                let rotated_90 = pattern.rotated_clockwise();
-                let rotated_180 = rotated_90.rotated_clockwise();
-                let rotated_270 = rotated_180.rotated_clockwise();
-                transforms.insert(pattern, to.clone());
-                transforms.insert(rotated_90, to.clone());
-                transforms.insert(rotated_180, to.clone());
-                transforms.insert(rotated_270, to.clone());
-            }
-        }
-        let grid = vec![
-            vec![false, true, false],
-            vec![false, false, true],
-            vec![true, true, true],
-        ];
-        println!("{:?}", grid);
-        let step_1 = step(grid, &transforms);
-        println!("{:?}", step_1);
-        let step_2 = step(step_1, &transforms);
-        println!("{:?}", step_2);
-    }
 
     #[test]
-    fn test_rotation() {
-        let pattern = Pattern {
-            data: vec![
-                vec![true, false, true],
-                vec![false, true, false],
-                vec![false, false, false],
-            ],
-        };
-        let rotated = pattern.rotated_clockwise();
-        assert_eq!(
-            rotated.data,
-            vec![
-                vec![false, false, true],
-                vec![false, true, false],
-                vec![false, false, true]
-            ]
-        );
+    fn test_example() {
+        let mut enhancer = Enhancer::new(EXAMPLE);
+        assert_eq!(enhancer.rules.len(), 2);
+        let start = START.parse().unwrap();
+        assert_eq!(enhancer.count_after(start, 2), 12);
     }
 
     #[test]
-    fn test_flip() {
-        let pattern = Pattern {
-            data: vec![
-                vec![true, false, true],
-                vec![true, true, false],
-                vec![false, false, false],
-            ],
+    fn test_count_matches_grid() {
+        // Every 2x2 and 3x3 pattern gets a rule so the grid can keep growing
+        let all_patterns = |size: usize| {
+            (0..1 << (size * size)).map(move |bits: u32| {
+                BitPattern::from_fn(size, |x, y| bits & (1 << (y * size + x)) != 0)
+            })
         };
-        let flipped = pattern.flipped();
-        assert_eq!(
-            flipped.data,
-            vec![
-                vec![true, false, true],
-                vec![false, true, true],
-                vec![false, false, false]
-            ]
-        );
+        let rules = all_patterns(2)
+            .chain(all_patterns(3))
+            .map(|pattern| pattern.canonical().0)
+            .map(|pattern| {
+                let size = pattern.size() + 1;
+                let seed = pattern.count_ones() as usize;
+                let to = BitPattern::from_fn(size, |x, y| (x * 7 + y * 3 + seed) % 5 < 2);
+                (pattern, to)
+            })
+            .collect();
+        let mut enhancer = Enhancer {
+            rules,
+            counts: HashMap::new(),
+        };
+
+        let start = START.parse::<BitPattern>().unwrap();
+        let mut grid = (0..3)
+            .map(|y| (0..3).map(|x| start.get(x, y)).collect())
+            .collect::<Grid>();
+        for iterations in 1..=8 {
+            grid = enhancer.step(&grid);
+            let expected = grid.iter().flatten().filter(|on| **on).count() as u64;
+            assert_eq!(enhancer.count_after(start, iterations), expected);
+        }
     }
 }
@@ -1,108 +1,482 @@
-use std::collections::{HashMap, HashSet};
-
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let lines = input.lines().collect::<Vec<_>>();
-    let starting_state = lines[0].chars().nth(15).unwrap();
-    let target_steps = lines[1]
-        .split(' ')
-        .nth(5)
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
-
-    let instructions = lines[2..]
-        .chunks(10)
-        .map(|sub_lines| {
-            let state_name = sub_lines[1].chars().nth(9).unwrap();
-            let false_write = match sub_lines[3].chars().nth(22).unwrap() {
-                '0' => false,
-                '1' => true,
-                _ => panic!(),
-            };
-            let false_dir = match sub_lines[4]
-                .split(' ')
-                .nth(10)
-                .unwrap()
-                .trim_end_matches('.')
-            {
-                "left" => Direction::Left,
-                "right" => Direction::Right,
-                _ => panic!(),
-            };
-            let false_next_state = sub_lines[5].chars().nth(26).unwrap();
-            let true_write = match sub_lines[7].chars().nth(22).unwrap() {
-                '0' => false,
-                '1' => true,
-                _ => panic!(),
-            };
-            let true_dir = match sub_lines[8]
-                .split(' ')
-                .nth(10)
-                .unwrap()
-                .trim_end_matches('.')
-            {
-                "left" => Direction::Left,
-                "right" => Direction::Right,
-                _ => panic!(),
-            };
-            let true_next_state = sub_lines[9].chars().nth(26).unwrap();
-            let instr = Instruction {
-                false_write,
-                false_dir,
-                false_next_state,
-                true_write,
-                true_dir,
-                true_next_state,
-            };
-            (state_name, instr)
-        })
-        .collect::<HashMap<_, _>>();
-
-    let mut tape = HashSet::new();
-    let mut state = &instructions[&starting_state];
-    let mut position = 0_i64;
-    for _ in 0..target_steps {
-        let cur_value = tape.contains(&position);
-        if cur_value {
-            if state.true_write {
-                tape.insert(position);
-            } else {
-                tape.remove(&position);
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
+
+use winnow::{
+    ascii::{line_ending, space0},
+    combinator::{alt, delimited, preceded, separated},
+    error::ContextError,
+    prelude::*,
+    stream::{AsChar, Offset},
+    token::one_of,
+};
+
+use crate::common::{expected, parse_all, parse_u64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let blueprint = Blueprint::parse(input)?;
+
+    let mut machine = Machine::new(&blueprint);
+    machine.run(blueprint.steps);
+    let part1 = machine.tape.count_ones();
+
+    Ok((part1, "Finished!"))
+}
+
+/// Cells per block of tape, which is also how far a macro step can reach
+const BLOCK: i64 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blueprint {
+    names: Vec<char>,
+    start: usize,
+    steps: u64,
+    /// What to do on reading 0 and 1, for each state
+    rules: Vec<[Rule; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rule {
+    write: bool,
+    direction: Direction,
+    next: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Left,
+    Right,
+}
+
+/// A tape of bits going on forever both ways. Only blocks that have been written to are stored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Tape {
+    blocks: HashMap<i64, u16>,
+}
+
+/// One step of a machine, for tracing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    state: usize,
+    position: i64,
+    read: bool,
+    rule: Rule,
+}
+
+/// Everything that happens between the head entering a block and leaving it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MacroStep {
+    block: u16,
+    state: usize,
+    direction: Direction,
+    steps: u64,
+}
+
+struct Machine<'a> {
+    blueprint: &'a Blueprint,
+    tape: Tape,
+    position: i64,
+    state: usize,
+    steps: u64,
+    /// Macro steps by state, block and offset into the block. Steps that run out of time before
+    /// leaving the block aren't kept
+    macro_steps: HashMap<(usize, u16, i64), MacroStep>,
+}
+
+/// What to write, which way to move and the name of the next state, not yet checked
+type RawRule<'a> = (bool, Direction, &'a str);
+
+type RawState<'a> = (&'a str, [RawRule<'a>; 2]);
+
+impl Blueprint {
+    fn parse(input: &str) -> Result<Blueprint, ParseError> {
+        let (start, steps, raw_states) = parse_all(input, parse_blueprint)?;
+        let names = raw_states
+            .iter()
+            .map(|(name, _)| name.chars().next().unwrap())
+            .collect::<Vec<_>>();
+        let index = |name: &str| {
+            let c = name.chars().next().unwrap();
+            names.iter().position(|other| *other == c).ok_or_else(|| {
+                ParseError::new(input, name.offset_from(&input), format!("no state {c}"))
+            })
+        };
+
+        for (i, (name, _)) in raw_states.iter().enumerate() {
+            if names[..i].contains(&names[i]) {
+                let message = format!("state {name} is defined twice");
+                return Err(ParseError::new(input, name.offset_from(&input), message));
             }
-            position += match state.true_dir {
-                Direction::Left => -1,
-                Direction::Right => 1,
+        }
+        let rules = raw_states
+            .iter()
+            .map(|(_, rules)| {
+                let rule = |(write, direction, next): RawRule| {
+                    Ok(Rule {
+                        write,
+                        direction,
+                        next: index(next)?,
+                    })
+                };
+                Ok([rule(rules[0])?, rule(rules[1])?])
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Blueprint {
+            start: index(start)?,
+            names,
+            steps,
+            rules,
+        })
+    }
+}
+
+impl Tape {
+    fn get(&self, position: i64) -> bool {
+        let block = self.blocks.get(&position.div_euclid(BLOCK)).unwrap_or(&0);
+        block & (1 << position.rem_euclid(BLOCK)) != 0
+    }
+
+    fn set(&mut self, position: i64, value: bool) {
+        let block = self.blocks.entry(position.div_euclid(BLOCK)).or_default();
+        let bit = 1 << position.rem_euclid(BLOCK);
+        match value {
+            true => *block |= bit,
+            false => *block &= !bit,
+        }
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.blocks.values().map(|block| block.count_ones()).sum()
+    }
+}
+
+impl<'a> Machine<'a> {
+    fn new(blueprint: &'a Blueprint) -> Self {
+        Self {
+            blueprint,
+            tape: Tape::default(),
+            position: 0,
+            state: blueprint.start,
+            steps: 0,
+            macro_steps: HashMap::new(),
+        }
+    }
+
+    fn step(&mut self) -> Transition {
+        let read = self.tape.get(self.position);
+        let rule = self.blueprint.rules[self.state][usize::from(read)];
+        let transition = Transition {
+            state: self.state,
+            position: self.position,
+            read,
+            rule,
+        };
+        self.tape.set(self.position, rule.write);
+        self.position += rule.direction.offset();
+        self.state = rule.next;
+        self.steps += 1;
+        transition
+    }
+
+    /// The first `count` transitions from the current configuration
+    #[allow(dead_code)]
+    fn trace(&mut self, count: usize) -> Vec<Transition> {
+        (0..count).map(|_| self.step()).collect()
+    }
+
+    /// Runs for `steps` more steps, a block at a time where it can
+    fn run(&mut self, steps: u64) {
+        let end = self.steps + steps;
+        while self.steps < end {
+            let remaining = end - self.steps;
+            let block_index = self.position.div_euclid(BLOCK);
+            let offset = self.position.rem_euclid(BLOCK);
+            let block = *self.tape.blocks.get(&block_index).unwrap_or(&0);
+            let key = (self.state, block, offset);
+
+            let macro_step = match self.macro_steps.get(&key) {
+                Some(macro_step) => Some(*macro_step),
+                None => {
+                    let macro_step = self.macro_step(self.state, block, offset, remaining);
+                    if let Some(macro_step) = macro_step {
+                        self.macro_steps.insert(key, macro_step);
+                    }
+                    macro_step
+                }
             };
-            state = &instructions[&state.true_next_state];
-        } else {
-            if state.false_write {
-                tape.insert(position);
-            } else {
-                tape.remove(&position);
+            match macro_step {
+                Some(macro_step) if macro_step.steps <= remaining => {
+                    self.tape.blocks.insert(block_index, macro_step.block);
+                    self.position = match macro_step.direction {
+                        Direction::Left => block_index * BLOCK - 1,
+                        Direction::Right => (block_index + 1) * BLOCK,
+                    };
+                    self.state = macro_step.state;
+                    self.steps += macro_step.steps;
+                }
+                // Not enough time left to leave the block, so finish a step at a time
+                _ => {
+                    for _ in 0..remaining {
+                        self.step();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs inside one block until the head leaves it, giving up after `limit` steps
+    fn macro_step(
+        &self,
+        mut state: usize,
+        mut block: u16,
+        mut offset: i64,
+        limit: u64,
+    ) -> Option<MacroStep> {
+        for steps in 1..=limit {
+            let bit = 1 << offset;
+            let rule = self.blueprint.rules[state][usize::from(block & bit != 0)];
+            match rule.write {
+                true => block |= bit,
+                false => block &= !bit,
+            }
+            offset += rule.direction.offset();
+            state = rule.next;
+            if !(0..BLOCK).contains(&offset) {
+                return Some(MacroStep {
+                    block,
+                    state,
+                    direction: rule.direction,
+                    steps,
+                });
+            }
+        }
+        None
+    }
+
+    /// The cells from `from` to `to` inclusive, with the head's cell in brackets
+    #[allow(dead_code)]
+    fn window(&self, from: i64, to: i64) -> String {
+        let mut window = String::new();
+        for position in from..=to {
+            let value = u8::from(self.tape.get(position));
+            match position == self.position {
+                true => write!(window, "[{value}]").unwrap(),
+                false => write!(window, " {value} ").unwrap(),
             }
-            position += match state.false_dir {
-                Direction::Left => -1,
-                Direction::Right => 1,
-            };
-            state = &instructions[&state.false_next_state];
         }
+        window
     }
+}
 
-    (tape.len(), "Finished!")
+impl Direction {
+    fn offset(&self) -> i64 {
+        match self {
+            Direction::Left => -1,
+            Direction::Right => 1,
+        }
+    }
 }
 
-#[derive(Debug)]
-struct Instruction {
-    false_write: bool,
-    false_dir: Direction,
-    false_next_state: char,
-    true_write: bool,
-    true_dir: Direction,
-    true_next_state: char,
+impl Transition {
+    #[allow(dead_code)]
+    fn display<'a>(&'a self, blueprint: &'a Blueprint) -> impl Display + 'a {
+        TransitionDisplay {
+            transition: self,
+            blueprint,
+        }
+    }
 }
 
-#[derive(Debug)]
-enum Direction {
-    Left,
-    Right,
+struct TransitionDisplay<'a> {
+    transition: &'a Transition,
+    blueprint: &'a Blueprint,
+}
+
+impl Display for TransitionDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Transition {
+            state,
+            position,
+            read,
+            rule,
+        } = self.transition;
+        let names = &self.blueprint.names;
+        write!(
+            f,
+            "{} at {position}: read {}, write {}, move {}, then {}",
+            names[*state],
+            u8::from(*read),
+            u8::from(rule.write),
+            match rule.direction {
+                Direction::Left => "left",
+                Direction::Right => "right",
+            },
+            names[rule.next]
+        )
+    }
+}
+
+fn parse_blueprint<'a>(input: &mut &'a str) -> winnow::Result<(&'a str, u64, Vec<RawState<'a>>)> {
+    (
+        "Begin in state ",
+        state_name.take(),
+        '.',
+        line_ending,
+        "Perform a diagnostic checksum after ",
+        parse_u64,
+        " steps.",
+        line_ending,
+        line_ending,
+        separated(1.., parse_state, (line_ending, line_ending)),
+    )
+        .map(|(_, start, _, _, _, steps, _, _, _, states)| (start, steps, states))
+        .parse_next(input)
+}
+
+fn parse_state<'a>(input: &mut &'a str) -> winnow::Result<RawState<'a>> {
+    (
+        "In state ",
+        state_name.take(),
+        ':',
+        line_ending,
+        parse_rule("0"),
+        line_ending,
+        parse_rule("1"),
+    )
+        .map(|(_, name, _, _, zero, _, one)| (name, [zero, one]))
+        .parse_next(input)
+}
+
+fn parse_rule<'a>(value: &'static str) -> impl Parser<&'a str, RawRule<'a>, ContextError> {
+    let line = |text: &'static str| (space0, text);
+    preceded(
+        (line("If the current value is "), value, ':', line_ending),
+        (
+            delimited(line("- Write the value "), parse_bit, ('.', line_ending)),
+            delimited(
+                line("- Move one slot to the "),
+                parse_direction,
+                ('.', line_ending),
+            ),
+            delimited(line("- Continue with state "), state_name.take(), '.'),
+        ),
+    )
+}
+
+fn state_name(input: &mut &str) -> winnow::Result<char> {
+    one_of(AsChar::is_alphanum)
+        .context(expected("state name"))
+        .parse_next(input)
+}
+
+fn parse_bit(input: &mut &str) -> winnow::Result<bool> {
+    alt(("0".value(false), "1".value(true)))
+        .context(expected("0 or 1"))
+        .parse_next(input)
+}
+
+fn parse_direction(input: &mut &str) -> winnow::Result<Direction> {
+    alt((
+        "left".value(Direction::Left),
+        "right".value(Direction::Right),
+    ))
+    .context(expected("left or right"))
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Begin in state A.
+Perform a diagnostic checksum after 6 steps.
+
+In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 0.
+    - Move one slot to the left.
+    - Continue with state B.
+
+In state B:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+";
+
+    #[test]
+    fn test_example() {
+        let (part1, _) = solve(EXAMPLE).unwrap();
+        assert_eq!(part1.to_string(), "3");
+
+        let blueprint = Blueprint::parse(EXAMPLE).unwrap();
+        let mut machine = Machine::new(&blueprint);
+        let trace = machine.trace(2);
+        assert_eq!(
+            trace[1].display(&blueprint).to_string(),
+            "B at 1: read 0, write 1, move left, then A"
+        );
+        machine.trace(4);
+        assert_eq!(machine.window(-3, 2), " 0  1  1 [0] 1  0 ");
+    }
+
+    #[test]
+    fn test_macro_steps() {
+        // A busy beaver, which wanders across plenty of blocks
+        let blueprint = Blueprint {
+            names: vec!['A', 'B', 'C', 'D'],
+            start: 0,
+            steps: 0,
+            rules: [
+                [(true, Direction::Right, 1), (true, Direction::Left, 1)],
+                [(true, Direction::Left, 0), (false, Direction::Left, 2)],
+                [(true, Direction::Right, 3), (true, Direction::Left, 3)],
+                [(true, Direction::Right, 3), (false, Direction::Right, 0)],
+            ]
+            .iter()
+            .map(|rules| {
+                rules.map(|(write, direction, next)| Rule {
+                    write,
+                    direction,
+                    next,
+                })
+            })
+            .collect(),
+        };
+        let mut fast = Machine::new(&blueprint);
+        let mut slow = Machine::new(&blueprint);
+        for steps in [1, 10, 100, 1_000, 10_000] {
+            fast.run(steps);
+            slow.trace(steps as usize);
+            assert_eq!(fast.steps, slow.steps);
+            assert_eq!((fast.position, fast.state), (slow.position, slow.state));
+            assert_eq!(fast.window(-100, 100), slow.window(-100, 100));
+            assert_eq!(fast.tape.count_ones(), slow.tape.count_ones());
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let unknown = EXAMPLE.replace("state B.\n\nIn state B", "state C.\n\nIn state B");
+        let error = Blueprint::parse(&unknown).unwrap_err();
+        assert_eq!((error.line(), error.column()), (12, 27));
+
+        let twice = EXAMPLE.replace("In state B", "In state A");
+        let error = Blueprint::parse(&twice).unwrap_err();
+        assert_eq!((error.line(), error.column()), (14, 10));
+
+        let direction = EXAMPLE.replace("slot to the left", "slot to the up");
+        let error = Blueprint::parse(&direction).unwrap_err();
+        assert_eq!((error.line(), error.column()), (11, 28));
+    }
 }
@@ -1,29 +1,13 @@
+use crate::common::{Automaton, Neighbourhood, Rule};
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let seating = Seating::from(input);
 
     // Part 1: Step until no change
-    let mut state = seating.clone();
-    let mut last_state = Seating { data: Vec::new() };
-    loop {
-        if state == last_state {
-            break;
-        }
-        last_state = state;
-        state = last_state.step();
-    }
-    let part1 = state.occupied();
+    let part1 = seating.settle(Neighbourhood::Moore, 4);
 
-    // Part 2: Alt-step until no change
-    let mut state = seating;
-    let mut last_state = Seating { data: Vec::new() };
-    loop {
-        if state == last_state {
-            break;
-        }
-        last_state = state;
-        state = last_state.step2();
-    }
-    let part2 = state.occupied();
+    // Part 2: Step by line of sight until no change
+    let part2 = seating.settle(Neighbourhood::LineOfSight, 5);
 
     (part1, part2)
 }
@@ -34,146 +18,25 @@ struct Seating {
 }
 
 impl Seating {
-    fn step(&self) -> Seating {
-        let data = (0..self.data.len())
-            .map(|y| {
-                (0..self.data[0].len())
-                    .map(|x| {
-                        let curr = &self.data[y][x];
-                        match curr {
-                            Tile::Empty => {
-                                if self.count_adjacent(x, y, Tile::Occupied) == 0 {
-                                    Tile::Occupied
-                                } else {
-                                    Tile::Empty
-                                }
-                            }
-                            Tile::Occupied => {
-                                if self.count_adjacent(x, y, Tile::Occupied) >= 4 {
-                                    Tile::Empty
-                                } else {
-                                    Tile::Occupied
-                                }
-                            }
-                            Tile::Floor => Tile::Floor,
-                        }
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        Self { data }
-    }
-
-    fn step2(&self) -> Self {
-        let data = (0..self.data.len())
-            .map(|y| {
-                (0..self.data[0].len())
-                    .map(|x| {
-                        let curr = &self.data[y][x];
-                        match curr {
-                            Tile::Empty => {
-                                if self.count_occupied_by_sight(x, y) == 0 {
-                                    Tile::Occupied
-                                } else {
-                                    Tile::Empty
-                                }
-                            }
-                            Tile::Occupied => {
-                                if self.count_occupied_by_sight(x, y) >= 5 {
-                                    Tile::Empty
-                                } else {
-                                    Tile::Occupied
-                                }
-                            }
-                            Tile::Floor => Tile::Floor,
-                        }
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        Self { data }
-    }
-
-    fn count_adjacent(&self, x: usize, y: usize, tile_type: Tile) -> usize {
-        let mut count = 0;
-        let width = self.data[0].len();
-        let height = self.data.len();
-        for j in -1..=1 {
-            for i in -1..=1 {
-                if i == 0 && j == 0 {
-                    continue;
-                }
-                if i == -1 && x == 0 {
-                    continue;
-                }
-                if i == 1 && x == width - 1 {
-                    continue;
-                }
-                if j == -1 && y == 0 {
-                    continue;
-                }
-                if j == 1 && y == height - 1 {
-                    continue;
-                }
-                if self.data[(y as i64 + j) as usize][(x as i64 + i) as usize] == tile_type {
-                    count += 1;
+    /// Seats fill when nothing is seen and empty when at least `tolerance` occupied seats are seen
+    fn settle(&self, neighbourhood: Neighbourhood, tolerance: usize) -> usize {
+        let size = [self.data[0].len(), self.data.len()];
+        let rule = Rule::life_like(&[0], &(0..tolerance).collect::<Vec<_>>());
+        let mut automaton = Automaton::dense(
+            size,
+            |&[x, y]| self.data[y as usize][x as usize] != Tile::Floor,
+            neighbourhood,
+            rule,
+        );
+        for (y, row) in self.data.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if *tile == Tile::Occupied {
+                    automaton.set([x as i64, y as i64], true);
                 }
             }
         }
-        count
-    }
-
-    /// Counts the number of visible occupied seats
-    ///
-    /// Visible meaning straight or diagonally from `(x, y)`
-    fn count_occupied_by_sight(&self, x: usize, y: usize) -> usize {
-        const DIRS: &[(i64, i64)] = &[
-            (0, 1),
-            (1, 0),
-            (0, -1),
-            (-1, 0),
-            (1, 1),
-            (1, -1),
-            (-1, 1),
-            (-1, -1),
-        ];
-        DIRS.iter()
-            .filter(|(dir_y, dir_x)| {
-                let mut result = false;
-                for i in 1.. {
-                    let cur_x = x as i64 + (*dir_x * i);
-                    let cur_y = y as i64 + (*dir_y * i);
-                    if cur_x < 0
-                        || cur_y < 0
-                        || cur_x as usize >= self.data[0].len()
-                        || cur_y as usize >= self.data.len()
-                    {
-                        result = false;
-                        break;
-                    }
-                    let tile = &self.data[cur_y as usize][cur_x as usize];
-                    match tile {
-                        Tile::Occupied => {
-                            result = true;
-                            break;
-                        }
-                        Tile::Empty => {
-                            result = false;
-                            break;
-                        }
-                        Tile::Floor => continue,
-                    }
-                }
-                result
-            })
-            .count()
-    }
-
-    fn occupied(&self) -> usize {
-        self.data
-            .iter()
-            .map(|row| row.iter().filter(|&t| t == &Tile::Occupied).count())
-            .sum()
+        automaton.run_until_stable();
+        automaton.alive_count()
     }
 }
 
@@ -203,3 +66,25 @@ enum Tile {
     Empty,
     Occupied,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known() {
+        let input = "L.LL.LL.LL
+LLLLLLL.LL
+L.L.L..L..
+LLLL.LL.LL
+L.LL.LL.LL
+L.LLLLL.LL
+..L.L.....
+LLLLLLLLLL
+L.LLLLLL.L
+L.LLLLL.LL";
+        let (part1, part2) = solve(input);
+        assert_eq!(part1.to_string(), "37");
+        assert_eq!(part2.to_string(), "26");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::common::{solve_congruences, Congruence};
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let mut lines = input.lines();
     let earliest = lines.next().unwrap().parse::<u64>().unwrap();
@@ -26,33 +28,24 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let wait_time = depart_time - earliest;
     let part1 = bus_id * wait_time;
 
-    // https://math.stackexchange.com/a/1485998
-    // Assume all numbers are co-prime
-    let final_mod = bus_ids.iter().filter_map(|id| id.as_ref()).product::<u64>();
-    let numbered_ids = bus_ids
+    // Bus i leaves i minutes after the first
+    let congruences = bus_ids
         .iter()
         .enumerate()
-        // want t to = -offset % bus_id
-        .filter_map(|(i, id)| id.map(|v| ((-(i as i64)).rem_euclid(v as i64), v)))
-        .collect::<Vec<_>>();
-    println!("{:?}", numbered_ids);
-    // Skip e0 as offset == 0
-    let part2 = numbered_ids
-        .iter()
-        .skip(1)
-        .map(|(offset, bus_id)| {
-            let a = numbered_ids
-                .iter()
-                .filter(|(i, _)| i != offset)
-                .map(|(_, id)| id)
-                .product::<u64>();
-            // a * x = 1 (mod bus_id)
-            let x = (1..).find(|x| (a * x) % bus_id == 1).unwrap();
-            let e = (a * x) % final_mod;
-            *offset as u64 * e
-        })
-        .sum::<u64>()
-        % final_mod;
+        .filter_map(|(i, id)| id.map(|id| Congruence::new(-(i as i128), id as i128)));
+    let part2 = solve_congruences(congruences).unwrap().residue();
 
     (part1, part2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let (part1, part2) = solve("939\n7,13,x,x,59,x,31,19");
+        assert_eq!(part1.to_string(), "295");
+        assert_eq!(part2.to_string(), "1068781");
+    }
+}
@@ -1,4 +1,6 @@
-use std::{collections::HashMap, num::ParseIntError, ops::RangeInclusive, str::FromStr};
+use std::{num::ParseIntError, ops::RangeInclusive, str::FromStr};
+
+use crate::common::Candidates;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     // Parse input
@@ -43,38 +45,19 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         .iter()
         .filter(|ticket| ticket.iter().all(|v| rules.iter().any(|r| r.contains(v))))
         .collect::<Vec<_>>();
-    // Find all possible valid columns for each rule
-    let rule_matches = rules
-        .iter()
-        .map(|rule| {
-            (0..ticket_len)
-                .filter(|i| {
-                    valid_tickets
-                        .iter()
-                        .all(|ticket| rule.contains(&ticket[*i]))
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    // Match rules to columns
-    // There will be one rule that has only one possible column each step
-    let mut rule_indexes = HashMap::new();
-    let mut queue = rule_matches;
-    for _ in 0..rules.len() {
-        let (rule_index, potential) = queue
+    // Each rule goes with the one column all valid tickets fit
+    let candidates = Candidates::from_fn(rules.len(), ticket_len, |rule, column| {
+        valid_tickets
             .iter()
-            .enumerate()
-            .find(|(_, potential)| potential.len() == 1)
-            .unwrap();
-        let column = potential[0];
-        rule_indexes.insert(&rules[rule_index], column);
-        queue.iter_mut().for_each(|p| p.retain(|v| v != &column));
-    }
+            .all(|ticket| rules[rule].contains(&ticket[column]))
+    });
+    let columns = candidates.solve_unique().unwrap();
     // Calculate product of all `departure` entries on own ticket
-    let part2 = rule_indexes
+    let part2 = rules
         .iter()
+        .zip(columns)
         .filter(|(r, _)| r.name.starts_with("departure"))
-        .map(|(_, i)| own_ticket[*i])
+        .map(|(_, i)| own_ticket[i])
         .product::<u64>();
 
     (part1, part2)
@@ -1,136 +1,254 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::{fmt::Display, iter::Peekable};
+
+use num::{BigInt, Zero};
+
+/// Everything evaluated left to right
+const SAME_PRECEDENCE: PrecedenceTable = PrecedenceTable(&[
+    (Operator::Add, 1, Associativity::Left),
+    (Operator::Multiply, 1, Associativity::Left),
+]);
+
+/// Addition before multiplication
+const ADDITION_FIRST: PrecedenceTable = PrecedenceTable(&[
+    (Operator::Add, 2, Associativity::Left),
+    (Operator::Multiply, 1, Associativity::Left),
+]);
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let expressions = input
-        .lines()
-        .map(|l| l.parse::<Expression>().unwrap())
-        .collect::<Vec<_>>();
+    let sum = |table: &PrecedenceTable| {
+        input
+            .lines()
+            .map(|l| {
+                Expression::parse(l, table)
+                    .unwrap()
+                    .evaluate::<u64>()
+                    .unwrap()
+            })
+            .sum::<u64>()
+    };
 
-    let part1 = expressions.iter().map(|e| e.evaluate()).sum::<u64>();
-    let part2 = expressions.iter().map(|e| e.evaluate2()).sum::<u64>();
+    let part1 = sum(&SAME_PRECEDENCE);
+    let part2 = sum(&ADDITION_FIRST);
 
     (part1, part2)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Expression {
     Number(u64),
-    Complex {
-        children: Vec<Expression>,
-        operators: Vec<Operator>,
+    Binary {
+        operator: Operator,
+        left: Box<Expression>,
+        right: Box<Expression>,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    #[allow(dead_code)]
+    Right,
+}
+
+/// How tightly each operator binds, higher first. Operators missing from the table aren't
+/// allowed
+#[derive(Debug, Clone, Copy)]
+struct PrecedenceTable<'a>(&'a [(Operator, u8, Associativity)]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Number(u64),
+    Operator(Operator),
+    LeftBracket,
+    RightBracket,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExpressionError {
+    UnexpectedCharacter(char),
+    UnexpectedToken(Token),
+    UnexpectedEnd,
+    UnsupportedOperator(Operator),
+    Overflow,
+    DivisionByZero,
+}
+
+/// Something expressions can be evaluated as
+trait Value: Sized {
+    fn from_u64(value: u64) -> Self;
+
+    fn apply(operator: Operator, left: Self, right: Self) -> Result<Self, ExpressionError>;
+}
+
 impl Expression {
-    fn from_tokens<I: Iterator<Item = Token>>(tokens: &mut I) -> Self {
-        let next = tokens.next().unwrap();
-        let first_child = match next {
-            Token::Number(value) => Expression::Number(value),
-            Token::LeftBracket => Self::from_tokens(tokens),
-            _ => unreachable!(),
-        };
-        let mut children = vec![first_child];
-        let mut operators = Vec::new();
-        loop {
-            match tokens.next() {
-                None => break,
-                Some(Token::RightBracket) => break,
-                Some(Token::Operator(op)) => operators.push(op),
-                _ => unreachable!(),
-            }
-            let child = match tokens.next().unwrap() {
-                Token::Number(value) => Expression::Number(value),
-                Token::LeftBracket => Self::from_tokens(tokens),
-                _ => unreachable!(),
-            };
-            children.push(child);
-        }
-        if children.len() == 1 {
-            return children.pop().unwrap();
-        }
-        Expression::Complex {
-            children,
-            operators,
+    /// Parses `s` with a Pratt parser, using `table` to decide which operators bind first
+    fn parse(s: &str, table: &PrecedenceTable) -> Result<Self, ExpressionError> {
+        let mut tokens = tokenize(s)?.into_iter().peekable();
+        let expression = Self::parse_tokens(&mut tokens, table, 0)?;
+        match tokens.next() {
+            Some(token) => Err(ExpressionError::UnexpectedToken(token)),
+            None => Ok(expression),
         }
     }
 
-    fn evaluate(&self) -> u64 {
-        match self {
-            Expression::Number(v) => *v,
-            Expression::Complex {
-                children,
-                operators,
-            } => {
-                let mut children = children.iter();
-                let mut curr = children.next().unwrap().evaluate();
-                for (operator, child) in operators.iter().zip(children) {
-                    let child_value = child.evaluate();
-                    match operator {
-                        Operator::Add => curr += child_value,
-                        Operator::Multiply => curr *= child_value,
-                    }
+    /// Parses as far as the next operator binding less tightly than `min_precedence`
+    fn parse_tokens(
+        tokens: &mut Peekable<impl Iterator<Item = Token>>,
+        table: &PrecedenceTable,
+        min_precedence: u8,
+    ) -> Result<Self, ExpressionError> {
+        let mut left = match tokens.next() {
+            Some(Token::Number(value)) => Expression::Number(value),
+            Some(Token::LeftBracket) => {
+                let inner = Self::parse_tokens(tokens, table, 0)?;
+                match tokens.next() {
+                    Some(Token::RightBracket) => inner,
+                    Some(token) => return Err(ExpressionError::UnexpectedToken(token)),
+                    None => return Err(ExpressionError::UnexpectedEnd),
                 }
-                curr
             }
+            Some(token) => return Err(ExpressionError::UnexpectedToken(token)),
+            None => return Err(ExpressionError::UnexpectedEnd),
+        };
+
+        while let Some(Token::Operator(operator)) = tokens.peek().copied() {
+            let (precedence, associativity) = table
+                .binding(operator)
+                .ok_or(ExpressionError::UnsupportedOperator(operator))?;
+            if precedence < min_precedence {
+                break;
+            }
+            tokens.next();
+
+            let right_precedence = match associativity {
+                Associativity::Left => precedence + 1,
+                Associativity::Right => precedence,
+            };
+            let right = Self::parse_tokens(tokens, table, right_precedence)?;
+            left = Expression::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
         }
+        Ok(left)
     }
 
-    fn evaluate2(&self) -> u64 {
+    fn evaluate<V: Value>(&self) -> Result<V, ExpressionError> {
         match self {
-            Expression::Number(v) => *v,
-            Expression::Complex {
-                children,
-                operators,
-            } => {
-                // Two passes. First addition, then multiplication
-                let nums = children.iter().map(|c| c.evaluate2()).collect::<Vec<_>>();
-                let mut to_mult = Vec::new();
-                let mut curr = nums[0];
-                for (n, op) in nums[1..].iter().zip(operators.iter()) {
-                    match op {
-                        Operator::Add => curr += *n,
-                        Operator::Multiply => {
-                            to_mult.push(curr);
-                            curr = *n;
-                        }
-                    }
-                }
-                to_mult.push(curr);
-                to_mult.iter().product()
-            }
+            Expression::Number(value) => Ok(V::from_u64(*value)),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => V::apply(*operator, left.evaluate()?, right.evaluate()?),
         }
     }
 }
 
-impl FromStr for Expression {
-    type Err = ParseIntError;
+impl PrecedenceTable<'_> {
+    fn binding(&self, operator: Operator) -> Option<(u8, Associativity)> {
+        self.0
+            .iter()
+            .find(|(other, _, _)| *other == operator)
+            .map(|(_, precedence, associativity)| (*precedence, *associativity))
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // Tokenize
-        let mut tokens = s.chars().filter(|c| *c != ' ').map(|c| match c {
+fn tokenize(s: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            ' ' => continue,
             '(' => Token::LeftBracket,
             ')' => Token::RightBracket,
             '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Subtract),
             '*' => Token::Operator(Operator::Multiply),
-            n if n.is_ascii_digit() => Token::Number(n.to_digit(10).unwrap() as u64),
-            _ => unreachable!(),
+            '/' => Token::Operator(Operator::Divide),
+            n if n.is_ascii_digit() => {
+                let mut value = n.to_digit(10).unwrap() as u64;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    chars.next();
+                    value = value
+                        .checked_mul(10)
+                        .and_then(|value| value.checked_add(digit as u64))
+                        .ok_or(ExpressionError::Overflow)?;
+                }
+                Token::Number(value)
+            }
+            c => return Err(ExpressionError::UnexpectedCharacter(c)),
         });
-        Ok(Self::from_tokens(&mut tokens))
     }
+    Ok(tokens)
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Operator {
-    Add,
-    Multiply,
+/// Checked, so overflow is an error rather than a wrap or panic
+impl Value for u64 {
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+
+    fn apply(operator: Operator, left: Self, right: Self) -> Result<Self, ExpressionError> {
+        match operator {
+            Operator::Add => left.checked_add(right),
+            Operator::Subtract => left.checked_sub(right),
+            Operator::Multiply => left.checked_mul(right),
+            Operator::Divide if right == 0 => return Err(ExpressionError::DivisionByZero),
+            Operator::Divide => left.checked_div(right),
+        }
+        .ok_or(ExpressionError::Overflow)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Token {
-    Number(u64),
-    Operator(Operator),
-    LeftBracket,
-    RightBracket,
+impl Value for BigInt {
+    fn from_u64(value: u64) -> Self {
+        BigInt::from(value)
+    }
+
+    fn apply(operator: Operator, left: Self, right: Self) -> Result<Self, ExpressionError> {
+        Ok(match operator {
+            Operator::Add => left + right,
+            Operator::Subtract => left - right,
+            Operator::Multiply => left * right,
+            Operator::Divide if right.is_zero() => return Err(ExpressionError::DivisionByZero),
+            Operator::Divide => left / right,
+        })
+    }
+}
+
+/// Fully parenthesised, so the structure doesn't depend on any precedence table
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Number(value) => write!(f, "{value}"),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => write!(f, "({left} {operator} {right})"),
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+        })
+    }
 }
 
 #[cfg(test)]
@@ -139,8 +257,60 @@ mod tests {
 
     #[test]
     fn test_known() {
-        let expr = "2 * 3 + (4 * 5)".parse::<Expression>().unwrap();
-        assert_eq!(expr.evaluate(), 26);
-        assert_eq!(expr.evaluate2(), 46);
+        let evaluate = |s, table| {
+            Expression::parse(s, table)
+                .unwrap()
+                .evaluate::<u64>()
+                .unwrap()
+        };
+        assert_eq!(evaluate("2 * 3 + (4 * 5)", &SAME_PRECEDENCE), 26);
+        assert_eq!(evaluate("2 * 3 + (4 * 5)", &ADDITION_FIRST), 46);
+        let s = "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2";
+        assert_eq!(evaluate(s, &SAME_PRECEDENCE), 13632);
+        assert_eq!(evaluate(s, &ADDITION_FIRST), 23340);
+    }
+
+    #[test]
+    fn test_tables() {
+        let arithmetic = PrecedenceTable(&[
+            (Operator::Add, 1, Associativity::Left),
+            (Operator::Subtract, 1, Associativity::Left),
+            (Operator::Multiply, 2, Associativity::Left),
+            (Operator::Divide, 2, Associativity::Left),
+        ]);
+        let parse = |s, table| Expression::parse(s, table).unwrap();
+
+        let expression = parse("10 - 4 - 3 * 12 / 6 / 2", &arithmetic);
+        assert_eq!(expression.to_string(), "((10 - 4) - (((3 * 12) / 6) / 2))");
+        assert_eq!(expression.evaluate::<u64>(), Ok(3));
+
+        let right = PrecedenceTable(&[(Operator::Subtract, 1, Associativity::Right)]);
+        let expression = parse("10 - 4 - 3", &right);
+        assert_eq!(expression.to_string(), "(10 - (4 - 3))");
+        assert_eq!(expression.evaluate::<u64>(), Ok(9));
+
+        assert_eq!(
+            Expression::parse("1 - 2", &SAME_PRECEDENCE),
+            Err(ExpressionError::UnsupportedOperator(Operator::Subtract))
+        );
+        assert_eq!(
+            Expression::parse("(1 + 2", &SAME_PRECEDENCE),
+            Err(ExpressionError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Expression::parse("1 + 2)", &SAME_PRECEDENCE),
+            Err(ExpressionError::UnexpectedToken(Token::RightBracket))
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let s = "4294967296 * 4294967296 + 1";
+        let expression = Expression::parse(s, &SAME_PRECEDENCE).unwrap();
+        assert_eq!(expression.evaluate::<u64>(), Err(ExpressionError::Overflow));
+        assert_eq!(
+            expression.evaluate::<BigInt>(),
+            Ok(BigInt::from(u64::MAX) + 2)
+        );
     }
 }
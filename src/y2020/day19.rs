@@ -1,56 +1,43 @@
-use std::collections::HashMap;
-
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    // Parse input
-    let mut lines = input.lines();
-    let mut rules = lines
-        .by_ref()
-        .take_while(|l| !l.is_empty())
-        .map(|l| {
-            let mut parts = l.split(": ");
-            let id = parts.next().unwrap().parse().unwrap();
-            let children_strs = parts.next().unwrap().split(" | ").collect::<Vec<_>>();
-            let children = if children_strs.len() == 1 && children_strs[0].starts_with('"') {
-                ChildType::Char(children_strs[0].chars().nth(1).unwrap())
-            } else {
-                ChildType::Rules(
-                    children_strs
-                        .iter()
-                        .map(|s| s.split(' ').map(|s| s.parse().unwrap()).collect::<Vec<_>>())
-                        .collect::<Vec<_>>(),
-                )
-            };
-            let rule = Rule { id, children };
-            (id, rule)
-        })
-        .collect::<HashMap<_, _>>();
-    let messages = lines.collect::<Vec<_>>();
-
-    // Count all matches
-    let part1 = messages
-        .iter()
-        .filter(|m| rules[&0].matches(m, &rules))
-        .count();
-
-    // Return early if rule 8 doesn't exist
-    if !rules.contains_key(&8) {
-        return (part1, 0);
-    }
-
-    // Change to recursive rules and count all matches
-    rules.get_mut(&8).unwrap().children = ChildType::Rules(vec![vec![42], vec![42, 8]]);
-    rules.get_mut(&11).unwrap().children = ChildType::Rules(vec![vec![42, 31], vec![42, 11, 31]]);
-    let part2 = messages
-        .iter()
-        .filter(|m| rules[&0].matches(m, &rules))
-        .count();
-
-    (part1, part2)
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::common::ParseError;
+
+/// The replacement rules part 2 asks for, which make the language infinite
+const LOOPING_RULES: &str = "8: 42 | 42 8
+11: 42 31 | 42 11 31";
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let (rules, messages) = input
+        .split_once("\n\n")
+        .ok_or_else(|| ParseError::new(input, input.len(), "expected rules, then messages"))?;
+    let mut grammar = rules
+        .parse::<Grammar>()
+        .map_err(|()| ParseError::new(input, 0, "invalid rules"))?;
+    let messages = messages.lines().collect::<Vec<_>>();
+
+    let part1 = messages.iter().filter(|m| grammar.matches(0, m)).count();
+
+    let looping_rules = LOOPING_RULES.parse().unwrap();
+    if let Err(GrammarError::UnknownRule(id)) = grammar.replace_rules(looping_rules) {
+        let message = format!("the looping rules need rule {id}");
+        return Err(ParseError::new(input, rules.len(), message));
+    }
+    let part2 = messages.iter().filter(|m| grammar.matches(0, m)).count();
+
+    Ok((part1, part2))
+}
+
+#[derive(Debug)]
+struct Grammar {
+    rules: HashMap<usize, Rule>,
 }
 
 #[derive(Debug)]
 struct Rule {
-    #[allow(dead_code)]
     id: usize,
     children: ChildType,
 }
@@ -61,36 +48,339 @@ enum ChildType {
     Rules(Vec<Vec<usize>>),
 }
 
-impl Rule {
-    fn matches(&self, message: &str, rules: &HashMap<usize, Rule>) -> bool {
-        self.inner_matches(&message.chars().collect::<Vec<_>>(), 0, rules)
-            .contains(&message.len())
+#[derive(Debug, PartialEq, Eq)]
+enum GrammarError {
+    UnknownRule(usize),
+    /// The rule can reach itself so its language is infinite
+    Recursive(usize),
+}
+
+/// An Earley item: partway through an alternative of a rule that started at `origin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alternative: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// How a message was derived from a rule
+#[derive(Debug, PartialEq, Eq)]
+enum Derivation {
+    Char(char),
+    Rule {
+        id: usize,
+        alternative: usize,
+        children: Vec<Derivation>,
+    },
+}
+
+impl Grammar {
+    /// Swaps in new definitions for rules, as long as everything they refer to exists
+    fn replace_rules(&mut self, rules: Grammar) -> Result<(), GrammarError> {
+        for rule in rules.rules.values() {
+            if let ChildType::Rules(options) = &rule.children {
+                let unknown = options
+                    .iter()
+                    .flatten()
+                    .find(|id| !self.rules.contains_key(id) && !rules.rules.contains_key(id));
+                if let Some(id) = unknown {
+                    return Err(GrammarError::UnknownRule(*id));
+                }
+            }
+        }
+        self.rules.extend(rules.rules);
+        Ok(())
+    }
+
+    /// The rules making up the `alternative` of `rule`, or nothing for a character rule
+    fn symbols(&self, rule: usize, alternative: usize) -> &[usize] {
+        match &self.rules[&rule].children {
+            ChildType::Char(_) => &[],
+            ChildType::Rules(options) => &options[alternative],
+        }
+    }
+
+    /// Builds the Earley chart for `message`. No rule can match the empty string, so there's no
+    /// need to deal with nullable rules
+    fn chart(&self, start: usize, message: &[char]) -> Vec<HashSet<Item>> {
+        let mut chart = vec![HashSet::new(); message.len() + 1];
+        let mut pending = Vec::new();
+        self.predict(start, 0, &mut chart[0], &mut pending);
+
+        for position in 0..=message.len() {
+            pending.extend(chart[position].iter().copied());
+            while let Some(item) = pending.pop() {
+                match &self.rules[&item.rule].children {
+                    ChildType::Char(c) => {
+                        if item.dot == 0 && message.get(position) == Some(c) {
+                            chart[position + 1].insert(Item { dot: 1, ..item });
+                        }
+                        if item.dot == 1 {
+                            self.complete(item, position, &mut chart, &mut pending);
+                        }
+                    }
+                    ChildType::Rules(_) => match self.next_symbol(&item) {
+                        Some(next) => {
+                            self.predict(next, position, &mut chart[position], &mut pending)
+                        }
+                        None => self.complete(item, position, &mut chart, &mut pending),
+                    },
+                }
+            }
+        }
+        chart
     }
 
-    fn inner_matches(
+    fn predict(
         &self,
+        rule: usize,
+        position: usize,
+        set: &mut HashSet<Item>,
+        pending: &mut Vec<Item>,
+    ) {
+        let Some(rule) = self.rules.get(&rule) else {
+            return;
+        };
+        let alternatives = match &rule.children {
+            ChildType::Char(_) => 1,
+            ChildType::Rules(options) => options.len(),
+        };
+        for alternative in 0..alternatives {
+            let item = Item {
+                rule: rule.id,
+                alternative,
+                dot: 0,
+                origin: position,
+            };
+            if set.insert(item) {
+                pending.push(item);
+            }
+        }
+    }
+
+    fn complete(
+        &self,
+        item: Item,
+        position: usize,
+        chart: &mut [HashSet<Item>],
+        pending: &mut Vec<Item>,
+    ) {
+        let waiting = chart[item.origin]
+            .iter()
+            .filter(|waiting| self.next_symbol(waiting) == Some(item.rule))
+            .copied()
+            .collect::<Vec<_>>();
+        for waiting in waiting {
+            let advanced = Item {
+                dot: waiting.dot + 1,
+                ..waiting
+            };
+            if chart[position].insert(advanced) {
+                pending.push(advanced);
+            }
+        }
+    }
+
+    /// The rule after the item's dot, if there is one
+    fn next_symbol(&self, item: &Item) -> Option<usize> {
+        self.symbols(item.rule, item.alternative)
+            .get(item.dot)
+            .copied()
+    }
+
+    fn is_finished(&self, item: &Item) -> bool {
+        match &self.rules[&item.rule].children {
+            ChildType::Char(_) => item.dot == 1,
+            ChildType::Rules(options) => item.dot == options[item.alternative].len(),
+        }
+    }
+
+    fn is_complete(&self, chart: &[HashSet<Item>], rule: usize, start: usize, end: usize) -> bool {
+        chart[end]
+            .iter()
+            .any(|item| item.rule == rule && item.origin == start && self.is_finished(item))
+    }
+
+    /// Whether the whole of `message` matches `rule`
+    fn matches(&self, rule: usize, message: &str) -> bool {
+        let message = message.chars().collect::<Vec<_>>();
+        let chart = self.chart(rule, &message);
+        self.is_complete(&chart, rule, 0, message.len())
+    }
+
+    /// One way `message` can be derived from `rule`, if it matches
+    #[allow(dead_code)]
+    fn derivation(&self, rule: usize, message: &str) -> Option<Derivation> {
+        let message = message.chars().collect::<Vec<_>>();
+        let chart = self.chart(rule, &message);
+        self.derive(&chart, &message, rule, 0, message.len(), &mut Vec::new())
+    }
+
+    fn derive(
+        &self,
+        chart: &[HashSet<Item>],
         message: &[char],
-        index: usize,
-        rules: &HashMap<usize, Rule>,
-    ) -> Vec<usize> {
-        match &self.children {
-            ChildType::Char(c) => {
-                if index < message.len() && message[index] == *c {
-                    vec![index + 1]
-                } else {
-                    Vec::new()
+        rule: usize,
+        start: usize,
+        end: usize,
+        stack: &mut Vec<(usize, usize, usize)>,
+    ) -> Option<Derivation> {
+        // Stops rules like `1: 2` and `2: 1` deriving each other forever
+        if stack.contains(&(rule, start, end)) {
+            return None;
+        }
+        let completed = chart[end]
+            .iter()
+            .filter(|item| item.rule == rule && item.origin == start && self.is_finished(item))
+            .map(|item| item.alternative)
+            .collect::<BTreeSet<_>>();
+
+        stack.push((rule, start, end));
+        let derivation =
+            completed
+                .into_iter()
+                .find_map(|alternative| match self.rules[&rule].children {
+                    ChildType::Char(c) => Some(Derivation::Rule {
+                        id: rule,
+                        alternative,
+                        children: vec![Derivation::Char(c)],
+                    }),
+                    ChildType::Rules(_) => {
+                        let item = Item {
+                            rule,
+                            alternative,
+                            dot: self.symbols(rule, alternative).len(),
+                            origin: start,
+                        };
+                        self.derive_children(chart, message, item, end, stack)
+                            .map(|children| Derivation::Rule {
+                                id: rule,
+                                alternative,
+                                children,
+                            })
+                    }
+                });
+        stack.pop();
+        derivation
+    }
+
+    /// Derives the symbols before `item`'s dot, which ends at `end`, by walking back through
+    /// the chart
+    fn derive_children(
+        &self,
+        chart: &[HashSet<Item>],
+        message: &[char],
+        item: Item,
+        end: usize,
+        stack: &mut Vec<(usize, usize, usize)>,
+    ) -> Option<Vec<Derivation>> {
+        if item.dot == 0 {
+            return (end == item.origin).then(Vec::new);
+        }
+        let symbol = self.symbols(item.rule, item.alternative)[item.dot - 1];
+        let previous = Item {
+            dot: item.dot - 1,
+            ..item
+        };
+        (item.origin..end)
+            .filter(|middle| chart[*middle].contains(&previous))
+            .filter(|middle| self.is_complete(chart, symbol, *middle, end))
+            .find_map(|middle| {
+                let last = self.derive(chart, message, symbol, middle, end, stack)?;
+                let mut children = self.derive_children(chart, message, previous, middle, stack)?;
+                children.push(last);
+                Some(children)
+            })
+    }
+
+    /// Every message `rule` matches, if there are finitely many
+    #[allow(dead_code)]
+    fn language(&self, rule: usize) -> Result<BTreeSet<String>, GrammarError> {
+        self.language_inner(rule, &mut Vec::new(), &mut HashMap::new())
+    }
+
+    fn language_inner(
+        &self,
+        rule: usize,
+        stack: &mut Vec<usize>,
+        memo: &mut HashMap<usize, BTreeSet<String>>,
+    ) -> Result<BTreeSet<String>, GrammarError> {
+        if let Some(language) = memo.get(&rule) {
+            return Ok(language.clone());
+        }
+        if stack.contains(&rule) {
+            return Err(GrammarError::Recursive(rule));
+        }
+        let definition = self
+            .rules
+            .get(&rule)
+            .ok_or(GrammarError::UnknownRule(rule))?;
+        let options = match &definition.children {
+            ChildType::Char(c) => return Ok(BTreeSet::from([c.to_string()])),
+            ChildType::Rules(options) => options,
+        };
+
+        stack.push(rule);
+        let mut language = BTreeSet::new();
+        for sequence in options {
+            let mut prefixes = BTreeSet::from([String::new()]);
+            for child in sequence {
+                let suffixes = self.language_inner(*child, stack, memo)?;
+                prefixes = prefixes
+                    .iter()
+                    .flat_map(|prefix| suffixes.iter().map(move |suffix| prefix.clone() + suffix))
+                    .collect();
+            }
+            language.extend(prefixes);
+        }
+        stack.pop();
+
+        memo.insert(rule, language.clone());
+        Ok(language)
+    }
+}
+
+impl FromStr for Grammar {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .lines()
+            .map(|l| {
+                let (id, children) = l.split_once(": ").ok_or(())?;
+                let id = id.parse().map_err(|_| ())?;
+                let children = match children.strip_prefix('"') {
+                    Some(c) => ChildType::Char(c.chars().next().ok_or(())?),
+                    None => ChildType::Rules(
+                        children
+                            .split(" | ")
+                            .map(|s| s.split(' ').map(|s| s.parse().map_err(|_| ())).collect())
+                            .collect::<Result<_, _>>()?,
+                    ),
+                };
+                Ok((id, Rule { id, children }))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+}
+
+/// Each rule as its id followed by what it derived in brackets, like `0(4(a) 5(b))`
+impl Display for Derivation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Derivation::Char(c) => write!(f, "{c}"),
+            Derivation::Rule { id, children, .. } => {
+                write!(f, "{id}(")?;
+                for (i, child) in children.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{child}")?;
                 }
+                write!(f, ")")
             }
-            ChildType::Rules(options) => options
-                .iter()
-                .flat_map(|rule_set| {
-                    rule_set.iter().fold(vec![index], |curr, rule| {
-                        curr.iter()
-                            .flat_map(|i| rules[rule].inner_matches(message, *i, rules))
-                            .collect()
-                    })
-                })
-                .collect(),
         }
     }
 }
@@ -113,7 +403,99 @@ bababa
 abbbab
 aaabbb
 aaaabbb"#;
-        let results = solve(input);
-        assert_eq!(results.0.to_string(), "2");
+        let (rules, messages) = input.split_once("\n\n").unwrap();
+        let grammar = rules.parse::<Grammar>().unwrap();
+        let matching = messages.lines().filter(|m| grammar.matches(0, m)).count();
+        assert_eq!(matching, 2);
+        // Part 2 can't be done without the rules the looping ones refer to
+        let error = solve(input).err().unwrap();
+        assert!(error.to_string().contains("need rule 42"), "{error}");
+    }
+
+    #[test]
+    fn test_looping() {
+        let input = r#"42: 9 14 | 10 1
+9: 14 27 | 1 26
+10: 23 14 | 28 1
+1: "a"
+11: 42 31
+5: 1 14 | 15 1
+19: 14 1 | 14 14
+12: 24 14 | 19 1
+16: 15 1 | 14 14
+31: 14 17 | 1 13
+6: 14 14 | 1 14
+2: 1 24 | 14 4
+0: 8 11
+13: 14 3 | 1 12
+15: 1 | 14
+17: 14 2 | 1 7
+23: 25 1 | 22 14
+28: 16 1
+4: 1 1
+20: 14 14 | 1 15
+3: 5 14 | 16 1
+27: 1 6 | 14 18
+14: "b"
+21: 14 1 | 1 14
+25: 1 1 | 1 14
+22: 14 14
+8: 42
+26: 14 22 | 1 20
+18: 15 15
+7: 14 5 | 1 21
+24: 14 1
+
+abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa
+bbabbbbaabaabba
+babbbbaabbbbbabbbbbbaabaaabaaa
+aaabbbbbbaaaabaababaabababbabaaabbababababaaa
+bbbbbbbaaaabbbbaaabbabaaa
+bbbababbbbaaaaaaaabbababaaababaabab
+ababaaaaaabaaab
+ababaaaaabbbaba
+baabbaaaabbaaaababbaababb
+abbbbabbbbaaaababbbbbbaaaababb
+aaaaabbaabaaaaababaa
+aaaabbaaaabbaaa
+aaaabbaabbaaaaaaabbbabbbaaabbaabaaa
+babaaabbbaaabaababbaabababaaab
+aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#;
+        let results = solve(input).unwrap();
+        assert_eq!(results.0.to_string(), "3");
+        assert_eq!(results.1.to_string(), "12");
+    }
+
+    #[test]
+    fn test_derivation() {
+        let grammar = "0: 1 2 | 2 1 1\n1: \"a\"\n2: 1 2 | 3\n3: \"b\""
+            .parse::<Grammar>()
+            .unwrap();
+        assert_eq!(
+            grammar.derivation(0, "aab").unwrap().to_string(),
+            "0(1(a) 2(1(a) 2(3(b))))"
+        );
+        assert_eq!(
+            grammar.derivation(0, "baa").unwrap().to_string(),
+            "0(2(3(b)) 1(a) 1(a))"
+        );
+        assert_eq!(grammar.derivation(0, "ba"), None);
+        assert_eq!(grammar.language(0), Err(GrammarError::Recursive(2)));
+    }
+
+    #[test]
+    fn test_language() {
+        let grammar = "0: 1 2\n1: 3 | 4\n2: 3 4 | 4 3 | 1\n3: \"a\"\n4: \"b\""
+            .parse::<Grammar>()
+            .unwrap();
+        let language = grammar.language(0).unwrap();
+        assert_eq!(
+            language,
+            ["aa", "aab", "aba", "ab", "bab", "bba", "ba", "bb"]
+                .map(String::from)
+                .into()
+        );
+        assert!(language.iter().all(|message| grammar.matches(0, message)));
+        assert!(!grammar.matches(0, "a"));
     }
 }
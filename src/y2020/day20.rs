@@ -1,342 +1,327 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::common::Symmetry;
+
+const SEA_MONSTER: &str = "                  #\n#    ##    ##    ###\n #  #  #  #  #  #";
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let tiles = input
         .split("\n\n")
-        .filter(|s| !s.is_empty())
-        .map(|s| {
-            let mut lines = s.lines();
-            let id = lines.next().unwrap()[5..9].parse::<u64>().unwrap();
-            let data = lines
-                .map(|l| {
-                    l.chars()
-                        .map(|c| match c {
-                            '#' => true,
-                            '.' => false,
-                            _ => unreachable!(),
-                        })
-                        .collect()
-                })
-                .collect();
-            Tile::new(id, data)
-        })
-        .collect::<Vec<_>>();
-
-    assert_eq!(tiles[0].data().len(), tiles[0].data()[0].len());
-    let tile_size = tiles[0].data().len();
-
-    // Connect tiles
-    let mut unused = tiles;
-    let mut map = HashMap::new();
-    map.insert((0, 0), unused.pop().unwrap());
-    let mut queue = vec![(0, 0)];
-    while let Some(tile_pos) = queue.pop() {
-        // Could do this without cloning
-        let tile = map.get(&tile_pos).unwrap().clone();
-        const DIRS: [(Side, (i32, i32)); 4] = [
-            (Side::Top, (-1, 0)),
-            (Side::Right, (0, 1)),
-            (Side::Bottom, (1, 0)),
-            (Side::Left, (0, -1)),
-        ];
-        for (dir, offset) in DIRS.iter() {
-            let new_pos = (tile_pos.0 + offset.0, tile_pos.1 + offset.1);
-            if map.contains_key(&new_pos) {
-                continue;
-            }
-            let side = tile.side(*dir);
-            // Find matching tile
-            let match_result = unused.iter().enumerate().find_map(|(i, other)| {
-                Side::all().find_map(|s| {
-                    if sides_match(side, other.side(s)) {
-                        Some((i, s))
-                    } else {
-                        None
-                    }
-                })
-            });
-            if match_result.is_none() {
-                continue;
-            }
-            let (matching_index, matching_side) = match_result.unwrap();
-            let mut matching_tile = unused.swap_remove(matching_index);
-
-            // Rotate matching tile
-            matching_tile.orientation = match (*dir, matching_side) {
-                (Side::Top, other) | (Side::TopFlipped, other) => {
-                    other.as_orientation().rot90().rot90()
-                }
-                (Side::Right, other) | (Side::RightFlipped, other) => {
-                    other.as_orientation().rot90()
-                }
-                (Side::Bottom, other) | (Side::BottomFlipped, other) => other.as_orientation(),
-                (Side::Left, other) | (Side::LeftFlipped, other) => {
-                    other.as_orientation().rot90().rot90().rot90()
-                }
-            };
-            // Insert into map
-            map.insert(new_pos, matching_tile);
-            // Add new pos to check queue
-            queue.push(new_pos);
-        }
-    }
-    assert!(unused.is_empty());
-    let min_x = *map.keys().map(|(_, x)| x).min().unwrap();
-    let max_x = *map.keys().map(|(_, x)| x).max().unwrap();
-    let min_y = *map.keys().map(|(y, _)| y).min().unwrap();
-    let max_y = *map.keys().map(|(y, _)| y).max().unwrap();
-
-    let part1 = [
-        (min_y, min_x),
-        (min_y, max_x),
-        (max_y, min_x),
-        (max_y, max_x),
-    ]
-    .iter()
-    .map(|pos| map.get(pos).unwrap().id)
-    .product::<u64>();
-
-    let mut search_data = (min_y..=max_y)
-        .flat_map(|y| {
-            let tile_row_data = (min_x..=max_x)
-                .map(|x| map.get(&(y, x)).unwrap().data())
-                .collect::<Vec<_>>();
-            (1..(tile_size - 1)).map(move |i| {
-                tile_row_data
-                    .iter()
-                    .flat_map(|tile| {
-                        tile[i].iter().skip(1).take(tile_size - 2).map(|b| {
-                            if *b {
-                                SearchType::Full
-                            } else {
-                                SearchType::Empty
-                            }
-                        })
-                    })
-                    .collect::<Vec<_>>()
-            })
-        })
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse::<Tile>().unwrap())
         .collect::<Vec<_>>();
 
-    let monster = "                  # 
-#    ##    ##    ###
- #  #  #  #  #  #   "
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| match c {
-                    '#' => true,
-                    ' ' => false,
-                    _ => unreachable!(),
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    let to_check = &[
-        rot90(&monster),
-        rot90(&rot90(&monster)),
-        rot90(&rot90(&rot90(&monster))),
-        flip(&monster),
-        rot90(&flip(&monster)),
-        rot90(&rot90(&flip(&monster))),
-        rot90(&rot90(&rot90(&flip(&monster)))),
-        monster,
-    ];
-    for monster_data in to_check.iter() {
-        for start_y in 0..=(search_data.len() - monster_data.len()) {
-            for start_x in 0..=(search_data[0].len() - monster_data[0].len()) {
-                let is_match = monster_data
-                    .iter()
-                    .enumerate()
-                    .all(|(monster_y, monster_row)| {
-                        monster_row.iter().enumerate().all(|(monster_x, b)| {
-                            let y = start_y + monster_y;
-                            let x = start_x + monster_x;
-                            !b || search_data[y][x] != SearchType::Empty
-                        })
-                    });
-                if is_match {
-                    monster_data
-                        .iter()
-                        .enumerate()
-                        .for_each(|(monster_y, monster_row)| {
-                            monster_row.iter().enumerate().for_each(|(monster_x, b)| {
-                                if *b {
-                                    let y = start_y + monster_y;
-                                    let x = start_x + monster_x;
-                                    search_data[y][x] = SearchType::SeaMonster;
-                                }
-                            });
-                        });
-                }
-            }
-        }
-    }
+    let image = Image::assemble(&tiles).expect("Tiles don't fit together");
+    let part1 = image.corner_ids().iter().product::<u64>();
 
-    let part2 = search_data
-        .iter()
-        .map(|row| row.iter().filter(|v| **v == SearchType::Full).count())
-        .sum::<usize>();
+    let sea_monster = SEA_MONSTER.parse::<SearchPattern>().unwrap();
+    let part2 = image.search(&sea_monster).roughness;
 
     (part1, part2)
 }
 
-fn sides_match(side_a: &[bool], side_b: &[bool]) -> bool {
-    assert_eq!(side_a.len(), side_b.len());
-    side_a.iter().zip(side_b.iter().rev()).all(|(a, b)| a == b)
-}
-
 #[derive(Debug, Clone)]
 struct Tile {
     id: u64,
     data: Vec<Vec<bool>>,
-    sides: Vec<Vec<bool>>,
-    orientation: Orientation,
 }
 
-impl Tile {
-    fn new(id: u64, data: Vec<Vec<bool>>) -> Self {
-        // All sides are read clockwise
-        let sides = vec![
-            data[0].clone(),
-            data.iter().map(|row| *row.last().unwrap()).collect(),
-            data.last().unwrap().iter().rev().cloned().collect(),
-            data.iter().map(|row| row[0]).rev().collect(),
-            // Flipped
-            data[0].iter().rev().cloned().collect(),
-            data.iter().map(|row| row[0]).collect(),
-            data.last().unwrap().clone(),
-            data.iter().map(|row| *row.last().unwrap()).rev().collect(),
-        ];
-        Self {
-            id,
-            data,
-            sides,
-            orientation: Orientation::Normal,
-        }
-    }
-
-    fn side(&self, side: Side) -> &Vec<bool> {
-        let is_flipped = self.orientation.is_flipped() ^ side.is_flipped();
-        let offset = (side as usize + self.orientation as usize) % Orientation::Flipped as usize;
-        let index = if !is_flipped {
-            offset
-        } else {
-            Orientation::Flipped as usize + offset
-        };
-        &self.sides[index]
-    }
+/// A tile turned to one of its 8 orientations, with its edges read left to right or top to
+/// bottom as bitmasks
+#[derive(Debug)]
+struct Oriented {
+    symmetry: Symmetry,
+    data: Vec<Vec<bool>>,
+    top: u64,
+    right: u64,
+    bottom: u64,
+    left: u64,
+}
 
-    fn data(&self) -> Vec<Vec<bool>> {
-        match self.orientation {
-            Orientation::Normal => self.data.clone(),
-            Orientation::Rot90 => rot90(&self.data),
-            Orientation::Rot180 => rot90(&rot90(&self.data)),
-            Orientation::Rot270 => rot90(&rot90(&rot90(&self.data))),
-            Orientation::Flipped => flip(&self.data),
-            Orientation::FlipRot90 => rot90(&flip(&self.data)),
-            Orientation::FlipRot180 => rot90(&rot90(&flip(&self.data))),
-            Orientation::FlipRot270 => rot90(&rot90(&rot90(&flip(&self.data)))),
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Placement {
+    id: u64,
+    /// Takes the tile as given in the input to how it sits in the image
+    symmetry: Symmetry,
 }
 
-fn rot90(data: &[Vec<bool>]) -> Vec<Vec<bool>> {
-    let width = data[0].len();
-    let height = data.len();
-    (0..width)
-        .map(|i| (0..height).map(|j| data[j][width - 1 - i]).collect())
-        .collect()
+/// The assembled tiles, with the borders of each tile removed from the pixels
+#[derive(Debug)]
+struct Image {
+    /// Tiles per side
+    size: usize,
+    /// In reading order
+    placements: Vec<Placement>,
+    pixels: Vec<Vec<bool>>,
 }
 
-fn flip(data: &[Vec<bool>]) -> Vec<Vec<bool>> {
-    data.iter()
-        .map(|row| row.iter().rev().cloned().collect::<Vec<_>>())
-        .collect()
+/// An ASCII pattern to look for, where `#` has to be on and anything else doesn't matter
+#[derive(Debug)]
+struct SearchPattern {
+    cells: Vec<Vec<bool>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
-enum SearchType {
-    Empty,
-    Full,
-    SeaMonster,
+struct SearchResult {
+    /// The orientation of the pattern with the most matches
+    symmetry: Symmetry,
+    /// Top left corners of the matches
+    positions: Vec<(usize, usize)>,
+    /// Pixels on that aren't part of any match
+    roughness: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Side {
-    Top,
-    Right,
-    Bottom,
-    Left,
-    TopFlipped,
-    RightFlipped,
-    BottomFlipped,
-    LeftFlipped,
+impl Tile {
+    fn orientations(&self) -> Vec<Oriented> {
+        let to_mask =
+            |edge: &mut dyn Iterator<Item = bool>| edge.fold(0, |acc, on| acc << 1 | u64::from(on));
+        Symmetry::all()
+            .map(|symmetry| {
+                let data = symmetry.apply(&self.data);
+                Oriented {
+                    symmetry,
+                    top: to_mask(&mut data[0].iter().copied()),
+                    right: to_mask(&mut data.iter().map(|row| *row.last().unwrap())),
+                    bottom: to_mask(&mut data.last().unwrap().iter().copied()),
+                    left: to_mask(&mut data.iter().map(|row| row[0])),
+                    data,
+                }
+            })
+            .collect()
+    }
 }
 
-impl Side {
-    fn all() -> impl Iterator<Item = Side> {
-        [
-            Side::Top,
-            Side::Right,
-            Side::Bottom,
-            Side::Left,
-            Side::TopFlipped,
-            Side::RightFlipped,
-            Side::BottomFlipped,
-            Side::LeftFlipped,
-        ]
-        .iter()
-        .copied()
-    }
+impl Image {
+    /// Fits the tiles into a square, backtracking whenever a tile has more than one possible
+    /// neighbour and the first choice leads to a dead end
+    fn assemble(tiles: &[Tile]) -> Option<Image> {
+        let size = (tiles.len() as f64).sqrt() as usize;
+        if size * size != tiles.len() || size == 0 {
+            return None;
+        }
+        let orientations = tiles.iter().map(Tile::orientations).collect::<Vec<_>>();
+        let tile_size = tiles[0].data.len();
+        if tiles.iter().any(|tile| tile.data.len() != tile_size) || tile_size > 64 {
+            return None;
+        }
+
+        let mut by_left = HashMap::<u64, Vec<(usize, usize)>>::new();
+        let mut by_top = HashMap::<u64, Vec<(usize, usize)>>::new();
+        let mut edge_counts = HashMap::<u64, usize>::new();
+        for (tile, oriented) in orientations.iter().enumerate() {
+            for (orientation, o) in oriented.iter().enumerate() {
+                by_left.entry(o.left).or_default().push((tile, orientation));
+                by_top.entry(o.top).or_default().push((tile, orientation));
+                *edge_counts.entry(o.top).or_default() += 1;
+            }
+        }
+
+        // Corners most likely have two edges no other tile shares, so try them first
+        let mut first = (0..tiles.len())
+            .flat_map(|tile| (0..8).map(move |orientation| (tile, orientation)))
+            .collect::<Vec<_>>();
+        let unmatched = |(tile, orientation): (usize, usize)| {
+            let o = &orientations[tile][orientation];
+            [o.top, o.left]
+                .iter()
+                .filter(|edge| edge_counts[edge] <= 2)
+                .count()
+        };
+        first.sort_by_key(|candidate| std::cmp::Reverse(unmatched(*candidate)));
 
-    fn as_orientation(&self) -> Orientation {
-        match self {
-            Self::Top => Orientation::Normal,
-            Self::Right => Orientation::Rot90,
-            Self::Bottom => Orientation::Rot180,
-            Self::Left => Orientation::Rot270,
-            Self::TopFlipped => Orientation::Flipped,
-            Self::RightFlipped => Orientation::FlipRot90,
-            Self::BottomFlipped => Orientation::FlipRot180,
-            Self::LeftFlipped => Orientation::FlipRot270,
+        let assembler = Assembler {
+            size,
+            orientations: &orientations,
+            by_left,
+            by_top,
+            first,
+        };
+        let mut placed = Vec::new();
+        let mut used = vec![false; tiles.len()];
+        if !assembler.place(&mut placed, &mut used) {
+            return None;
         }
+
+        let placements = placed
+            .iter()
+            .map(|(tile, orientation)| Placement {
+                id: tiles[*tile].id,
+                symmetry: orientations[*tile][*orientation].symmetry,
+            })
+            .collect();
+        let inner = tile_size - 2;
+        let pixels = (0..size * inner)
+            .map(|y| {
+                (0..size * inner)
+                    .map(|x| {
+                        let (tile, orientation) = placed[(y / inner) * size + x / inner];
+                        orientations[tile][orientation].data[y % inner + 1][x % inner + 1]
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Some(Image {
+            size,
+            placements,
+            pixels,
+        })
     }
 
-    fn is_flipped(&self) -> bool {
-        *self as usize >= Self::TopFlipped as usize
+    fn placement(&self, x: usize, y: usize) -> Placement {
+        self.placements[y * self.size + x]
+    }
+
+    fn corner_ids(&self) -> [u64; 4] {
+        let end = self.size - 1;
+        [(0, 0), (end, 0), (0, end), (end, end)].map(|(x, y)| self.placement(x, y).id)
+    }
+
+    /// Looks for `pattern` in all 8 orientations, counting roughness for whichever orientation
+    /// matches most. Overlapping matches only cover each pixel once
+    fn search(&self, pattern: &SearchPattern) -> SearchResult {
+        let on = self.pixels.iter().flatten().filter(|on| **on).count();
+        Symmetry::all()
+            .map(|symmetry| {
+                let cells = symmetry.apply(&pattern.cells);
+                let offsets = cells
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(y, row)| {
+                        row.iter()
+                            .enumerate()
+                            .filter(|(_, cell)| **cell)
+                            .map(move |(x, _)| (x, y))
+                    })
+                    .collect::<Vec<_>>();
+                let (width, height) = (cells.first().map_or(0, Vec::len), cells.len());
+
+                let mut positions = Vec::new();
+                let mut covered = HashSet::new();
+                for y in 0..(self.pixels.len() + 1).saturating_sub(height) {
+                    for x in 0..(self.pixels[y].len() + 1).saturating_sub(width) {
+                        if offsets.iter().all(|(dx, dy)| self.pixels[y + dy][x + dx]) {
+                            positions.push((x, y));
+                            covered.extend(offsets.iter().map(|(dx, dy)| (x + dx, y + dy)));
+                        }
+                    }
+                }
+                SearchResult {
+                    symmetry,
+                    positions,
+                    roughness: on - covered.len(),
+                }
+            })
+            .max_by_key(|result| (result.positions.len(), std::cmp::Reverse(result.roughness)))
+            .unwrap()
     }
 }
 
-/// Rotated anti-clockwise
-#[derive(Debug, Clone, Copy)]
-enum Orientation {
-    Normal,
-    Rot90,
-    Rot180,
-    Rot270,
-    Flipped,
-    FlipRot90,
-    FlipRot180,
-    FlipRot270,
+struct Assembler<'a> {
+    size: usize,
+    orientations: &'a [Vec<Oriented>],
+    by_left: HashMap<u64, Vec<(usize, usize)>>,
+    by_top: HashMap<u64, Vec<(usize, usize)>>,
+    /// Candidates for the top left corner
+    first: Vec<(usize, usize)>,
 }
 
-impl Orientation {
-    fn rot90(&self) -> Orientation {
-        match self {
-            Orientation::Normal => Orientation::Rot90,
-            Orientation::Rot90 => Orientation::Rot180,
-            Orientation::Rot180 => Orientation::Rot270,
-            Orientation::Rot270 => Orientation::Normal,
-            Orientation::Flipped => Orientation::FlipRot90,
-            Orientation::FlipRot90 => Orientation::FlipRot180,
-            Orientation::FlipRot180 => Orientation::FlipRot270,
-            Orientation::FlipRot270 => Orientation::Flipped,
+impl Assembler<'_> {
+    /// Fills the next position in reading order, returning whether the rest could be filled
+    fn place(&self, placed: &mut Vec<(usize, usize)>, used: &mut [bool]) -> bool {
+        let position = placed.len();
+        if position == self.size * self.size {
+            return true;
         }
+        let oriented = |(tile, orientation): (usize, usize)| &self.orientations[tile][orientation];
+        let first_column = position.is_multiple_of(self.size);
+        let left = (!first_column).then(|| oriented(placed[position - 1]).right);
+        let above = (position >= self.size).then(|| oriented(placed[position - self.size]).bottom);
+
+        let candidates = match (left, above) {
+            (Some(left), _) => self.by_left.get(&left),
+            (None, Some(above)) => self.by_top.get(&above),
+            (None, None) => Some(&self.first),
+        };
+        for candidate in candidates.into_iter().flatten() {
+            if used[candidate.0] || above.is_some_and(|above| oriented(*candidate).top != above) {
+                continue;
+            }
+            used[candidate.0] = true;
+            placed.push(*candidate);
+            if self.place(placed, used) {
+                return true;
+            }
+            placed.pop();
+            used[candidate.0] = false;
+        }
+        false
     }
+}
+
+impl FromStr for Tile {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let id = lines
+            .next()
+            .and_then(|line| line.strip_prefix("Tile "))
+            .and_then(|line| line.strip_suffix(':'))
+            .ok_or(())?
+            .parse()
+            .map_err(|_| ())?;
+        let data = lines
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(()),
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<_>>, _>>()?;
+        if data.is_empty() || data.iter().any(|row| row.len() != data.len()) {
+            return Err(());
+        }
+        Ok(Tile { id, data })
+    }
+}
+
+impl FromStr for SearchPattern {
+    type Err = ();
+
+    /// Rows can be ragged, they're padded to the widest
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let width = s.lines().map(|line| line.chars().count()).max().ok_or(())?;
+        let cells = s
+            .lines()
+            .map(|line| {
+                let mut row = line.chars().map(|c| c == '#').collect::<Vec<_>>();
+                row.resize(width, false);
+                row
+            })
+            .collect();
+        Ok(SearchPattern { cells })
+    }
+}
 
-    fn is_flipped(&self) -> bool {
-        *self as usize >= Orientation::Flipped as usize
+impl Display for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (y, row) in self.pixels.iter().enumerate() {
+            if y != 0 {
+                writeln!(f)?;
+            }
+            for on in row {
+                write!(f, "{}", if *on { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -344,38 +329,193 @@ impl Orientation {
 mod tests {
     use super::*;
 
+    /// Not very random, but enough to give tiles awkward edges
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Cuts `pixels` into `size` by `size` tiles of `tile_size`, where neighbouring tiles share
+    /// their edge pixels, then shuffles and turns them
+    fn cut(pixels: &[Vec<bool>], size: usize, tile_size: usize, seed: u64) -> Vec<Tile> {
+        let mut state = seed;
+        let symmetries = Symmetry::all().collect::<Vec<_>>();
+        let mut tiles = Vec::new();
+        for tile_y in 0..size {
+            for tile_x in 0..size {
+                let (x0, y0) = (tile_x * (tile_size - 1), tile_y * (tile_size - 1));
+                let data = (0..tile_size)
+                    .map(|y| (0..tile_size).map(|x| pixels[y0 + y][x0 + x]).collect())
+                    .collect::<Vec<_>>();
+                let symmetry = symmetries[xorshift(&mut state) as usize % 8];
+                tiles.push(Tile {
+                    id: (tile_y * size + tile_x) as u64 + 1,
+                    data: symmetry.apply(&data),
+                });
+            }
+        }
+        for i in (1..tiles.len()).rev() {
+            tiles.swap(i, xorshift(&mut state) as usize % (i + 1));
+        }
+        tiles
+    }
+
+    fn is_valid(image: &Image, tiles: &[Tile]) -> bool {
+        let ids = image
+            .placements
+            .iter()
+            .map(|p| p.id)
+            .collect::<HashSet<_>>();
+        let data = |x, y| {
+            let placement = image.placement(x, y);
+            let tile = tiles.iter().find(|tile| tile.id == placement.id).unwrap();
+            placement.symmetry.apply(&tile.data)
+        };
+        let last = tiles[0].data.len() - 1;
+        ids.len() == tiles.len()
+            && (0..image.size).all(|y| {
+                (0..image.size).all(|x| {
+                    let tile = data(x, y);
+                    let right_ok = x + 1 == image.size
+                        || (0..=last).all(|i| tile[i][last] == data(x + 1, y)[i][0]);
+                    let below_ok = y + 1 == image.size || tile[last] == data(x, y + 1)[0];
+                    right_ok && below_ok
+                })
+            })
+    }
+
     #[test]
-    fn test_sides() {
-        let mut tile = Tile::new(
-            0,
-            vec![
-                vec![true, false, true, true],
-                vec![false, false, false, false],
-                vec![false, false, false, true],
-                vec![true, true, true, false],
-            ],
-        );
-        assert_eq!(tile.side(Side::Top), &vec![true, false, true, true]);
-        assert_eq!(tile.side(Side::Right), &vec![true, false, true, false]);
-        assert_eq!(tile.side(Side::Bottom), &vec![false, true, true, true]);
-        assert_eq!(tile.side(Side::Left), &vec![true, false, false, true]);
-        assert_eq!(tile.side(Side::TopFlipped), &vec![true, true, false, true]);
-        assert_eq!(
-            tile.side(Side::RightFlipped),
-            &vec![true, false, false, true]
-        );
-        assert_eq!(
-            tile.side(Side::BottomFlipped),
-            &vec![true, true, true, false]
-        );
+    fn test_known() {
+        let input = "Tile 2311:
+..##.#..#.
+##..#.....
+#...##..#.
+####.#...#
+##.##.###.
+##...#.###
+.#.#.#..##
+..#....#..
+###...#.#.
+..###..###
+
+Tile 1951:
+#.##...##.
+#.####...#
+.....#..##
+#...######
+.##.#....#
+.###.#####
+###.##.##.
+.###....#.
+..#.#..#.#
+#...##.#..
+
+Tile 1171:
+####...##.
+#..##.#..#
+##.#..#.#.
+.###.####.
+..###.####
+.##....##.
+.#...####.
+#.##.####.
+####..#...
+.....##...
+
+Tile 1427:
+###.##.#..
+.#..#.##..
+.#.##.#..#
+#.#.#.##.#
+....#...##
+...##..##.
+...#.#####
+.#.####.#.
+..#..###.#
+..##.#..#.
+
+Tile 1489:
+##.#.#....
+..##...#..
+.##..##...
+..#...#...
+#####...#.
+#..#.#.#.#
+...#.#.#..
+##.#...##.
+..##.##.##
+###.##.#..
+
+Tile 2473:
+#....####.
+#..#.##...
+#.##..#...
+######.#.#
+.#...#.#.#
+.#########
+.###.#..#.
+########.#
+##...##.#.
+..###.#.#.
+
+Tile 2971:
+..#.#....#
+#...###...
+#.#.###...
+##.##..#..
+.#####..##
+.#..####.#
+#..#.#..#.
+..####.###
+..#.#.###.
+...#.#.#.#
+
+Tile 2729:
+...#.#.#.#
+####.#....
+..#.#.....
+....#..#.#
+.##..##.#.
+.#.####...
+####.#.#..
+##.####...
+##..#.##..
+#.##...##.
+
+Tile 3079:
+#.#.#####.
+.#..######
+..#.......
+######....
+####.#..#.
+.#...#.##.
+#.#####.##
+..#.###...
+..#.......
+..#.###...";
+
+        let (part1, part2) = solve(input);
+        assert_eq!(part1.to_string(), "20899048083289");
+        assert_eq!(part2.to_string(), "273");
+    }
+
+    #[test]
+    fn test_orientations() {
+        let tile = "Tile 7:\n#.##\n....\n...#\n###.".parse::<Tile>().unwrap();
+        let orientations = tile.orientations();
+        let identity = &orientations[0];
+        assert_eq!(identity.symmetry, Symmetry::all().next().unwrap());
         assert_eq!(
-            tile.side(Side::LeftFlipped),
-            &vec![false, true, false, true]
+            (identity.top, identity.right, identity.bottom, identity.left),
+            (0b1011, 0b1010, 0b1110, 0b1001)
         );
 
-        tile.orientation = Orientation::Rot270;
+        // A quarter turn clockwise
+        let turned = &orientations[1];
         assert_eq!(
-            tile.data(),
+            turned.data,
             vec![
                 vec![true, false, false, true],
                 vec![true, false, false, false],
@@ -383,39 +523,67 @@ mod tests {
                 vec![false, true, false, true],
             ]
         );
-        assert_eq!(tile.side(Side::Top), &vec![true, false, false, true]);
-        assert_eq!(tile.side(Side::Right), &vec![true, false, true, true]);
-        assert_eq!(tile.side(Side::Bottom), &vec![true, false, true, false]);
-        assert_eq!(tile.side(Side::Left), &vec![false, true, true, true]);
-
-        tile.orientation = Orientation::FlipRot90;
         assert_eq!(
-            tile.data(),
-            vec![
-                vec![true, false, false, true],
-                vec![false, false, false, true],
-                vec![true, false, false, true],
-                vec![true, false, true, false],
-            ]
+            (turned.top, turned.right, turned.bottom, turned.left),
+            (0b1001, 0b1011, 0b0101, 0b1110)
         );
-        assert_eq!(tile.side(Side::Top), &vec![true, false, false, true]);
-        assert_eq!(tile.side(Side::Right), &vec![true, true, true, false]);
-        assert_eq!(tile.side(Side::Bottom), &vec![false, true, false, true]);
-        assert_eq!(tile.side(Side::Left), &vec![true, true, false, true]);
+    }
+
+    #[test]
+    fn test_assemble() {
+        let mut state = 0x2020_2020;
+        for (size, tile_size) in [(3, 10), (4, 4), (5, 3)] {
+            let side = size * (tile_size - 1) + 1;
+            let pixels = (0..side)
+                .map(|_| {
+                    (0..side)
+                        .map(|_| xorshift(&mut state).is_multiple_of(3))
+                        .collect()
+                })
+                .collect::<Vec<Vec<_>>>();
+            let tiles = cut(&pixels, size, tile_size, xorshift(&mut state));
+            let image = Image::assemble(&tiles).unwrap();
+            assert!(is_valid(&image, &tiles));
+            assert_eq!(image.pixels.len(), size * (tile_size - 2));
+        }
+
+        // Tiles with only 3 pixels a side have edges so ambiguous that the wrong neighbour
+        // often gets picked first
+        let tiles = (0..9)
+            .map(|id| Tile {
+                id,
+                data: vec![vec![false; 3]; 3],
+            })
+            .collect::<Vec<_>>();
+        assert!(is_valid(&Image::assemble(&tiles).unwrap(), &tiles));
+        // A square number of tiles that every search has to give up on, as the last one's edges
+        // fit nothing
+        let mut unmatched = tiles[..4].to_vec();
+        unmatched[3].data = vec![vec![true; 3]; 3];
+        assert!(Image::assemble(&unmatched).is_none());
+    }
 
-        tile.orientation = Orientation::FlipRot270;
+    #[test]
+    fn test_search() {
+        let pattern = "#.#\n.#".parse::<SearchPattern>().unwrap();
+        assert_eq!(pattern.cells[1], [false, true, false]);
+
+        let image = Image {
+            size: 1,
+            placements: vec![],
+            pixels: "#....\n.#.#.\n#.#.#"
+                .lines()
+                .map(|line| line.chars().map(|c| c == '#').collect())
+                .collect(),
+        };
+        // Upside down, twice, overlapping at (2, 2)
+        let result = image.search(&pattern);
         assert_eq!(
-            tile.data(),
-            vec![
-                vec![false, true, false, true],
-                vec![true, false, false, true],
-                vec![true, false, false, false],
-                vec![true, false, false, true],
-            ]
+            result.symmetry.apply(&pattern.cells)[0],
+            [false, true, false]
         );
-        assert_eq!(tile.side(Side::Top), &vec![false, true, false, true]);
-        assert_eq!(tile.side(Side::Right), &vec![true, true, false, true]);
-        assert_eq!(tile.side(Side::Bottom), &vec![true, false, false, true]);
-        assert_eq!(tile.side(Side::Left), &vec![true, true, true, false]);
+        assert_eq!(result.positions, [(0, 1), (2, 1)]);
+        assert_eq!(result.roughness, 6 - 5);
+        assert_eq!(image.to_string(), "#....\n.#.#.\n#.#.#");
     }
 }
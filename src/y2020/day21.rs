@@ -1,8 +1,6 @@
-use std::{
-    collections::{HashMap, HashSet},
-    iter::FromIterator,
-    str::FromStr,
-};
+use std::str::FromStr;
+
+use crate::common::Candidates;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let foods = input
@@ -10,77 +8,39 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         .map(|l| l.parse::<Food>().unwrap())
         .collect::<Vec<_>>();
 
-    let all_ingredients = foods
+    let mut ingredients = foods
         .iter()
         .flat_map(|f| &f.ingredients)
-        .collect::<HashSet<_>>();
-    let all_allergens = foods
-        .iter()
-        .flat_map(|f| &f.allergens)
-        .collect::<HashSet<_>>();
-
-    let possible_matches = all_allergens
-        .iter()
-        .map(|allergen| {
-            let mut common = foods.iter().filter(|f| f.allergens.contains(allergen));
-            let first = common.next().unwrap();
-            let common = common.fold(
-                HashSet::<&String>::from_iter(&first.ingredients),
-                |acc, curr| {
-                    acc.intersection(&HashSet::from_iter(&curr.ingredients))
-                        .cloned()
-                        .collect()
-                },
-            );
-            (*allergen, common)
-        })
-        .collect::<HashMap<_, _>>();
+        .collect::<Vec<_>>();
+    ingredients.sort();
+    ingredients.dedup();
+    // Sorted so the dangerous list comes out in order
+    let mut allergens = foods.iter().flat_map(|f| &f.allergens).collect::<Vec<_>>();
+    allergens.sort();
+    allergens.dedup();
 
-    let all_possible = possible_matches
-        .values()
-        .flatten()
-        .cloned()
-        .collect::<HashSet<_>>();
-    let no_allergen_ingredients = all_ingredients
-        .difference(&all_possible)
-        .collect::<HashSet<_>>();
+    // An allergen can only be in ingredients of every food listing it
+    let candidates = Candidates::from_fn(allergens.len(), ingredients.len(), |a, i| {
+        foods
+            .iter()
+            .filter(|f| f.allergens.contains(allergens[a]))
+            .all(|f| f.ingredients.contains(ingredients[i]))
+    });
 
-    let part1 = no_allergen_ingredients
+    let part1 = foods
         .iter()
-        .map(|&&ingredient| {
-            foods
-                .iter()
-                .filter(|f| f.ingredients.contains(ingredient))
-                .count()
+        .flat_map(|f| &f.ingredients)
+        .filter(|ingredient| {
+            let i = ingredients.binary_search(ingredient).unwrap();
+            (0..allergens.len()).all(|a| !candidates.is_allowed(a, i))
         })
-        .sum::<usize>();
+        .count();
 
-    let mut known = HashMap::new();
-    let mut possible_matches = possible_matches;
-    while !possible_matches.is_empty() {
-        let allergen = *possible_matches
-            .iter()
-            .find(|(_, v)| v.len() == 1)
-            .unwrap()
-            .0;
-        let ingredient = possible_matches
-            .remove(allergen)
-            .unwrap()
-            .into_iter()
-            .next()
-            .unwrap();
-        known.insert(allergen, ingredient);
-        possible_matches.values_mut().for_each(|ingredients| {
-            ingredients.remove(ingredient);
-        });
-    }
-
-    let mut known_sorted = known.into_iter().collect::<Vec<_>>();
-    known_sorted.sort_by_key(|(allergen, _)| *allergen);
-
-    let part2 = known_sorted
+    let part2 = candidates
+        .solve_unique()
+        .unwrap()
         .into_iter()
-        .map(|(_, i)| i.clone())
+        .map(|i| ingredients[i].as_str())
         .collect::<Vec<_>>()
         .join(",");
 
@@ -116,3 +76,19 @@ impl FromStr for Food {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)
+trh fvjkl sbzzf mxmxvkd (contains dairy)
+sqjhc fvjkl (contains soy)
+sqjhc mxmxvkd sbzzf (contains fish)";
+        let (part1, part2) = solve(input);
+        assert_eq!(part1.to_string(), "5");
+        assert_eq!(part2.to_string(), "mxmxvkd,sqjhc,fvjkl");
+    }
+}
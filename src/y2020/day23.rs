@@ -1,74 +1,62 @@
-use std::{fmt::Write, iter};
+use std::fmt::Write;
+
+use crate::common::Ring;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let cup_values = input
+    // Labels start from 0 in the ring
+    let cups = input
         .lines()
         .next()
         .unwrap()
         .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
+        .map(|c| c.to_digit(10).unwrap() as usize - 1)
         .collect::<Vec<_>>();
-    let highest_val = *cup_values.iter().max().unwrap();
-    assert_eq!(highest_val, cup_values.len());
-    let first_cup = cup_values[0];
 
-    let mut cups = vec![0; cup_values.len() + 1];
-    for (a, b) in cup_values.iter().zip(cup_values.iter().skip(1)) {
-        cups[*a] = *b;
-    }
-    cups[*cup_values.last().unwrap()] = cup_values[0];
-    let result1 = cup_game(cups.clone(), 100, first_cup);
-    let mut part1_digits = Vec::new();
-    let mut index = 1;
-    for _ in 0..(highest_val - 1) {
-        index = result1[index];
-        part1_digits.push(index);
-    }
-    let part1 = part1_digits
-        .into_iter()
-        .fold(String::new(), |mut result, d| {
-            write!(result, "{d}").unwrap();
+    let mut ring = Ring::from_order(cups.iter().copied()).unwrap();
+    cup_game(&mut ring, 100);
+    let part1 = ring
+        .iter_from(0)
+        .skip(1)
+        .fold(String::new(), |mut result, cup| {
+            write!(result, "{}", cup + 1).unwrap();
             result
         });
 
-    let mut cups = cups
-        .into_iter()
-        .chain((highest_val + 2)..=1_000_000)
-        .chain(iter::once(cup_values[0]))
-        .collect::<Vec<_>>();
-    cups[*cup_values.last().unwrap()] = 10;
-    let result2 = cup_game(cups, 10_000_000, first_cup);
-    let part2 = result2[1] * result2[result2[1]];
+    let mut ring = Ring::from_order(cups.iter().copied().chain(cups.len()..1_000_000)).unwrap();
+    cup_game(&mut ring, 10_000_000);
+    let after = ring.next(0);
+    let part2 = (after + 1) * (ring.next(after) + 1);
 
     (part1, part2)
 }
 
-fn cup_game(initial_cups: Vec<usize>, rounds: usize, first_cup: usize) -> Vec<usize> {
-    let mut cups = initial_cups;
-    let highest = cups.len() - 1;
-    let mut index = first_cup;
+/// Plays from the ring's head, which stays on the current cup
+fn cup_game(ring: &mut Ring, rounds: usize) {
+    let highest = ring.len() - 1;
     for _ in 0..rounds {
-        let start = index;
-        let a = cups[start];
-        let b = cups[a];
-        let c = cups[b];
-        let end = cups[c];
-        let mut after = start - 1;
-        if after == 0 {
-            after = highest;
-        }
-        while after == a || after == b || after == c {
-            after -= 1;
-            if after == 0 {
-                after = highest;
+        let current = ring.head();
+        let picked = ring.remove_after(current, 3);
+        let mut destination = current;
+        loop {
+            destination = destination.checked_sub(1).unwrap_or(highest);
+            if !ring.run_labels(&picked).any(|cup| cup == destination) {
+                break;
             }
         }
-        let temp = cups[after];
-        cups[index] = end;
-        cups[after] = a;
-        cups[c] = temp;
+        ring.insert_after(destination, picked);
+        ring.rotate(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        index = cups[index];
+    #[test]
+    fn test_example() {
+        let mut ring = Ring::from_order([2, 7, 8, 0, 1, 4, 3, 5, 6]).unwrap();
+        cup_game(&mut ring, 10);
+        let order = ring.iter_from(0).map(|cup| cup + 1).collect::<Vec<_>>();
+        assert_eq!(order, [1, 9, 2, 6, 5, 8, 3, 7, 4]);
     }
-    cups
 }
@@ -1,6 +1,8 @@
 // Recommended reading https://www.redblobgames.com/grids/hexagons/
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+
+use crate::common::{Automaton, Neighbourhood, Rule};
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let routes = input
@@ -64,32 +66,14 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     }
     let part1 = flipped.len();
 
-    // If a tile is in state it is black
-    let mut state = flipped;
-    for _ in 0..100 {
-        let mut count = HashMap::new();
-        state.iter().for_each(|(q, r)| {
-            count.entry((*q, *r)).or_default();
-            const OFFSETS: [(i64, i64); 6] = [(1, -1), (1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1)];
-            for (offset_q, offset_r) in OFFSETS.iter() {
-                let q = q + offset_q;
-                let r = r + offset_r;
-                *count.entry((q, r)).or_insert(0) += 1;
-            }
-        });
-        state = count
-            .into_iter()
-            .filter_map(|((q, r), count)| {
-                let was_black = state.contains(&(q, r));
-                if (was_black && !(count == 0 || count > 2)) || (!was_black && count == 2) {
-                    Some((q, r))
-                } else {
-                    None
-                }
-            })
-            .collect();
-    }
-    let part2 = state.len();
+    // Black tiles are alive
+    let mut floor = Automaton::sparse(
+        flipped.into_iter().map(|(q, r)| [q, r]),
+        Neighbourhood::Hex,
+        Rule::life_like(&[2], &[1, 2]),
+    );
+    floor.run(100);
+    let part2 = floor.alive_count();
 
     (part1, part2)
 }
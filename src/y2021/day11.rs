@@ -1,5 +1,7 @@
 use std::collections::HashSet;
 
+use crate::common::Neighbourhood;
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let mut octopuses = Octopuses::from_str(input.trim());
 
@@ -59,6 +61,7 @@ impl Octopuses {
         }
 
         // Continue until all flashes handled
+        let offsets = Neighbourhood::Moore.offsets::<2>(false);
         let mut seen = HashSet::new();
         while let Some((x, y)) = queue.pop() {
             if !seen.insert((x, y)) {
@@ -67,17 +70,8 @@ impl Octopuses {
 
             self.flash_count += 1;
 
-            let adjacent = [
-                (x - 1, y),
-                (x + 1, y),
-                (x, y - 1),
-                (x, y + 1),
-                (x - 1, y - 1),
-                (x + 1, y - 1),
-                (x - 1, y + 1),
-                (x + 1, y + 1),
-            ];
-            for (adj_x, adj_y) in adjacent {
+            for [offset_x, offset_y] in offsets.iter() {
+                let (adj_x, adj_y) = (x + offset_x, y + offset_y);
                 if !self.in_bounds(adj_x, adj_y) {
                     continue;
                 }
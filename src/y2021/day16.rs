@@ -1,49 +1,13 @@
+use std::{fmt::Display, iter::Peekable, str::FromStr};
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let data = input
-        .trim()
-        .chars()
-        .map(|c| c.to_digit(16).unwrap() as u8)
-        .collect::<Vec<_>>();
-    let mut reader = PacketReader::from_bytes(&data);
-    let root_packet = reader.read_packet();
+    let root_packet = PacketReader::from_hex(input).read_packet();
 
     let mut total = 0_u64;
     let mut queue = vec![&root_packet];
     while let Some(curr) = queue.pop() {
-        match curr {
-            Packet::Literal { version, .. } => total += *version as u64,
-            Packet::Sum {
-                version,
-                sub_packets,
-            }
-            | Packet::Product {
-                version,
-                sub_packets,
-            }
-            | Packet::Minimum {
-                version,
-                sub_packets,
-            }
-            | Packet::Maximum {
-                version,
-                sub_packets,
-            }
-            | Packet::GreaterThan {
-                version,
-                sub_packets,
-            }
-            | Packet::LessThan {
-                version,
-                sub_packets,
-            }
-            | Packet::EqualTo {
-                version,
-                sub_packets,
-            } => {
-                total += *version as u64;
-                queue.extend(sub_packets);
-            }
-        }
+        total += curr.version() as u64;
+        queue.extend(curr.sub_packets());
     }
     let part1 = total;
 
@@ -60,11 +24,20 @@ struct PacketReader {
 }
 
 impl PacketReader {
+    fn from_hex(input: &str) -> Self {
+        let data = input
+            .trim()
+            .chars()
+            .map(|c| c.to_digit(16).unwrap() as u8)
+            .collect::<Vec<_>>();
+        PacketReader::from_bytes(&data)
+    }
+
     fn from_bytes(input: &[u8]) -> Self {
         let mut data = Vec::new();
         for i in (0..input.len()).step_by(2) {
             if i == input.len() - 1 {
-                data.push(input[i] & 0b1111);
+                data.push((input[i] & 0b1111) << 4);
             } else {
                 data.push((input[i] & 0b1111) << 4 | (input[i + 1] & 0b1111));
             }
@@ -99,48 +72,19 @@ impl PacketReader {
     fn read_packet(&mut self) -> Packet {
         let version = self.readn(3) as u8;
         let type_id = self.readn(3) as u8;
-        match type_id {
-            0 => Packet::Sum {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            1 => Packet::Product {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            2 => Packet::Minimum {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            3 => Packet::Maximum {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            4 => {
-                let mut value = 0;
-                loop {
-                    let next = self.readn(5);
-                    value <<= 4;
-                    value |= next & 0b1111;
-                    if next & 0b10000 == 0 {
-                        break;
-                    }
+        if type_id == 4 {
+            let mut value = 0;
+            loop {
+                let next = self.readn(5);
+                value <<= 4;
+                value |= next & 0b1111;
+                if next & 0b10000 == 0 {
+                    break;
                 }
-                Packet::Literal { version, value }
             }
-            5 => Packet::GreaterThan {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            6 => Packet::LessThan {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            7 => Packet::EqualTo {
-                version,
-                sub_packets: self.read_subpackets(),
-            },
-            _ => unreachable!(),
+            Packet::Literal { version, value }
+        } else {
+            Packet::operator(version, type_id, self.read_subpackets())
         }
     }
 
@@ -162,6 +106,7 @@ impl PacketReader {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Packet {
     Sum {
         version: u8,
@@ -198,6 +143,81 @@ enum Packet {
 }
 
 impl Packet {
+    const NAMES: [&'static str; 8] = ["sum", "product", "min", "max", "literal", "gt", "lt", "eq"];
+
+    fn operator(version: u8, type_id: u8, sub_packets: Vec<Packet>) -> Packet {
+        match type_id {
+            0 => Packet::Sum {
+                version,
+                sub_packets,
+            },
+            1 => Packet::Product {
+                version,
+                sub_packets,
+            },
+            2 => Packet::Minimum {
+                version,
+                sub_packets,
+            },
+            3 => Packet::Maximum {
+                version,
+                sub_packets,
+            },
+            5 => Packet::GreaterThan {
+                version,
+                sub_packets,
+            },
+            6 => Packet::LessThan {
+                version,
+                sub_packets,
+            },
+            7 => Packet::EqualTo {
+                version,
+                sub_packets,
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn version(&self) -> u8 {
+        match self {
+            Packet::Literal { version, .. }
+            | Packet::Sum { version, .. }
+            | Packet::Product { version, .. }
+            | Packet::Minimum { version, .. }
+            | Packet::Maximum { version, .. }
+            | Packet::GreaterThan { version, .. }
+            | Packet::LessThan { version, .. }
+            | Packet::EqualTo { version, .. } => *version,
+        }
+    }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            Packet::Sum { .. } => 0,
+            Packet::Product { .. } => 1,
+            Packet::Minimum { .. } => 2,
+            Packet::Maximum { .. } => 3,
+            Packet::Literal { .. } => 4,
+            Packet::GreaterThan { .. } => 5,
+            Packet::LessThan { .. } => 6,
+            Packet::EqualTo { .. } => 7,
+        }
+    }
+
+    fn sub_packets(&self) -> &[Packet] {
+        match self {
+            Packet::Literal { .. } => &[],
+            Packet::Sum { sub_packets, .. }
+            | Packet::Product { sub_packets, .. }
+            | Packet::Minimum { sub_packets, .. }
+            | Packet::Maximum { sub_packets, .. }
+            | Packet::GreaterThan { sub_packets, .. }
+            | Packet::LessThan { sub_packets, .. }
+            | Packet::EqualTo { sub_packets, .. } => sub_packets,
+        }
+    }
+
     fn value(&self) -> i64 {
         match self {
             Packet::Sum { sub_packets, .. } => sub_packets.iter().map(|p| p.value()).sum(),
@@ -221,3 +241,271 @@ impl Packet {
         }
     }
 }
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthType {
+    /// Sub-packets prefixed by their total length in bits
+    Bits,
+    /// Sub-packets prefixed by how many there are
+    Count,
+}
+
+/// Write packets to an array of bits
+#[allow(dead_code)]
+struct PacketWriter {
+    bits: Vec<bool>,
+    length_type: LengthType,
+}
+
+#[allow(dead_code)]
+impl PacketWriter {
+    fn new(length_type: LengthType) -> Self {
+        Self {
+            bits: Vec::new(),
+            length_type,
+        }
+    }
+
+    /// Encode a packet as hex, padding the final digit with zeros
+    fn encode(packet: &Packet, length_type: LengthType) -> String {
+        let mut writer = PacketWriter::new(length_type);
+        writer.write_packet(packet);
+        writer.into_hex()
+    }
+
+    /// Write the lowest `nbits` bits of `value`
+    fn writen(&mut self, nbits: usize, value: i64) {
+        assert!(
+            nbits == 64 || value >> nbits == 0,
+            "{value} doesn't fit in {nbits} bits"
+        );
+        self.bits
+            .extend((0..nbits).rev().map(|i| (value >> i) & 1 == 1));
+    }
+
+    fn write_packet(&mut self, packet: &Packet) {
+        self.writen(3, packet.version() as i64);
+        self.writen(3, packet.type_id() as i64);
+        match packet {
+            Packet::Literal { value, .. } => {
+                assert!(*value >= 0, "Literals can't be negative");
+                let groups = (64 - value.leading_zeros() as usize).div_ceil(4).max(1);
+                for i in (0..groups).rev() {
+                    let more = if i == 0 { 0 } else { 0b10000 };
+                    self.writen(5, more | (value >> (i * 4)) & 0b1111);
+                }
+            }
+            _ => self.write_subpackets(packet.sub_packets()),
+        }
+    }
+
+    fn write_subpackets(&mut self, sub_packets: &[Packet]) {
+        match self.length_type {
+            LengthType::Bits => {
+                let mut inner = PacketWriter::new(self.length_type);
+                sub_packets.iter().for_each(|p| inner.write_packet(p));
+                self.bits.push(false);
+                self.writen(15, inner.bits.len() as i64);
+                self.bits.extend(inner.bits);
+            }
+            LengthType::Count => {
+                self.bits.push(true);
+                self.writen(11, sub_packets.len() as i64);
+                sub_packets.iter().for_each(|p| self.write_packet(p));
+            }
+        }
+    }
+
+    fn into_hex(self) -> String {
+        self.bits
+            .chunks(4)
+            .map(|chunk| {
+                let bit = |i: usize| chunk.get(i).copied().unwrap_or(false) as u32;
+                let digit = (0..4).fold(0, |acc, i| acc << 1 | bit(i));
+                char::from_digit(digit, 16).unwrap().to_ascii_uppercase()
+            })
+            .collect()
+    }
+}
+
+/// S-expression of the packet tree, e.g. `(sum:0 v1 (literal:4 v6 2021))`
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let type_id = self.type_id();
+        write!(
+            f,
+            "({}:{} v{}",
+            Packet::NAMES[type_id as usize],
+            type_id,
+            self.version()
+        )?;
+        if let Packet::Literal { value, .. } = self {
+            write!(f, " {}", value)?;
+        }
+        for sub_packet in self.sub_packets() {
+            write!(f, " {}", sub_packet)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ();
+
+    /// Parses the S-expression notation. The `:type_id` after the name is optional
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s
+            .split_inclusive(['(', ')', ' '])
+            .flat_map(|part| {
+                let (token, delimiter) = part.split_at(part.len() - 1);
+                match delimiter {
+                    "(" | ")" => vec![token, delimiter],
+                    " " => vec![token],
+                    _ => vec![part],
+                }
+            })
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .peekable();
+        let packet = parse_sexpr(&mut tokens)?;
+        match tokens.next() {
+            Some(_) => Err(()),
+            None => Ok(packet),
+        }
+    }
+}
+
+fn parse_sexpr<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Result<Packet, ()> {
+    if tokens.next() != Some("(") {
+        return Err(());
+    }
+    let token = tokens.next().ok_or(())?;
+    let (name, type_id) = match token.split_once(':') {
+        Some((name, type_id)) => (name, Some(type_id.parse::<u8>().map_err(|_| ())?)),
+        None => (token, None),
+    };
+    let name_id = Packet::NAMES.iter().position(|n| *n == name).ok_or(())? as u8;
+    if type_id.is_some_and(|type_id| type_id != name_id) {
+        return Err(());
+    }
+    let version = tokens
+        .next()
+        .and_then(|token| token.strip_prefix('v'))
+        .and_then(|version| version.parse::<u8>().ok())
+        .filter(|version| *version < 8)
+        .ok_or(())?;
+
+    let packet = if name_id == 4 {
+        let value = tokens
+            .next()
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value >= 0)
+            .ok_or(())?;
+        Packet::Literal { version, value }
+    } else {
+        let mut sub_packets = Vec::new();
+        while tokens.peek() == Some(&"(") {
+            sub_packets.push(parse_sexpr(tokens)?);
+        }
+        // Comparisons take exactly two operands, everything else at least one
+        let arity_ok = match name_id {
+            5..=7 => sub_packets.len() == 2,
+            _ => !sub_packets.is_empty(),
+        };
+        if !arity_ok {
+            return Err(());
+        }
+        Packet::operator(version, name_id, sub_packets)
+    };
+    if tokens.next() != Some(")") {
+        return Err(());
+    }
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [&str; 15] = [
+        "D2FE28",
+        "38006F45291200",
+        "EE00D40C823060",
+        "8A004A801A8002F478",
+        "620080001611562C8802118E34",
+        "C0015000016115A2E0802F182340",
+        "A0016C880162017C3686B18A3D4780",
+        "C200B40A82",
+        "04005AC33890",
+        "880086C3E88112",
+        "CE00C43D881120",
+        "D8005AC2A8F0",
+        "F600BC2D8F",
+        "9C005AC2F8F0",
+        "9C0141080250320F1802104A08",
+    ];
+
+    #[test]
+    fn test_known() {
+        assert_eq!(solve("A0016C880162017C3686B18A3D4780").0.to_string(), "31");
+        assert_eq!(solve("9C0141080250320F1802104A08").1.to_string(), "1");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for hex in EXAMPLES {
+            let packet = PacketReader::from_hex(hex).read_packet();
+            for length_type in [LengthType::Bits, LengthType::Count] {
+                let encoded = PacketWriter::encode(&packet, length_type);
+                assert_eq!(PacketReader::from_hex(&encoded).read_packet(), packet);
+            }
+        }
+        assert_eq!(
+            PacketWriter::encode(
+                &Packet::Literal {
+                    version: 6,
+                    value: 2021
+                },
+                LengthType::Bits
+            ),
+            "D2FE28"
+        );
+    }
+
+    #[test]
+    fn test_sexpr() {
+        let packet = PacketReader::from_hex("38006F45291200").read_packet();
+        assert_eq!(
+            packet.to_string(),
+            "(lt:6 v1 (literal:4 v6 10) (literal:4 v2 20))"
+        );
+        for hex in EXAMPLES {
+            let packet = PacketReader::from_hex(hex).read_packet();
+            assert_eq!(packet.to_string().parse::<Packet>(), Ok(packet));
+        }
+        assert_eq!(
+            "(sum v0 (literal v1 1) (literal v2 2))"
+                .parse::<Packet>()
+                .map(|p| p.value()),
+            Ok(3)
+        );
+        assert!("(sum:1 v0)".parse::<Packet>().is_err());
+        assert!("(literal:4 v8 1)".parse::<Packet>().is_err());
+        assert!("(literal:4 v0 1".parse::<Packet>().is_err());
+        assert!("(literal:4 v0 -1)".parse::<Packet>().is_err());
+    }
+
+    #[test]
+    fn test_sexpr_arity() {
+        assert!("(gt:5 v0 (literal:4 v0 1))".parse::<Packet>().is_err());
+        assert!("(lt:6 v0)".parse::<Packet>().is_err());
+        let three = "(eq:7 v0 (literal:4 v0 1) (literal:4 v0 1) (literal:4 v0 1))";
+        assert!(three.parse::<Packet>().is_err());
+        for name in ["sum", "product", "min", "max"] {
+            assert!(format!("({name} v0)").parse::<Packet>().is_err());
+            let single = format!("({name} v0 (literal v0 7))").parse::<Packet>();
+            assert_eq!(single.map(|p| p.value()), Ok(7));
+        }
+    }
+}
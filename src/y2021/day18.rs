@@ -1,153 +1,454 @@
+use std::{fmt::Display, iter::Sum, ops::Add, str::FromStr};
+
+use itertools::Itertools;
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let snailfishes = input.lines().map(Segment::from_str).collect::<Vec<_>>();
+    let snailfishes = input
+        .lines()
+        .map(|line| line.parse::<SnailfishNumber>().unwrap())
+        .collect::<Vec<_>>();
+
+    let part1 = snailfishes
+        .iter()
+        .cloned()
+        .sum::<SnailfishNumber>()
+        .magnitude();
+
+    let part2 = snailfishes
+        .iter()
+        .permutations(2)
+        .map(|pair| (pair[0] + pair[1]).magnitude())
+        .max()
+        .unwrap();
+
+    (part1, part2)
+}
+
+/// A snailfish number stored as its regular numbers in order, each tagged with how many pairs
+/// enclose it
+///
+/// The depths of the leaves are enough to rebuild the tree, and keeping them flat means the
+/// neighbours an explosion adds to are just the elements either side of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SnailfishNumber {
+    leaves: Vec<Leaf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Leaf {
+    depth: u8,
+    value: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reduction {
+    Explode,
+    Split,
+}
 
-    let mut num = snailfishes[0].clone();
-    for other in &snailfishes[1..] {
-        num = num.add(other.clone());
+impl SnailfishNumber {
+    fn magnitude(&self) -> i64 {
+        self.collapse().expect("Malformed snailfish number")
     }
-    let part1 = num.magnitude();
 
-    let mut best = i64::MIN;
-    for (i, a) in snailfishes.iter().enumerate() {
-        for (j, b) in snailfishes.iter().enumerate() {
-            if i == j {
-                continue;
-            }
-            let mag = a.clone().add(b.clone()).magnitude();
-            if mag > best {
-                best = mag;
+    /// Magnitude found by merging sibling leaves from the bottom up, like evaluating postfix.
+    /// `None` if the depths don't describe a tree
+    fn collapse(&self) -> Option<i64> {
+        let mut stack: Vec<Leaf> = Vec::with_capacity(self.leaves.len());
+        for &leaf in &self.leaves {
+            let mut leaf = leaf;
+            while let Some(&left) = stack.last() {
+                if left.depth != leaf.depth || leaf.depth == 0 {
+                    break;
+                }
+                stack.pop();
+                leaf = Leaf {
+                    depth: leaf.depth - 1,
+                    value: 3 * left.value + 2 * leaf.value,
+                };
             }
+            stack.push(leaf);
+        }
+        match stack[..] {
+            [Leaf { depth: 0, value }] => Some(value),
+            _ => None,
         }
     }
-    let part2 = best;
 
-    (part1, part2)
+    fn reduce(&mut self) {
+        self.reduce_traced(|_, _| {});
+    }
+
+    /// Reduces the number, calling `trace` with the number after every explode or split
+    fn reduce_traced(&mut self, mut trace: impl FnMut(Reduction, &SnailfishNumber)) {
+        loop {
+            let action = if self.explode() {
+                Reduction::Explode
+            } else if self.split() {
+                Reduction::Split
+            } else {
+                break;
+            };
+            trace(action, self);
+        }
+    }
+
+    fn explode(&mut self) -> bool {
+        let Some(i) = self.leaves.iter().position(|leaf| leaf.depth > 4) else {
+            return false;
+        };
+        let (left, right) = (self.leaves[i], self.leaves[i + 1]);
+        debug_assert_eq!(left.depth, right.depth);
+        if i > 0 {
+            self.leaves[i - 1].value += left.value;
+        }
+        if let Some(next) = self.leaves.get_mut(i + 2) {
+            next.value += right.value;
+        }
+        self.leaves[i] = Leaf {
+            depth: left.depth - 1,
+            value: 0,
+        };
+        self.leaves.remove(i + 1);
+        true
+    }
+
+    fn split(&mut self) -> bool {
+        let Some(i) = self.leaves.iter().position(|leaf| leaf.value >= 10) else {
+            return false;
+        };
+        let Leaf { depth, value } = self.leaves[i];
+        self.leaves[i] = Leaf {
+            depth: depth + 1,
+            value: value / 2,
+        };
+        self.leaves.insert(
+            i + 1,
+            Leaf {
+                depth: depth + 1,
+                value: value - value / 2,
+            },
+        );
+        true
+    }
+
+    fn fmt_node(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        depth: u8,
+        index: &mut usize,
+    ) -> std::fmt::Result {
+        let leaf = self.leaves[*index];
+        if leaf.depth == depth {
+            *index += 1;
+            return write!(f, "{}", leaf.value);
+        }
+        write!(f, "[")?;
+        self.fmt_node(f, depth + 1, index)?;
+        write!(f, ",")?;
+        self.fmt_node(f, depth + 1, index)?;
+        write!(f, "]")
+    }
 }
 
-#[derive(Debug, Clone)]
-enum Segment {
-    Number(i64),
-    Pair(Box<Segment>, Box<Segment>),
+impl Add for &SnailfishNumber {
+    type Output = SnailfishNumber;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let leaves = self
+            .leaves
+            .iter()
+            .chain(rhs.leaves.iter())
+            .map(|leaf| Leaf {
+                depth: leaf.depth + 1,
+                value: leaf.value,
+            })
+            .collect();
+        let mut result = SnailfishNumber { leaves };
+        result.reduce();
+        result
+    }
 }
 
-impl Segment {
-    fn from_str(data: &str) -> Self {
-        if data.starts_with('[') {
-            assert!(data.ends_with(']'));
-            let inner = &data[1..(data.len() - 1)];
-            let mut split_index = None;
-            let mut depth = 0;
-            for (i, c) in inner.chars().enumerate() {
-                match c {
-                    '[' => depth += 1,
-                    ']' => depth -= 1,
-                    ',' => {
-                        if depth == 0 {
-                            split_index = Some(i);
-                            break;
-                        }
+impl Add for SnailfishNumber {
+    type Output = SnailfishNumber;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        (&self).add(&rhs)
+    }
+}
+
+impl Sum for SnailfishNumber {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, number| acc + number)
+            .expect("No identity for snailfish addition")
+    }
+}
+
+impl FromStr for SnailfishNumber {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut leaves = Vec::new();
+        let mut depth = 0_u8;
+        let mut chars = s.trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth = depth.checked_sub(1).ok_or(())?,
+                ',' => (),
+                '0'..='9' => {
+                    let mut value = c.to_digit(10).unwrap() as i64;
+                    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        value = value * 10 + digit as i64;
+                        chars.next();
                     }
-                    _ => (),
+                    leaves.push(Leaf { depth, value });
                 }
+                _ => return Err(()),
             }
-            let split_index = split_index.unwrap();
-            let (left, right) = inner.split_at(split_index);
-            let left = Segment::from_str(left);
-            let right = Segment::from_str(&right[1..]);
-            Segment::Pair(Box::new(left), Box::new(right))
-        } else {
-            Segment::Number(data.parse().unwrap())
         }
+        let number = SnailfishNumber { leaves };
+        // Catches stray commas and the like once the structure is known to be sound
+        if number.collapse().is_none() || number.to_string() != s.trim() {
+            return Err(());
+        }
+        Ok(number)
     }
+}
 
-    fn add(self, other: Segment) -> Self {
-        let mut result = Segment::Pair(Box::new(self), Box::new(other));
-        result.reduce();
-        result
+impl Display for SnailfishNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_node(f, 0, &mut 0)
     }
+}
 
-    fn reduce(&mut self) {
-        loop {
-            if self.explode(0).is_some() {
-                continue;
-            }
-            if self.split() {
-                continue;
-            }
-            break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
+
+    #[test]
+    fn test_known() {
+        let (part1, part2) = solve(EXAMPLE);
+        assert_eq!(part1.to_string(), "4140");
+        assert_eq!(part2.to_string(), "3993");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for line in EXAMPLE.lines() {
+            assert_eq!(line.parse::<SnailfishNumber>().unwrap().to_string(), line);
         }
+        assert!("[1,2".parse::<SnailfishNumber>().is_err());
+        assert!("[1,2]]".parse::<SnailfishNumber>().is_err());
+        assert!("[1,[2]]".parse::<SnailfishNumber>().is_err());
+        assert!("[1,,2]".parse::<SnailfishNumber>().is_err());
     }
 
-    fn explode(&mut self, depth: usize) -> Option<(Option<i64>, Option<i64>)> {
-        match self {
-            Segment::Pair(left, right) => {
-                if depth == 4 {
-                    match (left.as_ref(), right.as_ref()) {
-                        (Segment::Number(l), Segment::Number(r)) => {
-                            let (l, r) = (*l, *r);
-                            *self = Segment::Number(0);
-                            Some((Some(l), Some(r)))
+    #[test]
+    fn test_trace() {
+        let a = "[[[[4,3],4],4],[7,[[8,4],9]]]"
+            .parse::<SnailfishNumber>()
+            .unwrap();
+        let b = "[1,1]".parse::<SnailfishNumber>().unwrap();
+        assert_eq!((&a + &b).to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+
+        // Add without reducing, then reduce step by step
+        let mut number = SnailfishNumber {
+            leaves: a
+                .leaves
+                .iter()
+                .chain(b.leaves.iter())
+                .map(|leaf| Leaf {
+                    depth: leaf.depth + 1,
+                    value: leaf.value,
+                })
+                .collect(),
+        };
+        let mut steps = Vec::new();
+        number.reduce_traced(|action, number| steps.push((action, number.to_string())));
+        assert_eq!(
+            steps,
+            [
+                (Reduction::Explode, "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]"),
+                (Reduction::Explode, "[[[[0,7],4],[15,[0,13]]],[1,1]]"),
+                (Reduction::Split, "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]"),
+                (Reduction::Split, "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]"),
+                (Reduction::Explode, "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]"),
+            ]
+            .map(|(action, s)| (action, s.to_string()))
+        );
+    }
+
+    /// The original boxed tree implementation, kept as a reference
+    #[derive(Debug, Clone)]
+    enum Segment {
+        Number(i64),
+        Pair(Box<Segment>, Box<Segment>),
+    }
+
+    impl Segment {
+        fn from_str(data: &str) -> Self {
+            if data.starts_with('[') {
+                assert!(data.ends_with(']'));
+                let inner = &data[1..(data.len() - 1)];
+                let mut split_index = None;
+                let mut depth = 0;
+                for (i, c) in inner.chars().enumerate() {
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        ',' if depth == 0 => {
+                            split_index = Some(i);
+                            break;
                         }
-                        _ => None,
+                        _ => (),
                     }
-                } else if let Some((left_val, right_val)) = left.explode(depth + 1) {
-                    if let Some(right_val) = right_val {
-                        right.add_right(right_val);
+                }
+                let split_index = split_index.unwrap();
+                let (left, right) = inner.split_at(split_index);
+                let left = Segment::from_str(left);
+                let right = Segment::from_str(&right[1..]);
+                Segment::Pair(Box::new(left), Box::new(right))
+            } else {
+                Segment::Number(data.parse().unwrap())
+            }
+        }
+
+        fn add(self, other: Segment) -> Self {
+            let mut result = Segment::Pair(Box::new(self), Box::new(other));
+            result.reduce();
+            result
+        }
+
+        fn reduce(&mut self) {
+            loop {
+                if self.explode(0).is_some() {
+                    continue;
+                }
+                if self.split() {
+                    continue;
+                }
+                break;
+            }
+        }
+
+        fn explode(&mut self, depth: usize) -> Option<(Option<i64>, Option<i64>)> {
+            match self {
+                Segment::Pair(left, right) => {
+                    if depth == 4 {
+                        match (left.as_ref(), right.as_ref()) {
+                            (Segment::Number(l), Segment::Number(r)) => {
+                                let (l, r) = (*l, *r);
+                                *self = Segment::Number(0);
+                                Some((Some(l), Some(r)))
+                            }
+                            _ => None,
+                        }
+                    } else if let Some((left_val, right_val)) = left.explode(depth + 1) {
+                        if let Some(right_val) = right_val {
+                            right.add_right(right_val);
+                        }
+                        Some((left_val, None))
+                    } else if let Some((left_val, right_val)) = right.explode(depth + 1) {
+                        if let Some(left_val) = left_val {
+                            left.add_left(left_val);
+                        }
+                        Some((None, right_val))
+                    } else {
+                        None
                     }
-                    Some((left_val, None))
-                } else if let Some((left_val, right_val)) = right.explode(depth + 1) {
-                    if let Some(left_val) = left_val {
-                        left.add_left(left_val);
+                }
+                Segment::Number(_) => None,
+            }
+        }
+
+        fn add_right(&mut self, value: i64) {
+            match self {
+                Segment::Pair(left, _) => left.add_right(value),
+                Segment::Number(n) => *n += value,
+            }
+        }
+
+        fn add_left(&mut self, value: i64) {
+            match self {
+                Segment::Pair(_, right) => right.add_left(value),
+                Segment::Number(n) => *n += value,
+            }
+        }
+
+        fn split(&mut self) -> bool {
+            match self {
+                Segment::Pair(left, right) => left.split() || right.split(),
+                Segment::Number(n) => {
+                    if *n >= 10 {
+                        *self = Segment::Pair(
+                            Box::new(Segment::Number(*n / 2)),
+                            Box::new(Segment::Number((*n / 2) + (*n % 2))),
+                        );
+                        true
+                    } else {
+                        false
                     }
-                    Some((None, right_val))
-                } else {
-                    None
                 }
             }
-            Segment::Number(_) => None,
         }
-    }
 
-    /// Add a number from the right of an explosion
-    fn add_right(&mut self, value: i64) {
-        match self {
-            Segment::Pair(left, _) => left.add_right(value),
-            Segment::Number(n) => {
-                *n += value;
+        fn magnitude(&self) -> i64 {
+            match self {
+                Segment::Pair(left, right) => 3 * left.magnitude() + 2 * right.magnitude(),
+                Segment::Number(n) => *n,
             }
         }
     }
 
-    fn add_left(&mut self, value: i64) {
-        match self {
-            Segment::Pair(_, right) => right.add_left(value),
-            Segment::Number(n) => {
-                *n += value;
+    impl Display for Segment {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Segment::Pair(left, right) => write!(f, "[{},{}]", left, right),
+                Segment::Number(n) => write!(f, "{}", n),
             }
         }
     }
 
-    fn split(&mut self) -> bool {
-        match self {
-            Segment::Pair(left, right) => left.split() || right.split(),
-            Segment::Number(n) => {
-                if *n >= 10 {
-                    *self = Segment::Pair(
-                        Box::new(Segment::Number(*n / 2)),
-                        Box::new(Segment::Number((*n / 2) + (*n % 2))),
-                    );
-                    true
-                } else {
-                    false
-                }
-            }
+    /// Random reduced snailfish number, using a xorshift generator so failures are reproducible
+    fn random_number(state: &mut u64, depth: u8) -> String {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        if depth == 4 || (depth > 0 && state.is_multiple_of(3)) {
+            return (*state % 10).to_string();
         }
+        format!(
+            "[{},{}]",
+            random_number(state, depth + 1),
+            random_number(state, depth + 1)
+        )
     }
 
-    fn magnitude(&self) -> i64 {
-        match self {
-            Segment::Pair(left, right) => 3 * left.magnitude() + 2 * right.magnitude(),
-            Segment::Number(n) => *n,
+    #[test]
+    fn test_matches_tree() {
+        let mut state = 0x2021_1218;
+        for _ in 0..200 {
+            let a = random_number(&mut state, 0);
+            let b = random_number(&mut state, 0);
+
+            let tree = Segment::from_str(&a).add(Segment::from_str(&b));
+            let flat = a.parse::<SnailfishNumber>().unwrap() + b.parse().unwrap();
+
+            assert_eq!(flat.to_string(), tree.to_string());
+            assert_eq!(flat.magnitude(), tree.magnitude());
         }
     }
 }
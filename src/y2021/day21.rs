@@ -1,90 +1,217 @@
 use std::collections::HashMap;
 
+use num::BigUint;
+
+const PRACTICE: Game = Game {
+    faces: 100,
+    rolls: 3,
+    spaces: 10,
+    target: 1000,
+};
+
+const DIRAC: Game = Game {
+    faces: 3,
+    rolls: 3,
+    spaces: 10,
+    target: 21,
+};
+
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let starts = input
         .lines()
-        .map(|l| l.split(": ").last().unwrap().parse::<usize>().unwrap())
+        .map(|l| l.split(": ").last().unwrap().parse::<usize>().unwrap() - 1)
         .collect::<Vec<_>>();
 
-    let mut positions = (starts[0] - 1, starts[1] - 1);
-    let mut scores = (0, 0);
-    let mut dice = Dice::new();
-    let part1;
-    loop {
-        let player1_roll = dice.roll() + dice.roll() + dice.roll();
-        positions.0 = (positions.0 + player1_roll) % 10;
-        scores.0 += positions.0 + 1;
-        if scores.0 >= 1000 {
-            part1 = scores.1 * dice.rolls;
-            break;
+    let part1 = PRACTICE.practice(&starts);
+    let part2 = DIRAC.wins(&starts).into_iter().max().unwrap();
+
+    (part1, part2)
+}
+
+/// Dirac Dice with any die and board. Spaces and rolls are 1 based, positions 0 based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Game {
+    faces: usize,
+    /// Rolls per turn, added together
+    rolls: usize,
+    spaces: usize,
+    /// Score that wins
+    target: usize,
+}
+
+/// Whose turn it is and where everyone stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    turn: usize,
+    positions: Vec<usize>,
+    scores: Vec<usize>,
+}
+
+/// Something to add up universes with. Fails rather than overflowing
+trait Count: Clone {
+    fn zero() -> Self;
+    fn add(self, other: Self) -> Option<Self>;
+    fn mul(self, other: Self) -> Option<Self>;
+}
+
+impl Game {
+    /// Plays with a die that rolls 1, 2, 3 and so on, going back to 1 after its last face.
+    /// Gives the lowest losing score times the number of rolls
+    fn practice(&self, starts: &[usize]) -> usize {
+        let mut positions = starts.to_vec();
+        let mut scores = vec![0; starts.len()];
+        let mut die = (1..=self.faces).cycle();
+        let mut rolls = 0;
+        for player in (0..starts.len()).cycle() {
+            let roll = die.by_ref().take(self.rolls).sum::<usize>();
+            rolls += self.rolls;
+            positions[player] = (positions[player] + roll) % self.spaces;
+            scores[player] += positions[player] + 1;
+            if scores[player] >= self.target {
+                // The winner has the highest score, so this is a loser's
+                return scores.iter().min().unwrap() * rolls;
+            }
         }
-        let player2_roll = dice.roll() + dice.roll() + dice.roll();
-        positions.1 = (positions.1 + player2_roll) % 10;
-        scores.1 += positions.1 + 1;
-        if scores.1 >= 1000 {
-            part1 = scores.0 * dice.rolls;
-            break;
+        unreachable!()
+    }
+
+    /// How many universes each player wins in. Counts are done again with big integers if
+    /// they don't fit in `u64`
+    fn wins(&self, starts: &[usize]) -> Vec<BigUint> {
+        match self.tally(starts, |ways| ways) {
+            Some(wins) => wins.into_iter().map(BigUint::from).collect(),
+            None => self.tally(starts, BigUint::from).unwrap(),
         }
     }
 
-    let mut states = HashMap::new();
-    states.insert(
-        State {
-            positions: [starts[0] - 1, starts[1] - 1],
-            scores: [0, 0],
-        },
-        1,
-    );
-    let mut wins = [0_i128, 0];
-    let mut player2_turn = false;
-    while !states.is_empty() {
-        let mut new_states = HashMap::new();
-        let player_index = player2_turn as usize;
-        for (state, state_count) in states {
-            for (roll, roll_count) in [(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)] {
-                let mut new_state = state.clone();
-                new_state.positions[player_index] = (new_state.positions[player_index] + roll) % 10;
-                new_state.scores[player_index] += new_state.positions[player_index] + 1;
-                if new_state.scores[player_index] >= 21 {
-                    wins[player_index] += state_count * roll_count;
-                    continue;
-                }
-                *new_states.entry(new_state).or_default() += state_count * roll_count;
+    /// Each player's chance of winning
+    #[allow(dead_code)]
+    fn win_probabilities(&self, starts: &[usize]) -> Vec<f64> {
+        let outcomes = (self.faces as f64).powi(self.rolls as i32);
+        self.tally(starts, |ways| ways as f64 / outcomes).unwrap()
+    }
+
+    /// Adds up every way the game can go for each winner, weighting each turn's roll by
+    /// `weight` of the number of ways to roll it. `None` if that overflows
+    fn tally<T: Count>(&self, starts: &[usize], weight: impl Fn(u64) -> T) -> Option<Vec<T>> {
+        let states = (self.spaces * self.target)
+            .checked_pow(starts.len() as u32)
+            .and_then(|states| states.checked_mul(starts.len()));
+        assert!(states.is_some(), "Too many states to memoise");
+
+        let rolls = self
+            .roll_totals()
+            .into_iter()
+            .map(|(total, ways)| (total, weight(ways)))
+            .collect::<Vec<_>>();
+        let state = State {
+            turn: 0,
+            positions: starts.to_vec(),
+            scores: vec![0; starts.len()],
+        };
+        self.tally_from(&state, &rolls, &mut HashMap::new())
+    }
+
+    fn tally_from<T: Count>(
+        &self,
+        state: &State,
+        rolls: &[(usize, T)],
+        memo: &mut HashMap<usize, Vec<T>>,
+    ) -> Option<Vec<T>> {
+        let key = self.key(state);
+        if let Some(wins) = memo.get(&key) {
+            return Some(wins.clone());
+        }
+
+        let player = state.turn;
+        let mut wins = vec![T::zero(); state.positions.len()];
+        for (total, weight) in rolls.iter() {
+            let mut next = state.clone();
+            next.positions[player] = (next.positions[player] + total) % self.spaces;
+            next.scores[player] += next.positions[player] + 1;
+            if next.scores[player] >= self.target {
+                wins[player] = wins[player].clone().add(weight.clone())?;
+                continue;
+            }
+            next.turn = (player + 1) % state.positions.len();
+            let later = self.tally_from(&next, rolls, memo)?;
+            for (win, later) in wins.iter_mut().zip(later) {
+                *win = win.clone().add(later.mul(weight.clone())?)?;
             }
         }
+        memo.insert(key, wins.clone());
+        Some(wins)
+    }
 
-        states = new_states;
-        player2_turn = !player2_turn;
+    /// Packs a state into one number, which `tally` has checked fits
+    fn key(&self, state: &State) -> usize {
+        state
+            .positions
+            .iter()
+            .zip(state.scores.iter())
+            .fold(state.turn, |key, (position, score)| {
+                (key * self.spaces + position) * self.target + score
+            })
     }
-    let part2 = *wins.iter().max().unwrap();
 
-    (part1, part2)
+    /// Each total a turn's rolls can add up to, with how many ways there are to roll it
+    fn roll_totals(&self) -> Vec<(usize, u64)> {
+        let mut ways = vec![1_u64];
+        for _ in 0..self.rolls {
+            let mut next = vec![0; ways.len() + self.faces];
+            for (total, count) in ways.iter().enumerate() {
+                for face in 1..=self.faces {
+                    next[total + face] += count;
+                }
+            }
+            ways = next;
+        }
+        ways.into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct State {
-    positions: [usize; 2],
-    scores: [usize; 2],
+impl Count for u64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn add(self, other: Self) -> Option<Self> {
+        self.checked_add(other)
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        self.checked_mul(other)
+    }
 }
 
-struct Dice {
-    value: usize,
-    rolls: usize,
+impl Count for BigUint {
+    fn zero() -> Self {
+        BigUint::ZERO
+    }
+
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
 }
 
-impl Dice {
-    fn new() -> Dice {
-        Dice { value: 1, rolls: 0 }
+impl Count for f64 {
+    fn zero() -> Self {
+        0.0
     }
 
-    fn roll(&mut self) -> usize {
-        let roll = self.value;
-        self.rolls += 1;
-        self.value += 1;
-        if self.value > 100 {
-            self.value = 1;
-        }
-        roll
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
     }
 }
 
@@ -100,5 +227,49 @@ Player 2 starting position: 8
         let (part1, part2) = solve(input);
         assert_eq!(part1.to_string(), "739785");
         assert_eq!(part2.to_string(), "444356092776315");
+
+        let wins = DIRAC.wins(&[3, 7]);
+        assert_eq!(wins[1], BigUint::from(341960390180808_u64));
+    }
+
+    #[test]
+    fn test_roll_totals() {
+        assert_eq!(
+            DIRAC.roll_totals(),
+            [(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)]
+        );
+    }
+
+    #[test]
+    fn test_big_counts() {
+        let game = Game {
+            faces: 6,
+            rolls: 3,
+            spaces: 10,
+            target: 21,
+        };
+        assert!(game.tally(&[3, 7], |ways| ways).is_none());
+        let wins = game.wins(&[3, 7]);
+        assert!(wins[0] > BigUint::from(u64::MAX));
+
+        let probabilities = game.win_probabilities(&[3, 7]);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_more_players() {
+        let game = Game {
+            faces: 2,
+            rolls: 2,
+            spaces: 7,
+            target: 12,
+        };
+        let starts = [0, 3, 5];
+        assert_eq!(game.practice(&starts), 7 * 14);
+        let probabilities = game.win_probabilities(&starts);
+        assert_eq!(probabilities.len(), 3);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        // Going first is worth something
+        assert!(probabilities[0] > probabilities[2]);
     }
 }
@@ -1,214 +1,316 @@
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    ops::{Add, Div, Mul, Rem, Sub},
+};
 
 use itertools::Itertools;
 use num::Integer;
+use winnow::{
+    ascii::{line_ending, space0},
+    combinator::{alt, delimited, preceded, repeat, separated},
+    error::ContextError,
+    prelude::*,
+};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let monkeys = input
-        .split("\n\n")
-        .map(|m| m.parse::<Monkey>().unwrap())
-        .collect::<Vec<_>>();
+use crate::common::{blocks, expected, parse_all, parse_i64, parse_u64, ParseError};
 
-    let mut state = State::new(monkeys.clone());
-    for _ in 0..20 {
-        state.do_round(true);
-    }
-    let part1 = state.result();
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let troop = parse_all(input, blocks(parse_monkey)).map(|monkeys| Troop { monkeys })?;
 
-    let mut state = State::new(monkeys);
-    for _ in 0..10_000 {
-        state.do_round(false);
-    }
-    let part2 = state.result();
+    let part1 = monkey_business(&troop.simulate::<i64>(20, Reduction::Relief));
+    let part2 = monkey_business(&troop.inspections_by_cycles(10_000));
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
-#[derive(Debug)]
-struct State {
+#[derive(Debug, Clone)]
+struct Troop {
     monkeys: Vec<Monkey>,
-    inspection_counts: Vec<u64>,
-    lcm: i64,
 }
 
-impl State {
-    fn new(monkeys: Vec<Monkey>) -> Self {
-        let monkeys_len = monkeys.len();
-        let lcm = monkeys
-            .iter()
-            .map(|m| m.test_value)
-            .fold(1, |a, b| a.lcm(&b));
-        State {
-            monkeys,
-            inspection_counts: vec![0; monkeys_len],
-            lcm,
-        }
-    }
+#[derive(Debug, Clone)]
+struct Monkey {
+    items: Vec<i64>,
+    operation: Expression,
+    divisor: i64,
+    if_true: usize,
+    if_false: usize,
+}
 
-    fn do_round(&mut self, part1: bool) {
-        for i in 0..self.monkeys.len() {
-            let monkey = self.monkeys.get_mut(i).unwrap();
+/// The right hand side of `new = ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Old,
+    Number(i64),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
 
-            self.inspection_counts[i] += monkey.items.len() as u64;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+}
 
-            let mut items = Vec::new();
-            std::mem::swap(&mut items, &mut monkey.items);
+/// How worry levels are kept from growing forever
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reduction {
+    /// Divided by 3 after each inspection
+    Relief,
+    /// Worry levels are only ever tested for divisibility, so taking them modulo a multiple of
+    /// every divisor changes nothing
+    Modulus(i64),
+    /// Left alone, which needs `BigInt` for more than a few rounds
+    #[allow(dead_code)]
+    Exact,
+}
 
-            let to_send = items
-                .into_iter()
-                .map(|item| {
-                    let Item { mut worry_level } = item;
+/// What worry levels can be kept as
+trait Worry:
+    Clone
+    + PartialEq
+    + From<i64>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+}
 
-                    match monkey.operation_type {
-                        Operation::Add => worry_level += monkey.operation_value.get(worry_level),
-                        Operation::Multiply => {
-                            worry_level *= monkey.operation_value.get(worry_level)
-                        }
-                    }
+impl<W> Worry for W where
+    W: Clone
+        + PartialEq
+        + From<i64>
+        + Add<Output = W>
+        + Sub<Output = W>
+        + Mul<Output = W>
+        + Div<Output = W>
+        + Rem<Output = W>
+{
+}
 
-                    if part1 {
-                        worry_level /= 3;
-                    } else {
-                        worry_level %= self.lcm;
-                    }
+impl Troop {
+    /// Inspections per monkey after `rounds` rounds, passing every item round by round
+    fn simulate<W: Worry>(&self, rounds: usize, reduction: Reduction) -> Vec<u64> {
+        let mut items = self
+            .monkeys
+            .iter()
+            .map(|monkey| {
+                monkey
+                    .items
+                    .iter()
+                    .map(|worry| W::from(*worry))
+                    .collect_vec()
+            })
+            .collect_vec();
+        let mut inspections = vec![0; self.monkeys.len()];
+        for _ in 0..rounds {
+            for monkey in 0..self.monkeys.len() {
+                for worry in std::mem::take(&mut items[monkey]) {
+                    inspections[monkey] += 1;
+                    let (target, worry) = self.inspect(monkey, worry, reduction);
+                    items[target].push(worry);
+                }
+            }
+        }
+        inspections
+    }
 
-                    let target = if worry_level % monkey.test_value == 0 {
-                        monkey.test_true_target
-                    } else {
-                        monkey.test_false_target
-                    };
+    /// Inspections per monkey after `rounds` rounds without relief. Items never affect each
+    /// other, so each one is followed on its own until it starts a round somewhere it's
+    /// started one before, after which it just goes round the same cycle
+    fn inspections_by_cycles(&self, rounds: u64) -> Vec<u64> {
+        let modulus = self.modulus();
+        let reduction = Reduction::Modulus(modulus);
+        let mut totals = vec![0; self.monkeys.len()];
+        for (start, worry) in self.items() {
+            // Inspections per monkey in the item's first `r` rounds, for each `r` so far
+            let mut history = vec![vec![0; self.monkeys.len()]];
+            let mut seen = HashMap::new();
+            let mut state = (start, worry.rem_euclid(modulus));
+            let inspections = loop {
+                let round = history.len() - 1;
+                if round as u64 == rounds {
+                    break history[round].clone();
+                }
+                if let Some(first) = seen.insert(state, round) {
+                    let period = (round - first) as u64;
+                    let cycles = (rounds - first as u64) / period;
+                    let rest = ((rounds - first as u64) % period) as usize;
+                    let (before, after) = (&history[first], &history[round]);
+                    break (0..self.monkeys.len())
+                        .map(|m| history[first + rest][m] + cycles * (after[m] - before[m]))
+                        .collect();
+                }
+                let mut inspections = history[round].clone();
+                state = self.follow_round(state, &mut inspections, reduction);
+                history.push(inspections);
+            };
+            for (total, count) in totals.iter_mut().zip(inspections) {
+                *total += count;
+            }
+        }
+        totals
+    }
 
-                    (target, Item { worry_level })
-                })
-                .collect::<Vec<_>>();
+    /// Takes one item from the start of a round to the start of the next. It keeps going
+    /// within the round as long as it's thrown to a monkey that hasn't had its turn yet
+    fn follow_round(
+        &self,
+        (mut monkey, mut worry): (usize, i64),
+        inspections: &mut [u64],
+        reduction: Reduction,
+    ) -> (usize, i64) {
+        loop {
+            inspections[monkey] += 1;
+            let (target, new_worry) = self.inspect(monkey, worry, reduction);
+            let next_round = target <= monkey;
+            (monkey, worry) = (target, new_worry);
+            if next_round {
+                return (monkey, worry);
+            }
+        }
+    }
 
-            for (target, item) in to_send {
-                self.monkeys[target].items.push(item);
+    /// Where `monkey` throws an item, and its worry level by then
+    fn inspect<W: Worry>(&self, monkey: usize, worry: W, reduction: Reduction) -> (usize, W) {
+        let monkey = &self.monkeys[monkey];
+        let reduce = |worry: W| match reduction {
+            Reduction::Modulus(modulus) => {
+                let modulus = W::from(modulus);
+                (worry % modulus.clone() + modulus.clone()) % modulus
             }
+            Reduction::Relief | Reduction::Exact => worry,
+        };
+        let mut worry = monkey.operation.evaluate(&worry, &reduce);
+        if reduction == Reduction::Relief {
+            worry = worry / W::from(3);
+        }
+        match worry.clone() % W::from(monkey.divisor) == W::from(0) {
+            true => (monkey.if_true, worry),
+            false => (monkey.if_false, worry),
         }
     }
 
-    fn result(&self) -> u64 {
-        self.inspection_counts
+    /// A multiple of every divisor, as small as it can be
+    fn modulus(&self) -> i64 {
+        self.monkeys
             .iter()
-            .sorted()
-            .rev()
-            .take(2)
-            .product::<u64>()
+            .fold(1, |modulus, monkey| modulus.lcm(&monkey.divisor))
     }
-}
-
-#[derive(Debug, Clone)]
-struct Monkey {
-    items: Vec<Item>,
-    operation_type: Operation,
-    operation_value: OperationValue,
-    test_value: i64,
-    test_true_target: usize,
-    test_false_target: usize,
-}
 
-impl FromStr for Monkey {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines = s.lines().collect::<Vec<_>>();
-
-        let items = lines[1]
-            .trim_start_matches("  Starting items: ")
-            .split(", ")
-            .map(|p| Item {
-                worry_level: p.parse().unwrap(),
-            })
-            .collect::<Vec<_>>();
-
-        let operation_line_parts = lines[2]
-            .trim_start_matches("  Operation: new = old ")
-            .split_once(' ')
-            .unwrap();
-        let operation_type = operation_line_parts.0.parse().unwrap();
-        let operation_value = operation_line_parts.1.parse().unwrap();
-
-        let test_value = lines[3]
-            .trim_start_matches("  Test: divisible by ")
-            .parse()
-            .unwrap();
-        let test_true_target = lines[4]
-            .trim_start_matches("    If true: throw to monkey ")
-            .parse()
-            .unwrap();
-        let test_false_target = lines[5]
-            .trim_start_matches("    If false: throw to monkey ")
-            .parse()
-            .unwrap();
-
-        Ok(Monkey {
-            items,
-            operation_type,
-            operation_value,
-            test_value,
-            test_true_target,
-            test_false_target,
-        })
+    /// Each item's starting monkey and worry level
+    fn items(&self) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.monkeys
+            .iter()
+            .enumerate()
+            .flat_map(|(index, monkey)| monkey.items.iter().map(move |worry| (index, *worry)))
     }
 }
 
-#[derive(Debug, Clone)]
-struct Item {
-    worry_level: i64,
+/// The two highest inspection counts multiplied together
+fn monkey_business(inspections: &[u64]) -> u64 {
+    inspections.iter().sorted().rev().take(2).product()
 }
 
-#[derive(Debug, Clone)]
-enum Operation {
-    Multiply,
-    Add,
+/// Inspection counts worded the way the puzzle shows them
+#[allow(dead_code)]
+fn report(inspections: &[u64]) -> String {
+    inspections
+        .iter()
+        .enumerate()
+        .map(|(monkey, count)| format!("Monkey {monkey} inspected items {count} times."))
+        .join("\n")
 }
 
-impl FromStr for Operation {
-    type Err = ();
+impl Expression {
+    /// The value for worry level `old`, with `reduce` applied after each operation so
+    /// intermediate values don't overflow
+    fn evaluate<W: Worry>(&self, old: &W, reduce: &impl Fn(W) -> W) -> W {
+        match self {
+            Expression::Old => old.clone(),
+            Expression::Number(value) => reduce(W::from(*value)),
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(old, reduce);
+                let right = right.evaluate(old, reduce);
+                reduce(match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                })
+            }
+        }
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "*" => Operation::Multiply,
-            "+" => Operation::Add,
-            _ => unreachable!(),
+fn parse_monkey(input: &mut &str) -> winnow::Result<Monkey> {
+    let line = |text: &'static str| (line_ending, space0, text);
+    (
+        delimited("Monkey ", parse_u64, ':'),
+        preceded(
+            line("Starting items:"),
+            separated(0.., preceded(space0, parse_i64), ','),
+        ),
+        preceded(line("Operation: new ="), parse_expression),
+        preceded(line("Test: divisible by "), parse_i64),
+        preceded(line("If true: throw to monkey "), parse_u64),
+        preceded(line("If false: throw to monkey "), parse_u64),
+    )
+        .map(|(_, items, operation, divisor, if_true, if_false)| Monkey {
+            items,
+            operation,
+            divisor,
+            if_true: if_true as usize,
+            if_false: if_false as usize,
         })
-    }
+        .parse_next(input)
 }
 
-#[derive(Debug, Clone)]
-enum OperationValue {
-    Integer(i64),
-    Old,
+/// Sums of products of `old`, numbers and bracketed expressions
+fn parse_expression(input: &mut &str) -> winnow::Result<Expression> {
+    let operator = alt(('+'.value(Operator::Add), '-'.value(Operator::Subtract)));
+    parse_chain(input, parse_term, operator)
 }
 
-impl OperationValue {
-    fn get(&self, old: i64) -> i64 {
-        match self {
-            OperationValue::Integer(v) => *v,
-            OperationValue::Old => old,
-        }
-    }
+fn parse_term(input: &mut &str) -> winnow::Result<Expression> {
+    parse_chain(input, parse_factor, '*'.value(Operator::Multiply))
 }
 
-impl FromStr for OperationValue {
-    type Err = ();
+/// Operands with left associative operators between them
+fn parse_chain<'a>(
+    input: &mut &'a str,
+    mut operand: impl FnMut(&mut &'a str) -> winnow::Result<Expression>,
+    operator: impl Parser<&'a str, Operator, ContextError>,
+) -> winnow::Result<Expression> {
+    let first = operand(input)?;
+    let rest: Vec<_> =
+        repeat(0.., (delimited(space0, operator, space0), operand)).parse_next(input)?;
+    Ok(rest.into_iter().fold(first, |left, (operator, right)| {
+        Expression::Binary(Box::new(left), operator, Box::new(right))
+    }))
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "old" => OperationValue::Old,
-            v => OperationValue::Integer(v.parse().unwrap()),
-        })
-    }
+fn parse_factor(input: &mut &str) -> winnow::Result<Expression> {
+    preceded(
+        space0,
+        alt((
+            "old".value(Expression::Old),
+            parse_i64.map(Expression::Number),
+            delimited('(', parse_expression, (space0, ')')),
+        )),
+    )
+    .context(expected("old, a number or a bracketed expression"))
+    .parse_next(input)
 }
 
 #[cfg(test)]
 mod tests {
+    use num::BigInt;
+
     use super::*;
 
-    #[test]
-    fn test_known() {
-        let input = "Monkey 0:
+    const EXAMPLE: &str = "Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
   Test: divisible by 23
@@ -236,9 +338,58 @@ Monkey 3:
     If true: throw to monkey 0
     If false: throw to monkey 1";
 
-        let result = solve(input);
+    fn example() -> Troop {
+        let monkeys = parse_all(EXAMPLE, blocks(parse_monkey)).unwrap();
+        Troop { monkeys }
+    }
 
-        assert_eq!(result.0.to_string(), 10605.to_string());
-        assert_eq!(result.1.to_string(), 2713310158_u64.to_string());
+    #[test]
+    fn test_known() {
+        let (part1, part2) = solve(EXAMPLE).unwrap();
+        assert_eq!(part1.to_string(), "10605");
+        assert_eq!(part2.to_string(), "2713310158");
+    }
+
+    #[test]
+    fn test_modulus_matches_exact() {
+        let troop = example();
+        let modulus = Reduction::Modulus(troop.modulus());
+        assert_eq!(troop.simulate::<i64>(1, modulus), [2, 4, 3, 6]);
+        assert_eq!(troop.simulate::<i64>(20, modulus), [99, 97, 8, 103]);
+        assert_eq!(
+            troop.simulate::<BigInt>(20, Reduction::Exact),
+            troop.simulate::<i64>(20, modulus)
+        );
+    }
+
+    #[test]
+    fn test_cycles() {
+        let troop = example();
+        let modulus = Reduction::Modulus(troop.modulus());
+        for rounds in [0, 1, 20, 1000] {
+            assert_eq!(
+                troop.inspections_by_cycles(rounds as u64),
+                troop.simulate::<i64>(rounds, modulus)
+            );
+        }
+        assert_eq!(
+            report(&troop.inspections_by_cycles(10_000)),
+            "Monkey 0 inspected items 52166 times.
+Monkey 1 inspected items 47830 times.
+Monkey 2 inspected items 1938 times.
+Monkey 3 inspected items 52013 times."
+        );
+        // Every item gets inspected at least once a round
+        let billion = troop.inspections_by_cycles(1_000_000_000);
+        assert!(billion.iter().sum::<u64>() >= 10 * 1_000_000_000);
+    }
+
+    #[test]
+    fn test_expression() {
+        let expression = parse_all("old * (old + 2) - 3*old", parse_expression).unwrap();
+        assert_eq!(expression.evaluate(&5_i64, &|worry| worry), 20);
+        let reduce = |worry: i64| worry.rem_euclid(7);
+        assert_eq!(expression.evaluate(&5, &reduce), 6);
+        assert!(parse_all("old * ", parse_expression).is_err());
     }
 }
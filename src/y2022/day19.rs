@@ -1,61 +1,27 @@
-use std::str::FromStr;
-
-use nom::{
-    bytes::complete::tag,
-    character::complete::{alpha1, digit1},
-    combinator::{map, map_res, opt, recognize},
-    multi::{many1, separated_list0},
-    sequence::{delimited, preceded, separated_pair},
-    IResult, Parser,
+use rayon::prelude::*;
+use winnow::{
+    ascii::space0,
+    combinator::{alt, delimited, preceded, repeat, separated, separated_pair, terminated},
+    prelude::*,
 };
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let blueprints = input
-        .trim()
-        .lines()
-        .map(|l| l.parse::<Blueprint>().unwrap())
-        .collect::<Vec<_>>();
-
-    for blueprint in blueprints.iter() {
-        let expected_outputs = [
-            Resource::Ore,
-            Resource::Clay,
-            Resource::Obsidian,
-            Resource::Geode,
-        ];
-        let expected_inputs = [
-            vec![Resource::Ore],
-            vec![Resource::Ore],
-            vec![Resource::Ore, Resource::Clay],
-            vec![Resource::Ore, Resource::Obsidian],
-        ];
-        for ((recipe, expected_output), expected_inputs) in blueprint
-            .recipes
-            .iter()
-            .zip(expected_outputs.iter())
-            .zip(expected_inputs.iter())
-        {
-            assert_eq!(&recipe.output, expected_output);
-            assert_eq!(recipe.inputs.len(), expected_inputs.len());
-            for (input, expected_input) in recipe.inputs.iter().map(|(r, _)| r).zip(expected_inputs)
-            {
-                assert_eq!(input, expected_input);
-            }
-        }
-    }
+use crate::common::{expected, lines, parse_all, parse_i64, ParseError};
 
-    let part1 = blueprints
-        .iter()
-        .map(|b| b.quality_level(24) * b.id)
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let planners = parse_all(input, lines(parse_planner))?;
+
+    let part1 = planners
+        .par_iter()
+        .map(|(id, planner)| id * planner.best(24) as i64)
         .sum::<i64>();
 
-    let part2 = blueprints
-        .iter()
+    let part2 = planners
+        .par_iter()
         .take(3)
-        .map(|b| b.quality_level(32))
+        .map(|(_, planner)| planner.best(32) as i64)
         .product::<i64>();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -78,133 +44,223 @@ enum Resource {
     Geode,
 }
 
+/// Plans which producers to build to end up with as much of one resource as possible. Every
+/// resource has one kind of producer making one of it a minute, and one producer can be built a
+/// minute
 #[derive(Debug, Clone)]
-struct State {
-    income: [i64; 4],
-    resources: [i64; 4],
-    time: i64,
+struct Planner<const N: usize> {
+    /// What a producer costs, indexed by the resource it produces
+    costs: [[u32; N]; N],
+    /// The resource to maximise
+    target: usize,
+    initial_producers: [u32; N],
+    /// More producers than the most any producer costs in that resource can't all be spent
+    max_useful: [u32; N],
 }
 
-impl Blueprint {
-    fn quality_level(&self, max_time: i64) -> i64 {
-        let initial_state = State {
-            income: [1, 0, 0, 0],
-            resources: [0; 4],
-            time: 0,
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State<const N: usize> {
+    time_left: u32,
+    amounts: [u32; N],
+    producers: [u32; N],
+}
 
-        let mut max_incomes = [Resource::Ore, Resource::Clay, Resource::Obsidian]
-            .into_iter()
-            .map(|resource| {
-                self.recipes
-                    .iter()
-                    .filter_map(|r| {
-                        r.inputs
-                            .iter()
-                            .find(|(r, _)| r == &resource)
-                            .map(|(_, v)| *v)
-                    })
-                    .max()
-                    .unwrap()
-            })
-            .collect::<Vec<_>>();
-        max_incomes.push(i64::MAX);
-
-        let mut best = i64::MIN;
-        let mut queue = vec![initial_state];
-        while let Some(state) = queue.pop() {
-            // If time up test score
-            if state.time == max_time {
-                let score = state.resources[Resource::Geode.as_index()];
-                if score > best {
-                    best = score;
-                }
-                continue;
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Build {
+    /// Counting from 1, the minute the producer's cost is paid. It starts producing the minute
+    /// after
+    minute: u32,
+    producer: usize,
+}
 
-            if state.time > max_time {
-                continue;
-            }
+/// The state at the end of one minute of a schedule
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Minute<const N: usize> {
+    minute: u32,
+    built: Option<usize>,
+    amounts: [u32; N],
+    producers: [u32; N],
+}
+
+struct Search {
+    time: u32,
+    best: u32,
+    builds: Vec<Build>,
+    /// Only kept when asked for, as copying it on every improvement isn't free
+    best_builds: Option<Vec<Build>>,
+}
 
-            // If no further changes test score
-            let score = state.income[Resource::Geode.as_index()] * (max_time - state.time);
-            if score > best {
-                best = score;
+impl Blueprint {
+    /// Fails if a resource has no robot or more than one
+    fn planner(&self) -> Option<Planner<4>> {
+        let mut costs = [[0; 4]; 4];
+        let mut seen = [false; 4];
+        for recipe in self.recipes.iter() {
+            let output = recipe.output.as_index();
+            if std::mem::replace(&mut seen[output], true) {
+                return None;
+            }
+            for (resource, amount) in recipe.inputs.iter() {
+                costs[output][resource.as_index()] = u32::try_from(*amount).ok()?;
             }
+        }
+        if !seen.iter().all(|seen| *seen) {
+            return None;
+        }
+        Some(Planner::new(
+            costs,
+            Resource::Geode.as_index(),
+            [1, 0, 0, 0],
+        ))
+    }
+}
 
-            queue.extend(state.moves(&self.recipes, &max_incomes));
+impl<const N: usize> Planner<N> {
+    fn new(costs: [[u32; N]; N], target: usize, initial_producers: [u32; N]) -> Self {
+        let max_useful = std::array::from_fn(|resource| match resource == target {
+            true => u32::MAX,
+            false => costs.iter().map(|cost| cost[resource]).max().unwrap_or(0),
+        });
+        Self {
+            costs,
+            target,
+            initial_producers,
+            max_useful,
         }
+    }
 
-        best
+    /// The most of the target resource there can be after `time` minutes
+    fn best(&self, time: u32) -> u32 {
+        self.run(time, false).best
     }
-}
 
-impl State {
-    fn moves(&self, recipes: &[Recipe], max_incomes: &[i64]) -> Vec<State> {
-        let mut moves = Vec::new();
+    /// The best amount along with a schedule reaching it
+    #[allow(dead_code)]
+    fn best_schedule(&self, time: u32) -> (u32, Vec<Minute<N>>) {
+        let search = self.run(time, true);
+        let builds = search.best_builds.unwrap_or_default();
+        (search.best, self.timeline(&builds, time).unwrap())
+    }
 
-        for recipe in recipes {
-            if self.income[recipe.output.as_index()] >= max_incomes[recipe.output.as_index()] {
-                continue;
+    fn run(&self, time: u32, keep_schedule: bool) -> Search {
+        let mut search = Search {
+            time,
+            best: 0,
+            builds: Vec::new(),
+            best_builds: keep_schedule.then(Vec::new),
+        };
+        let start = State {
+            time_left: time,
+            amounts: [0; N],
+            producers: self.initial_producers,
+        };
+        self.search(&mut search, start);
+        search
+    }
+
+    /// Depth first over which producer to build next, jumping straight to the minute it can be
+    /// afforded rather than deciding minute by minute
+    fn search(&self, search: &mut Search, state: State<N>) {
+        let idle = state.amounts[self.target] + state.producers[self.target] * state.time_left;
+        if idle > search.best {
+            search.best = idle;
+            if let Some(best_builds) = search.best_builds.as_mut() {
+                best_builds.clone_from(&search.builds);
             }
+        }
+        if self.upper_bound(&state) <= search.best {
+            return;
+        }
 
-            // Check income to reach recipe
-            if !recipe
-                .inputs
-                .iter()
-                .all(|(resource, _)| self.income[resource.as_index()] > 0)
-            {
+        // The target's producer first, as it finds good plans soonest
+        let order = std::iter::once(self.target).chain((0..N).rev().filter(|p| *p != self.target));
+        for producer in order {
+            if state.producers[producer] >= self.max_useful[producer] {
+                continue;
+            }
+            let Some(wait) = self.wait(&state, producer) else {
+                continue;
+            };
+            // Anything built in the last minute never gets to produce
+            if wait + 1 >= state.time_left {
                 continue;
             }
 
-            // Calculate time needed to afford recipe
-            let time_taken = recipe
-                .inputs
-                .iter()
-                .map(|(resource, amount)| {
-                    (amount - self.resources[resource.as_index()])
-                        .div_ceil(self.income[resource.as_index()])
-                })
-                .max()
-                .unwrap()
-                .max(0)
-                + 1;
-
-            let mut new_state = self.clone();
-
-            new_state.time += time_taken;
-
-            // Update resources
-            new_state
-                .resources
-                .iter_mut()
-                .zip(self.income.iter())
-                .for_each(|(res, inc)| *res += inc * time_taken);
-
-            // Subtract amounts
-            recipe.inputs.iter().for_each(|(resource, amount)| {
-                new_state.resources[resource.as_index()] -= amount;
-            });
-
-            // Add income
-            new_state.income[recipe.output.as_index()] += 1;
+            let mut next = state;
+            next.time_left -= wait + 1;
+            for resource in 0..N {
+                next.amounts[resource] += state.producers[resource] * (wait + 1);
+                next.amounts[resource] -= self.costs[producer][resource];
+            }
+            next.producers[producer] += 1;
 
-            moves.push(new_state);
+            search.builds.push(Build {
+                minute: search.time - state.time_left + wait + 1,
+                producer,
+            });
+            self.search(search, next);
+            search.builds.pop();
         }
-
-        moves
     }
-}
 
-impl FromStr for Blueprint {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (s, blueprint) = parse_blueprint(s).unwrap();
+    /// Minutes until `producer` can be afforded, if it ever can be with the current producers
+    fn wait(&self, state: &State<N>, producer: usize) -> Option<u32> {
+        (0..N).try_fold(0, |wait, resource| {
+            let missing = self.costs[producer][resource].saturating_sub(state.amounts[resource]);
+            match (missing, state.producers[resource]) {
+                (0, _) => Some(wait),
+                (_, 0) => None,
+                (missing, producers) => Some(wait.max(missing.div_ceil(producers))),
+            }
+        })
+    }
 
-        assert!(s.is_empty());
+    /// What the target would reach if costs were never paid and one of every affordable
+    /// producer could be built each minute, which is never less than what's really possible
+    fn upper_bound(&self, state: &State<N>) -> u32 {
+        let mut amounts = state.amounts;
+        let mut producers = state.producers;
+        for _ in 0..state.time_left {
+            let affordable: [bool; N] = std::array::from_fn(|producer| {
+                (0..N).all(|resource| self.costs[producer][resource] <= amounts[resource])
+            });
+            for resource in 0..N {
+                amounts[resource] += producers[resource];
+                producers[resource] += u32::from(affordable[resource]);
+            }
+        }
+        amounts[self.target]
+    }
 
-        Ok(blueprint)
+    /// Replays `builds` minute by minute, failing if a build can't be afforded when scheduled
+    #[allow(dead_code)]
+    fn timeline(&self, builds: &[Build], time: u32) -> Option<Vec<Minute<N>>> {
+        let mut amounts: [u32; N] = [0; N];
+        let mut producers = self.initial_producers;
+        let mut builds = builds.iter().peekable();
+        let mut minutes = Vec::new();
+        for minute in 1..=time {
+            let built = builds.next_if(|b| b.minute == minute).map(|b| b.producer);
+            if let Some(producer) = built {
+                for (amount, cost) in amounts.iter_mut().zip(self.costs[producer]) {
+                    *amount = amount.checked_sub(cost)?;
+                }
+            }
+            for resource in 0..N {
+                amounts[resource] += producers[resource];
+            }
+            if let Some(producer) = built {
+                producers[producer] += 1;
+            }
+            minutes.push(Minute {
+                minute,
+                built,
+                amounts,
+                producers,
+            });
+        }
+        builds.next().is_none().then_some(minutes)
     }
 }
 
@@ -219,57 +275,133 @@ impl Resource {
     }
 }
 
-fn parse_blueprint(s: &str) -> IResult<&str, Blueprint> {
-    let (s, id) = preceded(tag("Blueprint "), parse_i64).parse(s)?;
-    let (s, recipes) = preceded(tag(": "), many1(parse_recipe)).parse(s)?;
-
-    Ok((s, Blueprint { id, recipes }))
+/// A blueprint's id and planner, which needs exactly one robot for each resource
+fn parse_planner(input: &mut &str) -> winnow::Result<(i64, Planner<4>)> {
+    parse_blueprint
+        .verify_map(|blueprint| Some((blueprint.id, blueprint.planner()?)))
+        .context(expected("one robot for each resource"))
+        .parse_next(input)
 }
 
-fn parse_recipe(s: &str) -> IResult<&str, Recipe> {
-    let (s, output) = preceded(preceded(opt(tag(" ")), tag("Each ")), parse_resource).parse(s)?;
-    let (s, inputs) = delimited(
-        tag(" robot costs "),
-        separated_list0(
-            tag(" and "),
-            separated_pair(parse_i64, tag(" "), parse_resource),
-        ),
-        tag("."),
+fn parse_blueprint(input: &mut &str) -> winnow::Result<Blueprint> {
+    (
+        delimited("Blueprint ", parse_i64, ':'),
+        repeat(1.., preceded(space0, parse_recipe)),
     )
-    .parse(s)?;
-
-    let inputs = inputs.into_iter().map(|(a, b)| (b, a)).collect();
-
-    Ok((s, Recipe { output, inputs }))
+        .map(|(id, recipes)| Blueprint { id, recipes })
+        .parse_next(input)
 }
 
-fn parse_resource(s: &str) -> IResult<&str, Resource> {
-    map(alpha1, |s| match s {
-        "ore" => Resource::Ore,
-        "clay" => Resource::Clay,
-        "obsidian" => Resource::Obsidian,
-        "geode" => Resource::Geode,
-        _ => unreachable!(),
-    })
-    .parse(s)
+fn parse_recipe(input: &mut &str) -> winnow::Result<Recipe> {
+    let cost =
+        separated_pair(parse_i64, ' ', parse_resource).map(|(amount, resource)| (resource, amount));
+    (
+        delimited("Each ", parse_resource, " robot costs "),
+        terminated(separated(1.., cost, " and "), '.'),
+    )
+        .map(|(output, inputs)| Recipe { output, inputs })
+        .parse_next(input)
 }
 
-fn parse_i64(s: &str) -> IResult<&str, i64> {
-    map_res(recognize(preceded(opt(tag("-")), digit1)), i64::from_str).parse(s)
+fn parse_resource(input: &mut &str) -> winnow::Result<Resource> {
+    alt((
+        "ore".value(Resource::Ore),
+        "clay".value(Resource::Clay),
+        "obsidian".value(Resource::Obsidian),
+        "geode".value(Resource::Geode),
+    ))
+    .context(expected("resource"))
+    .parse_next(input)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_known() {
-        let input = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.
+    const EXAMPLE: &str = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.
 Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.";
 
-        let result = solve(input);
+    #[test]
+    fn test_known() {
+        let result = solve(EXAMPLE).unwrap();
 
         assert_eq!(result.0.to_string(), 33.to_string());
         assert_eq!(result.1.to_string(), (56 * 62).to_string());
+
+        let duplicate = "Blueprint 1: Each ore robot costs 4 ore. Each ore robot costs 2 ore.";
+        let error = parse_all(duplicate, parse_planner).unwrap_err();
+        assert!(
+            error.to_string().contains("one robot for each resource"),
+            "{error}"
+        );
+        let unknown = "Blueprint 1: Each ore robot costs 4 wood.";
+        let error = parse_all(unknown, parse_planner).unwrap_err();
+        assert!(error.to_string().contains("resource"), "{error}");
+    }
+
+    #[test]
+    fn test_schedule() {
+        let (_, planner) = parse_all(EXAMPLE.lines().next().unwrap(), parse_planner).unwrap();
+        let (best, timeline) = planner.best_schedule(24);
+        assert_eq!(best, 9);
+        assert_eq!(timeline.len(), 24);
+        assert_eq!(timeline.last().unwrap().amounts[3], 9);
+        // Builds are only ever affordable ones, at most one a minute
+        for (before, after) in timeline.iter().zip(timeline.iter().skip(1)) {
+            let built = after.producers.iter().sum::<u32>() - before.producers.iter().sum::<u32>();
+            assert_eq!(built, u32::from(after.built.is_some()));
+        }
+    }
+
+    /// Every choice every minute, for comparing against on small cases
+    fn brute_force<const N: usize>(planner: &Planner<N>, state: State<N>) -> u32 {
+        if state.time_left == 0 {
+            return state.amounts[planner.target];
+        }
+        let mut next = state;
+        next.time_left -= 1;
+        for resource in 0..N {
+            next.amounts[resource] += state.producers[resource];
+        }
+        let mut best = brute_force(planner, next);
+        for producer in 0..N {
+            let cost = planner.costs[producer];
+            if (0..N).all(|resource| cost[resource] <= state.amounts[resource]) {
+                let mut built = next;
+                for (amount, cost) in built.amounts.iter_mut().zip(cost) {
+                    *amount -= cost;
+                }
+                built.producers[producer] += 1;
+                best = best.max(brute_force(planner, built));
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_other_configurations() {
+        // Wood, planks and chairs, where chairs come from both
+        let costs = [[2, 0, 0], [3, 0, 0], [1, 3, 0]];
+        let planner = Planner::new(costs, 2, [1, 0, 0]);
+        // Targeting planks instead, so chairs are never worth building
+        let planks = Planner::new(costs, 1, [1, 0, 0]);
+        assert_eq!(planks.max_useful, [3, u32::MAX, 0]);
+
+        for time in 1..=12 {
+            for planner in [&planner, &planks] {
+                let start = State {
+                    time_left: time,
+                    amounts: [0; 3],
+                    producers: planner.initial_producers,
+                };
+                let (best, timeline) = planner.best_schedule(time);
+                assert_eq!(best, brute_force(planner, start));
+                assert_eq!(
+                    timeline.last().unwrap().amounts[planner.target],
+                    best,
+                    "{time}"
+                );
+            }
+        }
     }
 }
@@ -1,19 +1,15 @@
-use std::str::FromStr;
-
-use nom::{
-    branch::alt,
-    character::complete::{char, digit1},
-    combinator::map,
-    multi::many1,
-    IResult, Parser,
+use winnow::{
+    ascii::line_ending,
+    combinator::{alt, repeat},
+    prelude::*,
 };
 
-use crate::common::{Vector2, Vector3};
+use crate::common::{expected, lines, parse_all, parse_i64, ParseError, Vector2, Vector3};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
     // glhf
 
-    let input = input.parse::<Input>().unwrap();
+    let input = parse_input(input)?;
 
     let object = input.map.position_after_moves(&input.moves);
     let part1 = object.score();
@@ -23,7 +19,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let object2 = cube_map.position_after_moves(&input.moves);
     let part2 = object2.score();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -619,7 +615,7 @@ impl Direction {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum Move {
     Left,
     Right,
@@ -632,47 +628,36 @@ struct Input {
     moves: Vec<Move>,
 }
 
-impl FromStr for Input {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (a, b) = s.split_once("\n\n").unwrap();
-
-        let mut tiles = a
-            .lines()
-            .map(|l| {
-                l.chars()
-                    .map(|c| match c {
-                        ' ' => Tile::Empty,
-                        '.' => Tile::Floor,
-                        '#' => Tile::Wall,
-                        _ => unreachable!(),
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    let tile = alt((
+        ' '.value(Tile::Empty),
+        '.'.value(Tile::Floor),
+        '#'.value(Tile::Wall),
+    ))
+    .context(expected("tile"));
+    // Rows stop at their last tile, so are padded out to the widest
+    let map = lines(repeat(1.., tile)).map(|mut tiles: Vec<Vec<Tile>>| {
         let width = tiles.iter().map(|row| row.len()).max().unwrap();
         tiles
             .iter_mut()
             .for_each(|row| row.resize_with(width, || Tile::Empty));
-
-        let moves = many1(parse_move).parse(b.trim()).unwrap().1;
-
-        Ok(Input {
-            map: Map { tiles },
-            moves,
-        })
-    }
+        Map { tiles }
+    });
+    let sections = (map, line_ending, line_ending, repeat(1.., parse_move));
+    parse_all(
+        input,
+        sections.map(|(map, _, _, moves)| Input { map, moves }),
+    )
 }
 
-fn parse_move(s: &str) -> IResult<&str, Move> {
+fn parse_move(input: &mut &str) -> winnow::Result<Move> {
     alt((
-        map(char('L'), |_| Move::Left),
-        map(char('R'), |_| Move::Right),
-        map(digit1, |s: &str| Move::Forward(s.parse().unwrap())),
+        'L'.value(Move::Left),
+        'R'.value(Move::Right),
+        parse_i64.map(Move::Forward),
     ))
-    .parse(s)
+    .context(expected("move"))
+    .parse_next(input)
 }
 
 #[cfg(test)]
@@ -696,7 +681,7 @@ mod tests {
 
 10R5L5R10L4R5L5";
 
-        let result = solve(input);
+        let result = solve(input).unwrap();
 
         assert_eq!(result.0.to_string(), 6032.to_string());
         assert_eq!(result.1.to_string(), 5031.to_string());
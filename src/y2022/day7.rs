@@ -1,25 +1,26 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+const DISK_SIZE: u64 = 70000000;
+const UPDATE_SIZE: u64 = 30000000;
 
 pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     let commands = CommandList::from_str(input).unwrap().0;
 
     let mut filesystem = FileSystem::default();
     for command in commands.iter() {
-        filesystem.run_command(command);
+        filesystem.run_command(command).unwrap();
     }
 
-    let folder_sizes = filesystem.calculate_folder_sizes();
-
-    let part1 = folder_sizes.values().filter(|v| **v <= 100000).sum::<u64>();
+    let part1 = filesystem
+        .folders_where(|size| size <= 100000)
+        .into_iter()
+        .map(|(_, size)| size)
+        .sum::<u64>();
 
-    let total_size = *folder_sizes.get(&filesystem.root).unwrap();
-    let minimum_to_delete = total_size - (70000000 - 30000000);
-    let mut candidates = folder_sizes
-        .values()
-        .filter(|v| **v >= minimum_to_delete)
-        .collect::<Vec<_>>();
-    candidates.sort();
-    let part2 = *candidates[0];
+    let part2 = filesystem
+        .smallest_freeing(DISK_SIZE, UPDATE_SIZE)
+        .unwrap()
+        .1;
 
     (part1, part2)
 }
@@ -34,23 +35,34 @@ struct FileSystem {
 #[derive(Debug)]
 struct Folder {
     name: String,
-    files: Vec<File>,
-    folders: Vec<FolderRef>,
+    /// In the order they were first listed
+    entries: Vec<Entry>,
     parent: Option<FolderRef>,
+    listed: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct FolderRef(usize);
 
-#[derive(Debug)]
-struct File {
-    size: u64,
-    _name: String,
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    File { name: String, size: u64 },
+    Folder(FolderRef),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum FileSystemError {
+    NotFound(String),
+    AboveRoot,
+    /// A folder listed twice with different contents
+    InconsistentListing(String),
+    /// An `ls` line that isn't `dir <name>` or `<size> <name>`
+    BadListing(String),
 }
 
 impl Default for FileSystem {
     fn default() -> Self {
-        let root_folder = Folder::new("".to_string(), None);
+        let root_folder = Folder::new("/".to_string(), None);
         let root_folder_ref = FolderRef(0);
         let folders = vec![root_folder];
         Self {
@@ -62,40 +74,113 @@ impl Default for FileSystem {
 }
 
 impl FileSystem {
-    fn run_command(&mut self, command: &Command) {
+    fn run_command(&mut self, command: &Command) -> Result<(), FileSystemError> {
         match command {
-            Command::Up => self.cwd = self.cwd().parent.unwrap(),
-            Command::Enter(name) => {
-                self.cwd = *self
-                    .cwd()
-                    .folders
-                    .iter()
-                    .map(|fr| (fr, self.folder(*fr)))
-                    .find(|(_, f)| &f.name == name)
-                    .unwrap()
-                    .0
-            }
-            Command::Root => self.cwd = self.root,
+            Command::ChangeDirectory(path) => self.cwd = self.resolve(self.cwd, path)?,
             Command::List(result) => {
-                let mut new_files = Vec::new();
-                let mut new_folders = Vec::new();
+                let mut listing = Vec::new();
                 for line in result {
-                    let parts = line.split(' ').collect::<Vec<_>>();
-                    match parts[0] {
-                        "dir" => {
-                            new_folders.push(self.create_folder(parts[1].to_string(), self.cwd))
-                        }
-                        _ => new_files.push(File {
-                            size: parts[0].parse().unwrap(),
-                            _name: parts[1].to_string(),
-                        }),
-                    }
+                    let bad_listing = || FileSystemError::BadListing(line.to_string());
+                    let (size, name) = line.split_once(' ').ok_or_else(bad_listing)?;
+                    listing.push(match size {
+                        "dir" => (name.to_string(), None),
+                        _ => (
+                            name.to_string(),
+                            Some(size.parse().map_err(|_| bad_listing())?),
+                        ),
+                    });
                 }
-                let folder = self.cwd_mut();
-                folder.files.extend(new_files);
-                folder.folders.extend(new_folders);
+                self.apply_listing(self.cwd, listing)?;
             }
         }
+        Ok(())
+    }
+
+    /// Fills in a folder from `ls` output, or checks it matches if it's been listed before
+    fn apply_listing(
+        &mut self,
+        folder_ref: FolderRef,
+        listing: Vec<(String, Option<u64>)>,
+    ) -> Result<(), FileSystemError> {
+        if self.folder(folder_ref).listed {
+            let mut existing = self
+                .folder(folder_ref)
+                .entries
+                .iter()
+                .map(|entry| match entry {
+                    Entry::File { name, size } => (name.clone(), Some(*size)),
+                    Entry::Folder(child) => (self.folder(*child).name.clone(), None),
+                })
+                .collect::<Vec<_>>();
+            let mut listing = listing;
+            existing.sort();
+            listing.sort();
+            if existing != listing {
+                return Err(FileSystemError::InconsistentListing(self.path(folder_ref)));
+            }
+            return Ok(());
+        }
+
+        let entries = listing
+            .into_iter()
+            .map(|(name, size)| match size {
+                Some(size) => Entry::File { name, size },
+                None => Entry::Folder(self.create_folder(name, folder_ref)),
+            })
+            .collect();
+        let folder = self.folder_mut(folder_ref);
+        folder.entries = entries;
+        folder.listed = true;
+        Ok(())
+    }
+
+    /// Resolves an absolute or relative path, which may contain `..`
+    fn resolve(&self, from: FolderRef, path: &str) -> Result<FolderRef, FileSystemError> {
+        let (mut current, path) = match path.strip_prefix('/') {
+            Some(path) => (self.root, path),
+            None => (from, path),
+        };
+        for part in path
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != ".")
+        {
+            current = match part {
+                ".." => self
+                    .folder(current)
+                    .parent
+                    .ok_or(FileSystemError::AboveRoot)?,
+                _ => self
+                    .child(current, part)
+                    .ok_or_else(|| FileSystemError::NotFound(path.to_string()))?,
+            };
+        }
+        Ok(current)
+    }
+
+    fn child(&self, folder_ref: FolderRef, name: &str) -> Option<FolderRef> {
+        self.subfolders(folder_ref)
+            .find(|child| self.folder(*child).name == name)
+    }
+
+    fn subfolders(&self, folder_ref: FolderRef) -> impl Iterator<Item = FolderRef> + '_ {
+        self.folder(folder_ref)
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Folder(child) => Some(*child),
+                Entry::File { .. } => None,
+            })
+    }
+
+    fn path(&self, folder_ref: FolderRef) -> String {
+        let mut parts = Vec::new();
+        let mut current = folder_ref;
+        while let Some(parent) = self.folder(current).parent {
+            parts.push(self.folder(current).name.as_str());
+            current = parent;
+        }
+        parts.reverse();
+        format!("/{}", parts.join("/"))
     }
 
     fn create_folder(&mut self, name: String, parent: FolderRef) -> FolderRef {
@@ -105,14 +190,6 @@ impl FileSystem {
         folder_ref
     }
 
-    fn cwd(&self) -> &Folder {
-        self.folder(self.cwd)
-    }
-
-    fn cwd_mut(&mut self) -> &mut Folder {
-        self.folder_mut(self.cwd)
-    }
-
     fn folder(&self, folder_ref: FolderRef) -> &Folder {
         &self.folders[folder_ref.0]
     }
@@ -126,36 +203,96 @@ impl FileSystem {
         let mut search_queue = Vec::new();
         while let Some(folder_ref) = queue.pop() {
             search_queue.push(folder_ref);
-            let folder = self.folder(folder_ref);
-            queue.extend(&folder.folders);
+            queue.extend(self.subfolders(folder_ref));
         }
 
         let mut folder_sizes = HashMap::new();
         while let Some(folder_ref) = search_queue.pop() {
-            let folder = self.folder(folder_ref);
-
-            let file_size_sum = folder.files.iter().map(|f| f.size).sum::<u64>();
-            let folder_size_sum = folder
-                .folders
+            let total_size = self
+                .folder(folder_ref)
+                .entries
                 .iter()
-                .map(|fr| folder_sizes.get(fr).unwrap())
+                .map(|entry| match entry {
+                    Entry::File { size, .. } => *size,
+                    Entry::Folder(child) => *folder_sizes.get(child).unwrap(),
+                })
                 .sum::<u64>();
-
-            let total_size = file_size_sum + folder_size_sum;
             folder_sizes.insert(folder_ref, total_size);
         }
 
         folder_sizes
     }
+
+    /// Every folder whose total size matches `predicate`, with that size
+    fn folders_where(&self, predicate: impl Fn(u64) -> bool) -> Vec<(FolderRef, u64)> {
+        let mut folders = self
+            .calculate_folder_sizes()
+            .into_iter()
+            .filter(|(_, size)| predicate(*size))
+            .collect::<Vec<_>>();
+        folders.sort_by_key(|(folder_ref, _)| folder_ref.0);
+        folders
+    }
+
+    /// Smallest folder that leaves at least `needed` free on a disk of `disk_size` once deleted
+    fn smallest_freeing(&self, disk_size: u64, needed: u64) -> Option<(FolderRef, u64)> {
+        let sizes = self.calculate_folder_sizes();
+        let to_delete = (sizes[&self.root] + needed).saturating_sub(disk_size);
+        sizes
+            .into_iter()
+            .filter(|(_, size)| *size >= to_delete)
+            .min_by_key(|(_, size)| *size)
+    }
+
+    /// Path to the most deeply nested folder. Ties go to whichever was listed first
+    #[allow(dead_code)]
+    fn deepest_path(&self) -> String {
+        let mut deepest = (0, self.root);
+        let mut queue = vec![(0, self.root)];
+        while let Some((depth, folder_ref)) = queue.pop() {
+            if depth > deepest.0 {
+                deepest = (depth, folder_ref);
+            }
+            let children = self.subfolders(folder_ref).collect::<Vec<_>>();
+            queue.extend(children.into_iter().rev().map(|child| (depth + 1, child)));
+        }
+        self.path(deepest.1)
+    }
+
+    fn fmt_folder(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        folder_ref: FolderRef,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{indent}- {} (dir)", self.folder(folder_ref).name)?;
+        for entry in self.folder(folder_ref).entries.iter() {
+            match entry {
+                Entry::File { name, size } => {
+                    writeln!(f, "{indent}  - {name} (file, size={size})")?
+                }
+                Entry::Folder(child) => self.fmt_folder(f, *child, depth + 1)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders like the tree in the puzzle text
+impl Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_folder(f, self.root, 0)
+    }
 }
 
 impl Folder {
     fn new(name: String, parent: Option<FolderRef>) -> Folder {
         Folder {
             name,
-            files: Vec::new(),
-            folders: Vec::new(),
+            entries: Vec::new(),
             parent,
+            listed: false,
         }
     }
 }
@@ -163,9 +300,7 @@ impl Folder {
 #[derive(Debug)]
 enum Command {
     List(Vec<String>),
-    Up,
-    Enter(String),
-    Root,
+    ChangeDirectory(String),
 }
 
 struct CommandList(Vec<Command>);
@@ -188,9 +323,7 @@ impl FromStr for CommandList {
                     }
                     Command::List(results)
                 }
-                "cd" if parts[2] == ".." => Command::Up,
-                "cd" if parts[2] == "/" => Command::Root,
-                "cd" => Command::Enter(parts[2].to_string()),
+                "cd" => Command::ChangeDirectory(parts[2].to_string()),
                 _ => unreachable!(),
             };
             commands.push(command)
@@ -204,9 +337,7 @@ impl FromStr for CommandList {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_known() {
-        let input = "$ cd /
+    const EXAMPLE: &str = "$ cd /
 $ ls
 dir a
 14848514 b.txt
@@ -230,9 +361,94 @@ $ ls
 5626152 d.ext
 7214296 k";
 
-        let result = solve(input);
+    #[test]
+    fn test_known() {
+        let result = solve(EXAMPLE);
 
         assert_eq!(result.0.to_string(), 95437.to_string());
         assert_eq!(result.1.to_string(), 24933642.to_string());
     }
+
+    fn example() -> FileSystem {
+        let mut filesystem = FileSystem::default();
+        for command in CommandList::from_str(EXAMPLE).unwrap().0.iter() {
+            filesystem.run_command(command).unwrap();
+        }
+        filesystem
+    }
+
+    #[test]
+    fn test_render() {
+        let expected = "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+";
+        assert_eq!(example().to_string(), expected);
+    }
+
+    #[test]
+    fn test_paths() {
+        let filesystem = example();
+        let e = filesystem.resolve(filesystem.root, "a/e").unwrap();
+        assert_eq!(filesystem.path(e), "/a/e");
+        let d = filesystem.resolve(e, "../../d").unwrap();
+        assert_eq!(filesystem.resolve(d, "/a/./e"), Ok(e));
+        assert_eq!(filesystem.resolve(d, ".."), Ok(filesystem.root));
+        assert_eq!(
+            filesystem.resolve(d, "../.."),
+            Err(FileSystemError::AboveRoot)
+        );
+        assert!(matches!(
+            filesystem.resolve(d, "e"),
+            Err(FileSystemError::NotFound(_))
+        ));
+        assert_eq!(filesystem.deepest_path(), "/a/e");
+    }
+
+    #[test]
+    fn test_queries() {
+        let filesystem = example();
+        let small = filesystem.folders_where(|size| size <= 100000);
+        let paths = small
+            .iter()
+            .map(|(folder_ref, _)| filesystem.path(*folder_ref))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/a", "/a/e"]);
+        let (folder_ref, size) = filesystem.smallest_freeing(DISK_SIZE, UPDATE_SIZE).unwrap();
+        assert_eq!(
+            (filesystem.path(folder_ref), size),
+            ("/d".to_string(), 24933642)
+        );
+    }
+
+    #[test]
+    fn test_relisting() {
+        let mut filesystem = example();
+        let relist =
+            |lines: &[&str]| Command::List(lines.iter().map(|line| line.to_string()).collect());
+        let cd = Command::ChangeDirectory("/a/e".to_string());
+        filesystem.run_command(&cd).unwrap();
+        assert_eq!(filesystem.run_command(&relist(&["584 i"])), Ok(()));
+        assert_eq!(
+            filesystem.run_command(&relist(&["584 i", "1 j"])),
+            Err(FileSystemError::InconsistentListing("/a/e".to_string()))
+        );
+        for line in ["584", "big i"] {
+            assert_eq!(
+                filesystem.run_command(&relist(&[line])),
+                Err(FileSystemError::BadListing(line.to_string()))
+            );
+        }
+    }
 }
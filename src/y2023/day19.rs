@@ -1,13 +1,15 @@
 use std::{collections::HashMap, ops::Range};
 
 use winnow::{
-    ascii::{alpha1, digit1, multispace0},
+    ascii::{alpha1, line_ending},
     combinator::{alt, separated},
     Parser,
 };
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let (workflows, ratings) = parse_input(input);
+use crate::common::{lines, parse_all, parse_i64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let (workflows, ratings) = parse_input(input)?;
 
     let workflow_map = workflows
         .iter()
@@ -99,7 +101,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -239,16 +241,17 @@ impl RangePartRating {
     }
 }
 
-fn parse_input(input: &str) -> (Vec<Workflow>, Vec<PartRating>) {
-    (
-        separated(1.., parse_workflow, "\n"),
-        "\n\n",
-        separated(1.., parse_rating, "\n"),
-        multispace0,
+fn parse_input(input: &str) -> Result<(Vec<Workflow>, Vec<PartRating>), ParseError> {
+    parse_all(
+        input,
+        (
+            lines(parse_workflow),
+            line_ending,
+            line_ending,
+            lines(parse_rating),
+        )
+            .map(|(workflows, _, _, ratings)| (workflows, ratings)),
     )
-        .map(|(workflows, _, ratings, _)| (workflows, ratings))
-        .parse(input)
-        .unwrap()
 }
 
 fn parse_workflow(input: &mut &str) -> winnow::Result<Workflow> {
@@ -272,7 +275,7 @@ fn parse_rule(input: &mut &str) -> winnow::Result<Rule> {
     (
         parse_category,
         parse_operation,
-        parse_number,
+        parse_i64,
         ":",
         parse_destination,
     )
@@ -306,15 +309,7 @@ fn parse_operation(input: &mut &str) -> winnow::Result<Operation> {
 
 fn parse_rating(input: &mut &str) -> winnow::Result<PartRating> {
     (
-        "{x=",
-        parse_number,
-        ",m=",
-        parse_number,
-        ",a=",
-        parse_number,
-        ",s=",
-        parse_number,
-        "}",
+        "{x=", parse_i64, ",m=", parse_i64, ",a=", parse_i64, ",s=", parse_i64, "}",
     )
         .map(
             |(_, extremely_cool, _, musical, _, aerodynamic, _, shiny, _)| PartRating {
@@ -337,12 +332,6 @@ fn parse_category(input: &mut &str) -> winnow::Result<Category> {
     .parse_next(input)
 }
 
-fn parse_number(input: &mut &str) -> winnow::Result<i64> {
-    digit1
-        .map(|digits: &str| digits.parse::<i64>().unwrap())
-        .parse_next(input)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -367,7 +356,7 @@ hdj{m>838:A,pv}
 {x=2461,m=1339,a=466,s=291}
 {x=2127,m=1623,a=2188,s=1013}";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "19114");
         assert_eq!(part2.to_string(), "167409079868000");
@@ -1,62 +1,59 @@
 use std::{
     collections::{HashMap, VecDeque},
-    str::FromStr,
+    fmt::{Display, Write},
 };
 
+use log::warn;
 use num::Integer;
+use winnow::{
+    ascii::alpha1,
+    combinator::{opt, separated},
+    prelude::*,
+    token::one_of,
+};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut network = input.parse::<Network>().unwrap();
+use crate::common::{lines, parse_all, ParseError};
 
-    let untyped_module = &network.modules[network.untyped_index.0];
-    assert_eq!(untyped_module.inputs.len(), 1);
-    let input_index = untyped_module.inputs[0];
-    let input_module = &network.modules[input_index.0];
+/// Presses to try before giving up when the network isn't made of counters
+const SIMULATION_LIMIT: usize = 1_000_000;
 
-    let mut check_modules = input_module
-        .inputs
-        .iter()
-        .map(|input| (*input, None))
-        .collect::<Vec<_>>();
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let mut network = Network::parse(input)?;
 
-    for step in 1..=1000 {
-        network.press_button(step, &mut check_modules);
+    for _ in 0..1000 {
+        network.press_button(|_| {});
     }
     let part1 = network.low_pulse_count * network.high_pulse_count;
 
-    let mut step = 1001;
-    loop {
-        network.press_button(step, &mut check_modules);
-
-        if check_modules.iter().all(|(_, cycle)| cycle.is_some()) {
-            break;
+    network.reset();
+    let part2 = match network.counter_periods("rx") {
+        Ok(periods) => periods.into_iter().reduce(|a, b| a.lcm(&b)).unwrap(),
+        Err(error) => {
+            warn!("{error}, simulating instead");
+            network
+                .presses_until_low("rx", SIMULATION_LIMIT)
+                .map_err(|error| error.in_input(input))? as u64
         }
+    };
 
-        step += 1;
-    }
-    let part2 = check_modules
-        .into_iter()
-        .map(|(_, cycle)| cycle.unwrap())
-        .reduce(|a, b| a.lcm(&b))
-        .unwrap();
-
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
 struct Network {
     modules: Vec<Module>,
     broadcaster_index: ModuleIndex,
-    untyped_index: ModuleIndex,
+    presses: usize,
     low_pulse_count: u64,
     high_pulse_count: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct ModuleIndex(usize);
 
 #[derive(Debug)]
 struct Module {
+    name: String,
     module_type: ModuleType,
     inputs: Vec<ModuleIndex>,
     outputs: Vec<ModuleIndex>,
@@ -64,9 +61,14 @@ struct Module {
 
 #[derive(Debug)]
 enum ModuleType {
-    FlipFlop { on: bool },
-    Conjunction { last_pulses: Vec<Pulse> },
+    FlipFlop {
+        on: bool,
+    },
+    Conjunction {
+        last_pulses: Vec<Pulse>,
+    },
     Broadcast,
+    /// Only ever a destination, like `rx`
     Untyped,
 }
 
@@ -76,27 +78,78 @@ enum Pulse {
     Low,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PulseEvent {
+    source: ModuleIndex,
+    destination: ModuleIndex,
+    pulse: Pulse,
+}
+
+/// A chain of flip-flops counting button presses, with a conjunction (the hub) that fires once
+/// the count reaches `period` and resets the chain back to zero
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Counter {
+    /// Least significant first
+    bits: Vec<ModuleIndex>,
+    hub: ModuleIndex,
+    /// The hub's only output outside the chain
+    tap: ModuleIndex,
+    period: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CircuitError {
+    MissingModule(String),
+    /// The network around `module` doesn't have the shape the analysis relies on
+    Unexpected {
+        module: String,
+        reason: &'static str,
+    },
+    NoLowPulse {
+        module: String,
+        presses: usize,
+    },
+}
+
 impl Network {
-    fn press_button(&mut self, step: usize, check_modules: &mut [(ModuleIndex, Option<usize>)]) {
+    fn index(&self, name: &str) -> Option<ModuleIndex> {
+        self.modules
+            .iter()
+            .position(|module| module.name == name)
+            .map(ModuleIndex)
+    }
+
+    fn name(&self, index: ModuleIndex) -> &str {
+        &self.modules[index.0].name
+    }
+
+    fn module(&self, name: &str) -> Result<&Module, CircuitError> {
+        self.index(name)
+            .map(|index| &self.modules[index.0])
+            .ok_or_else(|| CircuitError::MissingModule(name.to_string()))
+    }
+
+    /// Presses the button once, calling `observe` for every pulse in the order they're sent
+    fn press_button(&mut self, mut observe: impl FnMut(&PulseEvent)) {
+        self.presses += 1;
         let mut pulse_queue = VecDeque::new();
-        pulse_queue.push_front((self.broadcaster_index, self.broadcaster_index, Pulse::Low));
-        while let Some((source, destination, pulse)) = pulse_queue.pop_front() {
+        pulse_queue.push_back(PulseEvent {
+            source: self.broadcaster_index,
+            destination: self.broadcaster_index,
+            pulse: Pulse::Low,
+        });
+        while let Some(event) = pulse_queue.pop_front() {
+            observe(&event);
+            let PulseEvent {
+                source,
+                destination,
+                pulse,
+            } = event;
             match pulse {
                 Pulse::High => self.high_pulse_count += 1,
                 Pulse::Low => self.low_pulse_count += 1,
             }
 
-            if !check_modules.is_empty() && pulse == Pulse::High {
-                for (check_module, value) in check_modules.iter_mut() {
-                    if value.is_some() {
-                        continue;
-                    }
-                    if source == *check_module {
-                        *value = Some(step);
-                    }
-                }
-            }
-
             let module = &mut self.modules[destination.0];
             let result_pulse = match &mut module.module_type {
                 ModuleType::FlipFlop { on } => {
@@ -111,10 +164,8 @@ impl Network {
                     let last_pulse_index = module
                         .inputs
                         .iter()
-                        .enumerate()
-                        .find(|(_, other_index)| **other_index == source)
-                        .unwrap()
-                        .0;
+                        .position(|other_index| *other_index == source)
+                        .unwrap();
                     last_pulses[last_pulse_index] = pulse;
 
                     let all_high = last_pulses.iter().all(|pulse| *pulse == Pulse::High);
@@ -126,13 +177,69 @@ impl Network {
 
             if let Some(result_pulse) = result_pulse {
                 for output in module.outputs.iter() {
-                    pulse_queue.push_back((destination, *output, result_pulse));
+                    pulse_queue.push_back(PulseEvent {
+                        source: destination,
+                        destination: *output,
+                        pulse: result_pulse,
+                    });
                 }
             }
         }
     }
 
-    fn _reset(&mut self) {
+    /// Presses the button once, returning every pulse sent starting with the button's own
+    #[allow(dead_code)]
+    fn trace(&mut self) -> Vec<PulseEvent> {
+        let mut events = Vec::new();
+        self.press_button(|event| events.push(*event));
+        events
+    }
+
+    #[allow(dead_code)]
+    fn format_event(&self, event: &PulseEvent) -> String {
+        let source = match event.source == event.destination {
+            true => "button",
+            false => self.name(event.source),
+        };
+        format!(
+            "{source} -{}-> {}",
+            event.pulse,
+            self.name(event.destination)
+        )
+    }
+
+    /// Whether the flip-flop `name` is on
+    #[allow(dead_code)]
+    fn flip_flop_on(&self, name: &str) -> Result<bool, CircuitError> {
+        let module = self.module(name)?;
+        match module.module_type {
+            ModuleType::FlipFlop { on } => Ok(on),
+            _ => Err(CircuitError::Unexpected {
+                module: name.to_string(),
+                reason: "not a flip-flop",
+            }),
+        }
+    }
+
+    /// The last pulse the conjunction `name` remembers from each of its inputs
+    #[allow(dead_code)]
+    fn conjunction_memory(&self, name: &str) -> Result<Vec<(&str, Pulse)>, CircuitError> {
+        let module = self.module(name)?;
+        match &module.module_type {
+            ModuleType::Conjunction { last_pulses } => Ok(module
+                .inputs
+                .iter()
+                .zip(last_pulses)
+                .map(|(input, pulse)| (self.name(*input), *pulse))
+                .collect()),
+            _ => Err(CircuitError::Unexpected {
+                module: name.to_string(),
+                reason: "not a conjunction",
+            }),
+        }
+    }
+
+    fn reset(&mut self) {
         for module in self.modules.iter_mut() {
             match &mut module.module_type {
                 ModuleType::FlipFlop { on } => {
@@ -147,61 +254,257 @@ impl Network {
                 ModuleType::Untyped => {}
             }
         }
+        self.presses = 0;
+        self.low_pulse_count = 0;
+        self.high_pulse_count = 0;
     }
-}
 
-impl FromStr for Network {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut partials = Vec::new();
-        for line in s.lines() {
-            let (name, outputs) = line.split_once(" -> ").unwrap();
-            let (name, module_type) = if let Some(name_stripped) = name.strip_prefix('%') {
-                (name_stripped, ModuleType::FlipFlop { on: false })
-            } else if let Some(name_stripped) = name.strip_prefix('&') {
-                (
-                    name_stripped,
-                    ModuleType::Conjunction {
-                        last_pulses: Vec::new(),
-                    },
-                )
-            } else {
-                (name, ModuleType::Broadcast)
+    /// Keeps pressing the button until `name` gets a low pulse, returning the total presses
+    fn presses_until_low(&mut self, name: &str, limit: usize) -> Result<usize, CircuitError> {
+        let target = self
+            .index(name)
+            .ok_or_else(|| CircuitError::MissingModule(name.to_string()))?;
+        for _ in 0..limit {
+            let mut received = false;
+            self.press_button(|event| {
+                received |= event.destination == target && event.pulse == Pulse::Low
+            });
+            if received {
+                return Ok(self.presses);
+            }
+        }
+        Err(CircuitError::NoLowPulse {
+            module: name.to_string(),
+            presses: self.presses,
+        })
+    }
+
+    /// Splits the network into the counters started by the broadcaster
+    fn counters(&self) -> Result<Vec<Counter>, CircuitError> {
+        self.modules[self.broadcaster_index.0]
+            .outputs
+            .iter()
+            .map(|start| self.counter(*start))
+            .collect()
+    }
+
+    fn counter(&self, start: ModuleIndex) -> Result<Counter, CircuitError> {
+        let unexpected = |index: ModuleIndex, reason| CircuitError::Unexpected {
+            module: self.name(index).to_string(),
+            reason,
+        };
+
+        let mut bits = Vec::new();
+        let mut hub = None;
+        let mut current = Some(start);
+        while let Some(index) = current {
+            if !matches!(
+                self.modules[index.0].module_type,
+                ModuleType::FlipFlop { .. }
+            ) {
+                return Err(unexpected(index, "counter bit isn't a flip-flop"));
+            }
+            if bits.contains(&index) {
+                return Err(unexpected(index, "counter loops back on itself"));
+            }
+            bits.push(index);
+
+            current = None;
+            for output in self.modules[index.0].outputs.iter() {
+                match self.modules[output.0].module_type {
+                    ModuleType::FlipFlop { .. } if current.is_none() => current = Some(*output),
+                    ModuleType::Conjunction { .. } if hub.is_none_or(|hub| hub == *output) => {
+                        hub = Some(*output)
+                    }
+                    _ => return Err(unexpected(index, "counter bit has unexpected outputs")),
+                }
+            }
+        }
+        let hub = hub.ok_or_else(|| unexpected(start, "counter has no hub"))?;
+
+        let feeds_hub = |bit: &ModuleIndex| self.modules[bit.0].outputs.contains(&hub);
+        if bits.len() > 63 || !feeds_hub(bits.last().unwrap()) {
+            return Err(unexpected(hub, "hub doesn't watch the top bit"));
+        }
+        for (i, bit) in bits.iter().enumerate() {
+            let previous = match i {
+                0 => self.broadcaster_index,
+                _ => bits[i - 1],
             };
+            let inputs = &self.modules[bit.0].inputs;
+            if inputs
+                .iter()
+                .any(|input| *input != previous && *input != hub)
+            {
+                return Err(unexpected(*bit, "counter bit has inputs from elsewhere"));
+            }
+        }
+
+        let hub_module = &self.modules[hub.0];
+        let mut watched = hub_module.inputs.clone();
+        watched.sort_by_key(|index| index.0);
+        let mut expected_watched = bits.iter().copied().filter(feeds_hub).collect::<Vec<_>>();
+        expected_watched.sort_by_key(|index| index.0);
+        if watched != expected_watched {
+            return Err(unexpected(hub, "hub has inputs from outside its counter"));
+        }
 
-            partials.push((name, module_type, outputs));
+        // When it fires the hub sets every bit it isn't watching, then carries out of the first
+        // bit so the whole counter rolls over to zero
+        let mut tap = None;
+        for output in hub_module.outputs.iter() {
+            if bits.contains(output) {
+                if feeds_hub(output) && *output != bits[0] {
+                    return Err(unexpected(hub, "hub resets a bit it watches"));
+                }
+            } else if tap.replace(*output).is_some() {
+                return Err(unexpected(
+                    hub,
+                    "hub has more than one output outside the counter",
+                ));
+            }
+        }
+        let reset = bits
+            .iter()
+            .filter(|bit| !feeds_hub(bit) || **bit == bits[0])
+            .all(|bit| hub_module.outputs.contains(bit));
+        if !reset {
+            return Err(unexpected(hub, "hub doesn't reset its counter"));
         }
+        let tap = tap.ok_or_else(|| unexpected(hub, "hub has no output outside the counter"))?;
 
-        let module_indexes = partials
+        let period = bits
             .iter()
             .enumerate()
-            .map(|(i, (name, _, _))| (name.to_string(), i))
+            .filter(|(_, bit)| feeds_hub(bit))
+            .map(|(i, _)| 1 << i)
+            .sum();
+
+        Ok(Counter {
+            bits,
+            hub,
+            tap,
+            period,
+        })
+    }
+
+    /// The periods of the counters that have to line up for `name` to get a low pulse
+    ///
+    /// Expects `name` to be fed by a single conjunction, whose inputs are each a counter's hub
+    /// inverted
+    fn counter_periods(&self, name: &str) -> Result<Vec<u64>, CircuitError> {
+        let target = self.module(name)?;
+        let unexpected = |module: &Module, reason| CircuitError::Unexpected {
+            module: module.name.clone(),
+            reason,
+        };
+
+        let [collector] = target.inputs[..] else {
+            return Err(unexpected(target, "expected a single input"));
+        };
+        let collector = &self.modules[collector.0];
+        if !matches!(collector.module_type, ModuleType::Conjunction { .. }) {
+            return Err(unexpected(collector, "expected a conjunction"));
+        }
+
+        let counters = self.counters()?;
+        let mut periods = Vec::new();
+        for tap in collector.inputs.iter() {
+            let tap_module = &self.modules[tap.0];
+            let is_inverter = matches!(
+                &tap_module.module_type,
+                ModuleType::Conjunction { last_pulses } if last_pulses.len() == 1
+            );
+            if !is_inverter || tap_module.outputs != [self.index(&collector.name).unwrap()] {
+                return Err(unexpected(tap_module, "expected an inverter"));
+            }
+            let counter = counters
+                .iter()
+                .find(|counter| counter.tap == *tap && counter.hub == tap_module.inputs[0])
+                .ok_or_else(|| unexpected(tap_module, "expected to be fed by a counter"))?;
+            periods.push(counter.period);
+        }
+        if periods.len() != counters.len() {
+            return Err(unexpected(
+                collector,
+                "expected every counter to be collected",
+            ));
+        }
+        Ok(periods)
+    }
+
+    /// The module graph in Graphviz's DOT language
+    #[allow(dead_code)]
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph network {\n");
+        for module in self.modules.iter() {
+            let shape = match module.module_type {
+                ModuleType::FlipFlop { .. } => "box",
+                ModuleType::Conjunction { .. } => "invtrapezium",
+                ModuleType::Broadcast => "doublecircle",
+                ModuleType::Untyped => "circle",
+            };
+            writeln!(dot, "    \"{}\" [shape={shape}];", module.name).unwrap();
+        }
+        for module in self.modules.iter() {
+            for output in module.outputs.iter() {
+                writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\";",
+                    module.name,
+                    self.name(*output)
+                )
+                .unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Network {
+    fn parse(input: &str) -> Result<Network, ParseError> {
+        let partials = parse_all(input, lines(parse_module))?;
+
+        let mut module_indexes = partials
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _, _))| (name.to_string(), ModuleIndex(i)))
             .collect::<HashMap<_, _>>();
-        let broadcaster_index = ModuleIndex(*module_indexes.get("broadcaster").unwrap());
-        let untyped_index = ModuleIndex(partials.len());
+        let broadcaster_index = *module_indexes
+            .get("broadcaster")
+            .ok_or_else(|| ParseError::new(input, 0, "no broadcaster"))?;
 
         let mut modules = Vec::new();
-        for (_, module_type, outputs) in partials {
+        let mut untyped = Vec::new();
+        for (name, module_type, outputs) in partials {
+            let outputs = outputs
+                .into_iter()
+                .map(|output_name| {
+                    let next_index = module_indexes.len();
+                    *module_indexes
+                        .entry(output_name.to_string())
+                        .or_insert_with(|| {
+                            untyped.push(output_name.to_string());
+                            ModuleIndex(next_index)
+                        })
+                })
+                .collect();
             modules.push(Module {
+                name: name.to_string(),
                 module_type,
                 inputs: Vec::new(),
-                outputs: outputs
-                    .split(", ")
-                    .map(|output_name| {
-                        module_indexes
-                            .get(output_name)
-                            .map(|i| ModuleIndex(*i))
-                            .unwrap_or(untyped_index)
-                    })
-                    .collect(),
+                outputs,
+            });
+        }
+        for name in untyped {
+            modules.push(Module {
+                name,
+                module_type: ModuleType::Untyped,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
             });
         }
-        modules.push(Module {
-            module_type: ModuleType::Untyped,
-            inputs: Vec::new(),
-            outputs: Vec::new(),
-        });
 
         for i in 0..modules.len() {
             let module = &modules[i];
@@ -224,13 +527,29 @@ impl FromStr for Network {
         Ok(Network {
             modules,
             broadcaster_index,
-            untyped_index,
+            presses: 0,
             low_pulse_count: 0,
             high_pulse_count: 0,
         })
     }
 }
 
+/// The name, type and outputs of one module
+type RawModule<'a> = (&'a str, ModuleType, Vec<&'a str>);
+
+fn parse_module<'a>(input: &mut &'a str) -> winnow::Result<RawModule<'a>> {
+    let module_type = opt(one_of(['%', '&'])).map(|prefix| match prefix {
+        Some('%') => ModuleType::FlipFlop { on: false },
+        Some(_) => ModuleType::Conjunction {
+            last_pulses: Vec::new(),
+        },
+        None => ModuleType::Broadcast,
+    });
+    (module_type, alpha1, " -> ", separated(1.., alpha1, ", "))
+        .map(|(module_type, name, _, outputs)| (name, module_type, outputs))
+        .parse_next(input)
+}
+
 impl Pulse {
     fn from_bool(value: bool) -> Pulse {
         match value {
@@ -240,20 +559,177 @@ impl Pulse {
     }
 }
 
+impl Display for Pulse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pulse::High => f.write_str("high"),
+            Pulse::Low => f.write_str("low"),
+        }
+    }
+}
+
+impl CircuitError {
+    /// As a `ParseError` at the line defining the module it's about, or the end of `input` if
+    /// no line does
+    fn in_input(self, input: &str) -> ParseError {
+        let (CircuitError::MissingModule(module)
+        | CircuitError::Unexpected { module, .. }
+        | CircuitError::NoLowPulse { module, .. }) = &self;
+        let mut offset = input.len();
+        let mut line_start = 0;
+        for line in input.split_inclusive('\n') {
+            let name = line.trim_start_matches(['%', '&']);
+            if name
+                .strip_prefix(module.as_str())
+                .is_some_and(|rest| rest.starts_with(" -> "))
+            {
+                offset = line_start;
+                break;
+            }
+            line_start += line.len();
+        }
+        ParseError::new(input, offset, self.to_string())
+    }
+}
+
+impl Display for CircuitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitError::MissingModule(name) => write!(f, "no module named {name}"),
+            CircuitError::Unexpected { module, reason } => write!(f, "{module}: {reason}"),
+            CircuitError::NoLowPulse { module, presses } => {
+                write!(f, "{module} got no low pulse in {presses} presses")
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output";
+
+    /// Two counters of periods 5 and 3 feeding `rx`
+    const COUNTERS: &str = "broadcaster -> a, d
+%a -> b, h
+%b -> c
+%c -> h
+&h -> a, b, t
+&t -> f
+%d -> e, g
+%e -> g
+&g -> d, u
+&u -> f
+&f -> rx";
+
     #[test]
     fn test_example() {
-        let input = "broadcaster -> a, b, c
+        let mut network = Network::parse(
+            "broadcaster -> a, b, c
 %a -> b
 %b -> c
 %c -> inv
-&inv -> a";
+&inv -> a",
+        )
+        .unwrap();
+        for _ in 0..1000 {
+            network.press_button(|_| {});
+        }
+        assert_eq!(network.low_pulse_count * network.high_pulse_count, 32000000);
+
+        let mut network = Network::parse(EXAMPLE).unwrap();
+        for _ in 0..1000 {
+            network.press_button(|_| {});
+        }
+        assert_eq!(network.low_pulse_count * network.high_pulse_count, 11687500);
+    }
+
+    #[test]
+    fn test_trace_and_state() {
+        let mut network = Network::parse(EXAMPLE).unwrap();
+        let trace = network.trace();
+        let trace = trace
+            .iter()
+            .map(|event| network.format_event(event))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trace,
+            [
+                "button -low-> broadcaster",
+                "broadcaster -low-> a",
+                "a -high-> inv",
+                "a -high-> con",
+                "inv -low-> b",
+                "con -high-> output",
+                "b -high-> con",
+                "con -low-> output",
+            ]
+        );
+        assert_eq!(network.flip_flop_on("a"), Ok(true));
+        assert_eq!(network.flip_flop_on("b"), Ok(true));
+        assert_eq!(
+            network.conjunction_memory("con"),
+            Ok(vec![("a", Pulse::High), ("b", Pulse::High)])
+        );
+        assert!(network.flip_flop_on("con").is_err());
+
+        network.reset();
+        assert_eq!(network.flip_flop_on("a"), Ok(false));
+        assert_eq!(network.presses, 0);
+        assert_eq!(network.trace().len(), trace.len());
+    }
 
-        let (part1, _) = solve(input);
+    #[test]
+    fn test_counters() {
+        let mut network = Network::parse(COUNTERS).unwrap();
+        assert_eq!(network.counter_periods("rx"), Ok(vec![5, 3]));
+        assert_eq!(network.presses_until_low("rx", 100), Ok(15));
+
+        for counter in network.counters().unwrap() {
+            let tap = network.name(counter.tap).to_string();
+            network.reset();
+            assert_eq!(
+                network.presses_until_low(&tap, 100),
+                Ok(counter.period as usize)
+            );
+        }
+
+        let network = Network::parse(EXAMPLE).unwrap();
+        assert_eq!(
+            network.counter_periods("rx"),
+            Err(CircuitError::MissingModule("rx".to_string()))
+        );
+        assert!(network.counter_periods("output").is_err());
+    }
 
-        assert_eq!(part1.to_string(), "32000000");
+    #[test]
+    fn test_errors() {
+        assert!(Network::parse("broadcaster -> a\n%a b").is_err());
+        let error = Network::parse("%a -> b").unwrap_err();
+        assert!(error.to_string().contains("no broadcaster"), "{error}");
+
+        let mut network = Network::parse(EXAMPLE).unwrap();
+        let error = network.presses_until_low("inv", 1).unwrap_err();
+        let error = error.in_input(EXAMPLE).to_string();
+        assert!(
+            error.contains("inv got no low pulse in 1 presses at line 3"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let network = Network::parse(EXAMPLE).unwrap();
+        let dot = network.to_dot();
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"con\" [shape=invtrapezium];\n"));
+        assert!(dot.contains("    \"output\" [shape=circle];\n"));
+        assert!(dot.contains("    \"a\" -> \"inv\";\n"));
+        assert_eq!(dot.matches("->").count(), 6);
     }
 }
@@ -1,101 +1,231 @@
-use crate::common::Vector3;
+use winnow::prelude::*;
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut blocks = parse_input(input);
-    blocks.sort_by_key(|block| block.position.z);
+use crate::common::{lines, parse_all, parse_i64, ParseError, Vector3};
 
-    for i in 0..blocks.len() {
-        let block = &blocks[i];
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let stack = Stack::settle(&parse_all(input, lines(parse_brick))?);
+    let chain_reactions = stack.chain_reactions();
 
-        let falls_to_z = (0..i)
-            .map(|j| &blocks[j])
-            .find(|other| block.is_above(other))
-            .map(|block| block.position.z + block.size.z + 1)
-            .unwrap_or(1);
+    let part1 = chain_reactions.iter().filter(|count| **count == 0).count();
+    let part2 = chain_reactions.iter().sum::<usize>();
 
-        blocks[i].position.z = falls_to_z;
-    }
-
-    blocks.sort_by_key(|block| block.position.z);
-    dbg!(&blocks);
+    Ok((part1, part2))
+}
 
-    let above_counts = blocks
-        .iter()
-        .enumerate()
-        .map(|(i, block)| {
-            blocks
-                .iter()
-                .enumerate()
-                .filter(|(j, other)| i != *j && other.is_above(block))
-                .count()
-        })
-        .collect::<Vec<_>>();
-    dbg!(&above_counts);
+/// Inclusive corners, with `min` no bigger than `max` on any axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Brick {
+    min: Vector3,
+    max: Vector3,
+}
 
-    let part1 = above_counts.iter().filter(|count| **count == 1).count();
+/// Bricks after they've all fallen as far as they can, and which rest on which
+#[derive(Debug, Clone)]
+struct Stack {
+    /// In the same order as they were given
+    bricks: Vec<Brick>,
+    /// What each brick rests on, with nothing meaning the ground
+    below: Vec<Vec<usize>>,
+    /// Bottom to top, so every brick comes after everything it rests on
+    order: Vec<usize>,
+}
 
-    (part1, "")
+/// Which way to look at the stack from
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// Looking along `y`, so `x` goes across
+    X,
+    /// Looking along `x`, so `y` goes across
+    Y,
 }
 
-#[derive(Debug)]
-struct Block {
-    position: Vector3,
-    size: Vector3,
+impl Brick {
+    fn new(a: Vector3, b: Vector3) -> Brick {
+        Brick {
+            min: Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// Each `(x, y)` the brick is over
+    fn footprint(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.min.x..=self.max.x)
+            .flat_map(move |x| (self.min.y..=self.max.y).map(move |y| (x as usize, y as usize)))
+    }
 }
 
-impl Block {
-    fn new(position_a: Vector3, position_b: Vector3) -> Block {
-        let position = Vector3::new(
-            position_a.x.min(position_b.x),
-            position_a.y.min(position_b.y),
-            position_a.z.min(position_b.z),
-        );
+impl Stack {
+    /// Drops bricks lowest first, keeping the top height of each column and the brick there
+    fn settle(bricks: &[Brick]) -> Stack {
+        let mut order = (0..bricks.len()).collect::<Vec<_>>();
+        order.sort_by_key(|index| bricks[*index].min.z);
+        let width = bricks
+            .iter()
+            .map(|brick| brick.max.x + 1)
+            .max()
+            .unwrap_or(0) as usize;
+        let depth = bricks
+            .iter()
+            .map(|brick| brick.max.y + 1)
+            .max()
+            .unwrap_or(0) as usize;
 
-        let size = Vector3::new(
-            (position_a.x - position_b.x).abs(),
-            (position_a.y - position_b.y).abs(),
-            (position_a.z - position_b.z).abs(),
-        );
+        let mut tops = vec![vec![(0, None); depth]; width];
+        let mut settled = bricks.to_vec();
+        let mut below = vec![Vec::new(); bricks.len()];
+        for &index in order.iter() {
+            let brick = &mut settled[index];
+            let floor = brick.footprint().map(|(x, y)| tops[x][y].0).max().unwrap();
+            brick.max.z -= brick.min.z - floor - 1;
+            brick.min.z = floor + 1;
+            for (x, y) in brick.footprint() {
+                if let (height, Some(other)) = tops[x][y] {
+                    if height == floor && !below[index].contains(&other) {
+                        below[index].push(other);
+                    }
+                }
+                tops[x][y] = (brick.max.z, Some(index));
+            }
+        }
 
-        Block { position, size }
+        Stack {
+            bricks: settled,
+            below,
+            order,
+        }
     }
 
-    fn is_above(&self, other: &Block) -> bool {
-        self.position.x <= other.position.x + other.size.x
-            && self.position.x + self.size.x >= other.position.x
-            && self.position.y <= other.position.y + other.size.y
-            && self.position.y + self.size.y >= other.position.y
-            && self.position.z > other.position.z + other.size.z
+    /// The brick every way down to the ground from each brick goes through, or `None` if
+    /// that's only the ground. Support only goes upwards, so each brick's dominator is the
+    /// lowest common ancestor of what it rests on in the tree built so far, which is found by
+    /// jumping up in powers of two
+    fn dominators(&self) -> Vec<Option<usize>> {
+        let ground = self.bricks.len();
+        let levels = (usize::BITS - ground.leading_zeros()) as usize + 1;
+        // Each brick's ancestors 1, 2, 4 and so on steps up the tree, then the ground's
+        let mut jumps = vec![Vec::new(); ground + 1];
+        jumps[ground] = vec![ground; levels];
+        let mut depth = vec![0; ground + 1];
+
+        let common_ancestor =
+            |jumps: &[Vec<usize>], depth: &[usize], mut a: usize, mut b: usize| {
+                if depth[a] < depth[b] {
+                    std::mem::swap(&mut a, &mut b);
+                }
+                for level in (0..levels).rev() {
+                    if depth[a] - depth[b] >= 1 << level {
+                        a = jumps[a][level];
+                    }
+                }
+                if a == b {
+                    return a;
+                }
+                for level in (0..levels).rev() {
+                    if jumps[a][level] != jumps[b][level] {
+                        a = jumps[a][level];
+                        b = jumps[b][level];
+                    }
+                }
+                jumps[a][0]
+            };
+
+        let mut dominators = vec![None; ground];
+        for &brick in self.order.iter() {
+            let parent = self.below[brick]
+                .iter()
+                .copied()
+                .reduce(|a, b| common_ancestor(&jumps, &depth, a, b))
+                .unwrap_or(ground);
+            dominators[brick] = (parent != ground).then_some(parent);
+            depth[brick] = depth[parent] + 1;
+            let mut ancestors = vec![parent];
+            for level in 1..levels {
+                ancestors.push(jumps[ancestors[level - 1]][level - 1]);
+            }
+            jumps[brick] = ancestors;
+        }
+        dominators
+    }
+
+    /// How many other bricks fall if each brick is taken away. Those are exactly the ones it
+    /// dominates, so it's the size of its subtree of the dominator tree, less itself
+    fn chain_reactions(&self) -> Vec<usize> {
+        let dominators = self.dominators();
+        let mut sizes = vec![1; self.bricks.len()];
+        for &brick in self.order.iter().rev() {
+            if let Some(dominator) = dominators[brick] {
+                sizes[dominator] += sizes[brick];
+            }
+        }
+        sizes.into_iter().map(|size| size - 1).collect()
     }
 }
 
-fn parse_input(input: &str) -> Vec<Block> {
-    input
-        .lines()
-        .map(|line| {
-            let (position_a, position_b) = line.split_once('~').unwrap();
-            Block::new(parse_vector3(position_a), parse_vector3(position_b))
-        })
-        .collect()
+/// The stack from one side like the puzzle draws it, with bricks lettered in order and `?`
+/// where more than one is in line
+#[allow(dead_code)]
+fn render(bricks: &[Brick], side: Side) -> String {
+    let across = |point: Vector3| match side {
+        Side::X => point.x as usize,
+        Side::Y => point.y as usize,
+    };
+    let width = bricks.iter().map(|b| across(b.max) + 1).max().unwrap_or(0);
+    let top = bricks.iter().map(|b| b.max.z as usize).max().unwrap_or(0);
+
+    // `Some(None)` where there's more than one brick
+    let mut cells = vec![vec![None::<Option<usize>>; width]; top + 1];
+    for (index, brick) in bricks.iter().enumerate() {
+        for row in cells[brick.min.z as usize..=brick.max.z as usize].iter_mut() {
+            for cell in row[across(brick.min)..=across(brick.max)].iter_mut() {
+                *cell = match cell {
+                    None => Some(Some(index)),
+                    Some(_) => Some(None),
+                };
+            }
+        }
+    }
+
+    let label = match side {
+        Side::X => 'x',
+        Side::Y => 'y',
+    };
+    let mut lines = vec![
+        format!("{}{label}", " ".repeat(width / 2)),
+        (0..width)
+            .map(|a| char::from(b'0' + (a % 10) as u8))
+            .collect(),
+    ];
+    for z in (1..=top).rev() {
+        let row = cells[z].iter().map(|cell| match cell {
+            None => '.',
+            Some(Some(index)) => char::from(b'A' + (index % 26) as u8),
+            Some(None) => '?',
+        });
+        let axis = if z == top.div_ceil(2) { " z" } else { "" };
+        lines.push(format!("{} {z}{axis}", row.collect::<String>()));
+    }
+    lines.push(format!("{} 0", "-".repeat(width)));
+    lines.join("\n")
 }
 
-fn parse_vector3(block_str: &str) -> Vector3 {
-    let mut parts = block_str.split(',').map(|v| v.parse::<i64>().unwrap());
+fn parse_brick(input: &mut &str) -> winnow::Result<Brick> {
+    (parse_vector3, '~', parse_vector3)
+        .map(|(a, _, b)| Brick::new(a, b))
+        .parse_next(input)
+}
 
-    Vector3::new(
-        parts.next().unwrap(),
-        parts.next().unwrap(),
-        parts.next().unwrap(),
-    )
+fn parse_vector3(input: &mut &str) -> winnow::Result<Vector3> {
+    (parse_i64, ',', parse_i64, ',', parse_i64)
+        .map(|(x, _, y, _, z)| Vector3::new(x, y, z))
+        .parse_next(input)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_example() {
-        let input = "1,0,1~1,2,1
+    const EXAMPLE: &str = "1,0,1~1,2,1
 0,0,2~2,0,2
 0,2,3~2,2,3
 0,0,4~0,2,4
@@ -103,8 +233,103 @@ mod tests {
 0,1,6~2,1,6
 1,1,8~1,1,9";
 
-        let (part1, _) = solve(input);
+    /// Bricks in a 10 by 10 area, anywhere up to `height`
+    fn generate(count: usize, height: i64, mut seed: u64) -> Vec<Brick> {
+        let mut random = move |below: i64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % below as u64) as i64
+        };
+        (0..count)
+            .map(|_| {
+                let start = Vector3::new(random(10), random(10), random(height) + 1);
+                let mut end = start;
+                match random(3) {
+                    0 => end.x = (start.x + random(4)).min(9),
+                    1 => end.y = (start.y + random(4)).min(9),
+                    _ => end.z += random(4),
+                }
+                Brick::new(start, end)
+            })
+            .collect()
+    }
+
+    /// Takes each brick away in turn and lets everything above fall
+    fn brute_force(stack: &Stack) -> Vec<usize> {
+        (0..stack.bricks.len())
+            .map(|removed| {
+                let mut fallen = vec![false; stack.bricks.len()];
+                fallen[removed] = true;
+                for &brick in stack.order.iter() {
+                    let below = &stack.below[brick];
+                    if !below.is_empty() && below.iter().all(|other| fallen[*other]) {
+                        fallen[brick] = true;
+                    }
+                }
+                fallen.iter().filter(|fallen| **fallen).count() - 1
+            })
+            .collect()
+    }
 
+    #[test]
+    fn test_example() {
+        let (part1, part2) = solve(EXAMPLE).unwrap();
         assert_eq!(part1.to_string(), "5");
+        assert_eq!(part2.to_string(), "7");
+        assert!(parse_all("1,0,1~1,2", lines(parse_brick)).is_err());
+
+        let stack = Stack::settle(&parse_all(EXAMPLE, lines(parse_brick)).unwrap());
+        assert_eq!(
+            render(&stack.bricks, Side::X),
+            " x
+012
+.G. 6
+.G. 5
+FFF 4
+D.E 3 z
+??? 2
+.A. 1
+--- 0"
+        );
+        assert_eq!(
+            render(&stack.bricks, Side::Y),
+            " y
+012
+.G. 6
+.G. 5
+.F. 4
+??? 3 z
+B.C 2
+AAA 1
+--- 0"
+        );
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        for seed in 1..=5 {
+            let stack = Stack::settle(&generate(300, 200, seed));
+            assert_eq!(stack.chain_reactions(), brute_force(&stack));
+        }
+    }
+
+    #[test]
+    fn test_many_bricks() {
+        let stack = Stack::settle(&generate(200_000, 500_000, 7));
+        let chain_reactions = stack.chain_reactions();
+        // Safe to take away exactly when everything on top has something else holding it up
+        let mut above = vec![Vec::new(); stack.bricks.len()];
+        for (brick, below) in stack.below.iter().enumerate() {
+            for other in below.iter() {
+                above[*other].push(brick);
+            }
+        }
+        for (brick, count) in chain_reactions.iter().enumerate() {
+            let safe = above[brick]
+                .iter()
+                .all(|other| stack.below[*other].len() > 1);
+            assert_eq!(*count == 0, safe);
+        }
     }
 }
@@ -1,15 +1,15 @@
 use std::ops::Range;
 
-use nom::{
-    bytes::complete::tag,
-    character::complete::{alpha1, digit1},
-    combinator::{all_consuming, map},
-    multi::{many0, separated_list1},
-    IResult, Parser,
+use winnow::{
+    ascii::{alpha1, line_ending},
+    combinator::{preceded, separated},
+    prelude::*,
 };
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let almanac = parse_almanac(input).unwrap().1;
+use crate::common::{blocks, lines, parse_all, parse_i64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let almanac = parse_almanac(input)?;
 
     let mut values = almanac.seeds.clone();
     for almanac_map in almanac.maps.iter() {
@@ -30,7 +30,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
     }
     let part2 = ranges.into_iter().map(|range| range.start).min().unwrap();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 struct Almanac {
@@ -112,57 +112,37 @@ fn intersect(input: Range<i64>, against: Range<i64>) -> Option<(Range<i64>, Vec<
     Some((overlap, remaining))
 }
 
-fn parse_almanac(input: &str) -> IResult<&str, Almanac> {
-    all_consuming(map(
-        (
-            parse_seeds,
-            tag("\n\n"),
-            separated_list1(tag("\n\n"), parse_map),
-            many0(tag("\n")),
-        ),
-        |(seeds, _, maps, _)| Almanac { seeds, maps },
-    ))
-    .parse(input)
+fn parse_almanac(input: &str) -> Result<Almanac, ParseError> {
+    let sections = (parse_seeds, line_ending, line_ending, blocks(parse_map));
+    parse_all(
+        input,
+        sections.map(|(seeds, _, _, maps)| Almanac { seeds, maps }),
+    )
 }
 
-fn parse_seeds(input: &str) -> IResult<&str, Vec<i64>> {
-    map(
-        (tag("seeds: "), separated_list1(tag(" "), digit1)),
-        |(_, digit_list)| {
-            digit_list
-                .into_iter()
-                .map(|digits: &str| digits.parse::<i64>().unwrap())
-                .collect::<Vec<_>>()
-        },
-    )
-    .parse(input)
+fn parse_seeds(input: &mut &str) -> winnow::Result<Vec<i64>> {
+    preceded("seeds: ", separated(1.., parse_i64, ' ')).parse_next(input)
 }
 
-fn parse_map(input: &str) -> IResult<&str, AlmanacMap> {
-    map(
-        (
-            alpha1::<&str, _>,
-            tag("-to-"),
-            alpha1,
-            tag(" map:\n"),
-            separated_list1(tag("\n"), (digit1, tag(" "), digit1, tag(" "), digit1)),
-        ),
-        |(source_name, _, destination_name, _, entries)| AlmanacMap {
-            _source_name: source_name.to_string(),
-            _destination_name: destination_name.to_string(),
-            entries: entries
-                .into_iter()
-                .map(
-                    |(destination_start, _, source_start, _, length)| AlmanacMapEntry {
-                        source_start: source_start.parse::<i64>().unwrap(),
-                        destination_start: destination_start.parse::<i64>().unwrap(),
-                        length: length.parse::<i64>().unwrap(),
-                    },
-                )
-                .collect(),
+fn parse_map(input: &mut &str) -> winnow::Result<AlmanacMap> {
+    let entry = (parse_i64, ' ', parse_i64, ' ', parse_i64).map(
+        |(destination_start, _, source_start, _, length)| AlmanacMapEntry {
+            source_start,
+            destination_start,
+            length,
         },
-    )
-    .parse(input)
+    );
+    (alpha1, "-to-", alpha1, " map:", line_ending, lines(entry))
+        .map(
+            |(source_name, _, destination_name, _, _, entries): (&str, _, &str, _, _, _)| {
+                AlmanacMap {
+                    _source_name: source_name.to_string(),
+                    _destination_name: destination_name.to_string(),
+                    entries,
+                }
+            },
+        )
+        .parse_next(input)
 }
 
 #[cfg(test)]
@@ -205,7 +185,7 @@ humidity-to-location map:
 60 56 37
 56 93 4";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "35");
         assert_eq!(part2.to_string(), "46");
@@ -5,15 +5,16 @@ use std::{
 };
 
 use winnow::{
-    ascii::{dec_int, multispace0},
-    combinator::{empty, fail, repeat, separated},
+    combinator::{empty, fail, repeat},
     dispatch,
     prelude::*,
     token::any,
 };
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let mut hands = parse_input(input);
+use crate::common::{expected, lines, parse_all, parse_i64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let mut hands = parse_input(input)?;
 
     hands.sort_by(|a, b| {
         a.hand_type()
@@ -45,7 +46,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         .map(|(i, hand)| (i as i64 + 1) * hand.bid)
         .sum::<i64>();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -188,22 +189,13 @@ fn joker_cmp(a: &Card, b: &Card) -> Ordering {
     }
 }
 
-fn parse_input(input: &str) -> Vec<Hand> {
-    (
-        separated(1.., (repeat(5..=5, parse_card), " ", dec_int), "\n"),
-        multispace0,
-    )
-        .map(|(lines, _): (Vec<_>, _)| {
-            lines
-                .into_iter()
-                .map(|(cards, _, bid): (Vec<_>, _, _)| Hand {
-                    cards: cards.try_into().unwrap(),
-                    bid,
-                })
-                .collect()
-        })
-        .parse(input)
-        .unwrap()
+fn parse_input(input: &str) -> Result<Vec<Hand>, ParseError> {
+    let hand =
+        (repeat(5..=5, parse_card), " ", parse_i64).map(|(cards, _, bid): (Vec<_>, _, _)| Hand {
+            cards: cards.try_into().unwrap(),
+            bid,
+        });
+    parse_all(input, lines(hand))
 }
 
 fn parse_card(input: &mut &str) -> winnow::Result<Card> {
@@ -223,6 +215,7 @@ fn parse_card(input: &mut &str) -> winnow::Result<Card> {
     '2' => empty.value(Card::Two),
     _ => fail,
     }
+    .context(expected("card"))
     .parse_next(input)
 }
 
@@ -238,7 +231,7 @@ KK677 28
 KTJJT 220
 QQQJA 483";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "6440");
         assert_eq!(part2.to_string(), "5905");
@@ -1,15 +1,17 @@
 use std::collections::HashMap;
 
-use num::Integer;
 use winnow::{
-    ascii::{alphanumeric1, multispace0},
-    combinator::{empty, fail, repeat, separated},
+    ascii::{alphanumeric1, line_ending},
+    combinator::{empty, fail, repeat},
+    dispatch,
     prelude::*,
+    token::any,
 };
-use winnow::{dispatch, token::any};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let (instructions, map) = parse_input(input);
+use crate::common::{expected, lines, parse_all, EventuallyPeriodic, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let (instructions, map) = parse_input(input)?;
 
     let mut position = map.root();
     let mut part1 = 0;
@@ -21,23 +23,15 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         position = position.apply(*instruction);
     }
 
-    let part2 = map
+    // Each ghost's path eventually loops, so the answer is where all the loops line up
+    let paths = map
         .all_roots()
         .into_iter()
-        .map(|root| {
-            let mut position = root;
-            let mut iterable = instructions.iter().cycle().enumerate();
-            loop {
-                let (moves, instruction) = iterable.next().unwrap();
-                if position.ends_with_z() {
-                    break moves;
-                }
-                position = position.apply(*instruction);
-            }
-        })
-        .fold(1, |acc, other| acc.lcm(&other));
+        .map(|root| root.ghost_path(&instructions))
+        .collect::<Vec<_>>();
+    let part2 = EventuallyPeriodic::first_common_hit(&paths).unwrap();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -159,18 +153,47 @@ impl<'a> MapPosition<'a> {
     fn ends_with_z(&self) -> bool {
         self.map.nodes[self.index].ends_with_z
     }
+
+    /// The moves after which this is on a node ending in Z, found by following the
+    /// instructions until a node comes round at the same point in them
+    fn ghost_path(self, instructions: &[Instruction]) -> EventuallyPeriodic {
+        let mut seen = vec![None; self.map.nodes.len() * instructions.len()];
+        let mut hits = Vec::new();
+        let mut position = self;
+        for (moves, (step, instruction)) in instructions.iter().enumerate().cycle().enumerate() {
+            let moves = moves as i128;
+            let state = position.index * instructions.len() + step;
+            if let Some(cycle_start) = seen[state] {
+                let (prefix_hits, cycle_hits) =
+                    hits.into_iter().partition(|hit| *hit < cycle_start);
+                return EventuallyPeriodic::new(
+                    prefix_hits,
+                    cycle_start,
+                    moves - cycle_start,
+                    cycle_hits,
+                );
+            }
+            seen[state] = Some(moves);
+            if position.ends_with_z() {
+                hits.push(moves);
+            }
+            position = position.apply(*instruction);
+        }
+        unreachable!()
+    }
 }
 
-fn parse_input(input: &str) -> (Vec<Instruction>, Map) {
-    (
-        parse_instructions,
-        "\n\n",
-        separated(1.., parse_node, "\n"),
-        multispace0,
+fn parse_input(input: &str) -> Result<(Vec<Instruction>, Map), ParseError> {
+    parse_all(
+        input,
+        (
+            parse_instructions,
+            line_ending,
+            line_ending,
+            lines(parse_node),
+        )
+            .map(|(instructions, _, _, nodes)| (instructions, Map::new(nodes))),
     )
-        .map(|(instructions, _, nodes, _)| (instructions, Map::new(nodes)))
-        .parse(input)
-        .unwrap()
 }
 
 fn parse_instructions(input: &mut &str) -> winnow::Result<Vec<Instruction>> {
@@ -180,7 +203,8 @@ fn parse_instructions(input: &mut &str) -> winnow::Result<Vec<Instruction>> {
             'R' => empty.value(Instruction::Right),
             'L' => empty.value(Instruction::Left),
             _ => fail,
-        },
+        }
+        .context(expected("instruction")),
     )
     .parse_next(input)
 }
@@ -211,7 +235,7 @@ BBB = (AAA, ZZZ)
 ZZZ = (ZZZ, ZZZ)
 ";
 
-        let (part1, _) = solve(input);
+        let (part1, _) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "6");
 
@@ -229,7 +253,7 @@ ZZZ = (ZZZ, ZZZ)
 AAA = (ZZZ, ZZZ)
 ";
 
-        let (_, part2) = solve(input);
+        let (_, part2) = solve(input).unwrap();
 
         assert_eq!(part2.to_string(), "6");
     }
@@ -1,12 +1,10 @@
 use itertools::Itertools;
-use winnow::{
-    ascii::{digit1, multispace0, multispace1},
-    combinator::separated,
-    Parser,
-};
+use winnow::{ascii::space1, Parser};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = parse_input(input);
+use crate::common::{lines, parse_all, parse_u32, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = parse_input(input)?;
 
     let left_sorted = input
         .pairs
@@ -67,32 +65,23 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 struct Input {
     pairs: Vec<(u32, u32)>,
 }
 
-fn parse_input(input: &str) -> Input {
-    (separated(1.., parse_pair, "\n"), multispace0)
-        .map(|(pairs, _)| Input { pairs })
-        .parse(input)
-        .unwrap()
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    parse_all(input, lines(parse_pair).map(|pairs| Input { pairs }))
 }
 
 fn parse_pair(input: &mut &str) -> winnow::Result<(u32, u32)> {
-    (parse_number, multispace1, parse_number)
+    (parse_u32, space1, parse_u32)
         .map(|(a, _, b)| (a, b))
         .parse_next(input)
 }
 
-fn parse_number(input: &mut &str) -> winnow::Result<u32> {
-    digit1
-        .map(|digits: &str| digits.parse::<u32>().unwrap())
-        .parse_next(input)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,7 +94,7 @@ mod tests {
 1   3
 3   9
 3   3"#;
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "11");
         assert_eq!(part2.to_string(), "31");
@@ -1,9 +1,9 @@
-use winnow::{ascii::multispace0, combinator::separated, Parser};
+use winnow::{combinator::separated, Parser};
 
-use crate::common::parse_u32;
+use crate::common::{lines, parse_all, parse_u32, ParseError};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let part1 = input
         .reports
@@ -17,7 +17,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         .filter(|report| report.is_safe2())
         .count();
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 struct Input {
@@ -25,11 +25,8 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Input {
-        (separated(1.., Report::parse, "\n"), multispace0)
-            .map(|(reports, _)| Input { reports })
-            .parse(input)
-            .unwrap()
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        parse_all(input, lines(Report::parse).map(|reports| Input { reports }))
     }
 }
 
@@ -122,7 +119,7 @@ mod tests {
 8 6 4 4 1
 1 3 6 7 9";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "2");
         assert_eq!(part2.to_string(), "4");
@@ -4,10 +4,10 @@ use winnow::{
     Parser,
 };
 
-use crate::common::parse_u32;
+use crate::common::{parse_all, parse_u32, ParseError};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let instructions = parse_instructions(input);
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let instructions = parse_instructions(input)?;
     // println!("{:?}", instructions);
 
     let part1 = instructions
@@ -33,7 +33,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -59,8 +59,8 @@ impl Instruction {
     }
 }
 
-fn parse_instructions(input: &str) -> Vec<Instruction> {
-    repeat(
+fn parse_instructions(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    let instructions = repeat(
         0..,
         alt((
             Instruction::parse_mul.map(Some),
@@ -69,9 +69,8 @@ fn parse_instructions(input: &str) -> Vec<Instruction> {
             any.map(|_| None),
         )),
     )
-    .map(|instructions: Vec<Option<Instruction>>| instructions.into_iter().flatten().collect())
-    .parse(input)
-    .unwrap()
+    .map(|instructions: Vec<Option<Instruction>>| instructions.into_iter().flatten().collect());
+    parse_all(input, instructions)
 }
 
 #[cfg(test)]
@@ -82,7 +81,7 @@ mod tests {
     fn test_known() {
         let input = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "161");
         assert_eq!(part2.to_string(), "48");
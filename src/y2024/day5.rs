@@ -3,12 +3,12 @@ use std::{
     collections::{HashMap, HashSet},
 };
 
-use winnow::{combinator::separated, Parser};
+use winnow::{ascii::line_ending, combinator::separated, Parser};
 
-use crate::common::parse_u32;
+use crate::common::{lines, parse_all, parse_u32, ParseError};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut before_map = HashMap::<u32, HashSet<u32>>::new();
     for rule in &input.rules {
@@ -49,7 +49,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1, part2)
+    Ok((part1, part2))
 }
 
 struct Input {
@@ -58,15 +58,17 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Input {
-        (
-            separated(1.., Rule::parse, '\n'),
-            "\n\n",
-            separated(1.., Update::parse, '\n'),
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        let sections = (
+            lines(Rule::parse),
+            line_ending,
+            line_ending,
+            lines(Update::parse),
+        );
+        parse_all(
+            input,
+            sections.map(|(rules, _, _, updates)| Input { rules, updates }),
         )
-            .map(|(rules, _, updates)| Input { rules, updates })
-            .parse(input.trim())
-            .unwrap()
     }
 }
 
@@ -130,7 +132,7 @@ mod tests {
 61,13,29
 97,13,75,29,47";
 
-        let (part1, part2) = solve(input);
+        let (part1, part2) = solve(input).unwrap();
 
         assert_eq!(part1.to_string(), "143");
         assert_eq!(part2.to_string(), "123");
@@ -1,16 +1,12 @@
 use std::{cmp::Ordering, fmt::Display};
 
 use num::traits::Euclid;
-use winnow::{
-    ascii::dec_int,
-    combinator::separated,
-    error::{ContextError, ParserError},
-    token::one_of,
-    Parser,
-};
+use winnow::{ascii::dec_int, error::ParserError, token::one_of, Parser};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+use crate::common::{lines, parse_all, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut position = 50;
     let mut part1_count = 0;
@@ -51,7 +47,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         // println!("{rotation} {position} {div} {rem} {part2_count}");
     }
 
-    (part1_count.to_string(), part2_count.to_string())
+    Ok((part1_count.to_string(), part2_count.to_string()))
 }
 
 struct Input {
@@ -59,11 +55,11 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Self {
-        separated(1.., Rotation::parse::<ContextError<&str>>, '\n')
-            .parse(input.trim_end())
-            .map(|rotations| Self { rotations })
-            .unwrap()
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        parse_all(
+            input,
+            lines(Rotation::parse).map(|rotations| Self { rotations }),
+        )
     }
 }
 
@@ -109,13 +105,13 @@ L1
 L99
 R14
 L82";
-        let (result1, result2) = solve(input);
+        let (result1, result2) = solve(input).unwrap();
         assert_eq!(result1.to_string(), "3");
         assert_eq!(result2.to_string(), "6");
 
-        assert_eq!(solve("R1000").1.to_string(), "10");
-        assert_eq!(solve("R950").1.to_string(), "10");
-        assert_eq!(solve("R50\nL1").1.to_string(), "1");
-        assert_eq!(solve("L50\nR1").1.to_string(), "1");
+        assert_eq!(solve("R1000").unwrap().1.to_string(), "10");
+        assert_eq!(solve("R950").unwrap().1.to_string(), "10");
+        assert_eq!(solve("R50\nL1").unwrap().1.to_string(), "1");
+        assert_eq!(solve("L50\nR1").unwrap().1.to_string(), "1");
     }
 }
@@ -1,13 +1,10 @@
 use num::Integer;
-use winnow::{
-    ascii::dec_int,
-    combinator::separated,
-    error::{ContextError, ParserError},
-    Parser,
-};
+use winnow::{ascii::dec_int, combinator::separated, error::ParserError, Parser};
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+use crate::common::{parse_all, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut part1 = 0_i64;
     let mut part2 = 0_i64;
@@ -60,7 +57,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1.to_string(), part2.to_string())
+    Ok((part1.to_string(), part2.to_string()))
 }
 
 fn all_digits_same(number: i64, digits: u32) -> bool {
@@ -77,11 +74,9 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Self {
-        separated(1.., Range::parse::<ContextError<&str>>, ',')
-            .parse(input.trim_end())
-            .map(|ranges| Self { ranges })
-            .unwrap()
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let ranges = separated(1.., Range::parse, ',');
+        parse_all(input, ranges.map(|ranges| Self { ranges }))
     }
 }
 
@@ -104,23 +99,35 @@ mod tests {
 
     #[test]
     fn known() {
-        assert_eq!(solve("11-22").0.to_string(), "33");
-        assert_eq!(solve("998-1012").0.to_string(), "1010");
-
-        assert_eq!(solve("11-22").1.to_string(), "33");
-        assert_eq!(solve("95-115").1.to_string(), "210");
-        assert_eq!(solve("998-1012").1.to_string(), "2009");
-        assert_eq!(solve("1188511880-1188511890").1.to_string(), "1188511885");
-        assert_eq!(solve("222220-222224").1.to_string(), "222222");
-        assert_eq!(solve("1698522-1698528").1.to_string(), "0");
-        assert_eq!(solve("446443-446449").1.to_string(), "446446");
-        assert_eq!(solve("38593856-38593862").1.to_string(), "38593859");
-        assert_eq!(solve("565653-565659").1.to_string(), "565656");
-        assert_eq!(solve("824824821-824824827").1.to_string(), "824824824");
-        assert_eq!(solve("2121212118-2121212124").1.to_string(), "2121212121");
+        assert_eq!(solve("11-22").unwrap().0.to_string(), "33");
+        assert_eq!(solve("998-1012").unwrap().0.to_string(), "1010");
+
+        assert_eq!(solve("11-22").unwrap().1.to_string(), "33");
+        assert_eq!(solve("95-115").unwrap().1.to_string(), "210");
+        assert_eq!(solve("998-1012").unwrap().1.to_string(), "2009");
+        assert_eq!(
+            solve("1188511880-1188511890").unwrap().1.to_string(),
+            "1188511885"
+        );
+        assert_eq!(solve("222220-222224").unwrap().1.to_string(), "222222");
+        assert_eq!(solve("1698522-1698528").unwrap().1.to_string(), "0");
+        assert_eq!(solve("446443-446449").unwrap().1.to_string(), "446446");
+        assert_eq!(
+            solve("38593856-38593862").unwrap().1.to_string(),
+            "38593859"
+        );
+        assert_eq!(solve("565653-565659").unwrap().1.to_string(), "565656");
+        assert_eq!(
+            solve("824824821-824824827").unwrap().1.to_string(),
+            "824824824"
+        );
+        assert_eq!(
+            solve("2121212118-2121212124").unwrap().1.to_string(),
+            "2121212121"
+        );
 
         let input = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
-        let (result1, result2) = solve(input);
+        let (result1, result2) = solve(input).unwrap();
         assert_eq!(result1.to_string(), "1227775554");
         assert_eq!(result2.to_string(), "4174379265");
     }
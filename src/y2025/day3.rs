@@ -1,12 +1,9 @@
-use winnow::{
-    combinator::{repeat, separated},
-    error::ContextError,
-    token::one_of,
-    Parser,
-};
+use winnow::Parser;
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+use crate::common::{grid, parse_all, parse_digit, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut part1 = 0_i64;
     let mut part2 = 0_i64;
@@ -46,7 +43,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
             + (*d11 as i64);
     }
 
-    (part1.to_string(), part2.to_string())
+    Ok((part1.to_string(), part2.to_string()))
 }
 
 fn find_max_initial(bank: &[u8], limit: usize) -> (usize, &u8) {
@@ -71,19 +68,8 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Self {
-        separated(
-            1..,
-            repeat::<_, _, Vec<u8>, _, _>(
-                1..,
-                one_of::<_, _, ContextError<&str>>('0'..='9')
-                    .map(|c: char| c.to_digit(10).unwrap() as u8),
-            ),
-            '\n',
-        )
-        .parse(input.trim_ascii_end())
-        .map(|banks| Self { banks })
-        .unwrap()
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        parse_all(input, grid(parse_digit).map(|banks| Self { banks }))
     }
 }
 
@@ -97,7 +83,7 @@ mod tests {
 811111111111119
 234234234234278
 818181911112111";
-        let (result1, result2) = solve(input);
+        let (result1, result2) = solve(input).unwrap();
         assert_eq!(result1.to_string(), "357");
         assert_eq!(result2.to_string(), "3121910778619");
     }
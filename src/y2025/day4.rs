@@ -1,11 +1,8 @@
 use std::collections::HashSet;
 
-use winnow::{
-    combinator::{repeat, separated},
-    error::ContextError,
-    token::one_of,
-    Parser,
-};
+use winnow::{token::one_of, Parser};
+
+use crate::common::{expected, grid, parse_all, ParseError};
 
 const ADJACENT_DIRECTIONS: [(i64, i64); 8] = [
     (-1, -1),
@@ -18,8 +15,8 @@ const ADJACENT_DIRECTIONS: [(i64, i64); 8] = [
     (1, 1),
 ];
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut part1 = 0_i64;
 
@@ -75,7 +72,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
         }
     }
 
-    (part1.to_string(), removed.len().to_string())
+    Ok((part1.to_string(), removed.len().to_string()))
 }
 
 struct Input {
@@ -83,22 +80,15 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Self {
-        separated(
-            1..,
-            repeat::<_, _, Vec<Tile>, _, _>(
-                1..,
-                one_of::<_, _, ContextError<&str>>(['.', '@']).map(|c: char| match c {
-                    '.' => Tile::Empty,
-                    '@' => Tile::Paper,
-                    _ => unreachable!(),
-                }),
-            ),
-            '\n',
-        )
-        .parse(input.trim_ascii_end())
-        .map(|grid| Self { grid })
-        .unwrap()
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let tile = one_of(['.', '@'])
+            .map(|c: char| match c {
+                '.' => Tile::Empty,
+                '@' => Tile::Paper,
+                _ => unreachable!(),
+            })
+            .context(expected("tile"));
+        parse_all(input, grid(tile).map(|grid| Self { grid }))
     }
 }
 
@@ -124,7 +114,7 @@ mod tests {
 @.@@@.@@@@
 .@@@@@@@@.
 @.@.@@@.@.";
-        let (result1, result2) = solve(input);
+        let (result1, result2) = solve(input).unwrap();
         assert_eq!(result1.to_string(), "13");
         assert_eq!(result2.to_string(), "43");
     }
@@ -1,12 +1,13 @@
 use winnow::{
-    ascii::dec_int,
-    combinator::separated,
-    error::{ContextError, ParserError, StrContext},
+    ascii::{dec_int, line_ending},
+    error::ParserError,
     Parser,
 };
 
-pub fn solve(input: &str) -> (impl ToString, impl ToString) {
-    let input = Input::parse(input);
+use crate::common::{lines, parse_all, parse_i64, ParseError};
+
+pub fn solve(input: &str) -> Result<(impl ToString, impl ToString), ParseError> {
+    let input = Input::parse(input)?;
 
     let mut part1 = 0_i64;
 
@@ -45,7 +46,7 @@ pub fn solve(input: &str) -> (impl ToString, impl ToString) {
 
     let part2 = ranges.iter().map(|range| range.len()).sum::<i64>();
 
-    (part1.to_string(), part2.to_string())
+    Ok((part1.to_string(), part2.to_string()))
 }
 
 struct Input {
@@ -54,18 +55,20 @@ struct Input {
 }
 
 impl Input {
-    fn parse(input: &str) -> Self {
-        (
-            separated(1.., Range::parse::<ContextError<_>>, '\n'),
-            "\n\n",
-            separated(1.., dec_int::<_, i64, ContextError<StrContext>>, '\n'),
-        )
-            .parse(input.trim_ascii_end())
-            .map(|(id_ranges, _, ingredient_ids)| Self {
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let sections = (
+            lines(Range::parse),
+            line_ending,
+            line_ending,
+            lines(parse_i64),
+        );
+        parse_all(
+            input,
+            sections.map(|(id_ranges, _, _, ingredient_ids)| Self {
                 id_ranges,
                 ingredient_ids,
-            })
-            .unwrap()
+            }),
+        )
     }
 }
 
@@ -122,7 +125,7 @@ mod tests {
 11
 17
 32";
-        let (result1, result2) = solve(input);
+        let (result1, result2) = solve(input).unwrap();
         assert_eq!(result1.to_string(), "3");
         assert_eq!(result2.to_string(), "14");
     }